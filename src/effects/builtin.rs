@@ -0,0 +1,47 @@
+use effects::Effect;
+use entities::Entity;
+use registry::{self, Parameters, RegistryError};
+
+//name under which `RiskIncrease` is registered by `Registry::with_builtins`
+pub const RISK_INCREASE: &str = "RiskIncrease";
+
+//raises the fire and damage risks of all affected structures, e.g. for droughts or earthquakes
+#[derive(Clone, Debug)]
+pub struct RiskIncrease {
+    fire: u8,
+    damage: u8,
+}
+
+impl RiskIncrease {
+    pub fn new(fire: u8, damage: u8) -> RiskIncrease {
+        RiskIncrease { fire, damage }
+    }
+
+    //both `fire` and `damage` are optional and default to no increase
+    pub fn from_parameters(parameters: &Parameters) -> Result<RiskIncrease, RegistryError> {
+        Ok(RiskIncrease {
+            fire: registry::optional_parameter(parameters, "fire")?.unwrap_or(0),
+            damage: registry::optional_parameter(parameters, "damage")?.unwrap_or(0),
+        })
+    }
+}
+
+impl Effect for RiskIncrease {
+    fn apply(&self, entity: &mut Entity) {
+        if let Entity::Structure { ref mut state, .. } = *entity {
+            state.risk.fire = state.risk.fire.saturating_add(self.fire);
+            state.risk.damage = state.risk.damage.saturating_add(self.damage);
+        }
+    }
+
+    fn type_name(&self) -> &str {
+        RISK_INCREASE
+    }
+
+    fn parameters(&self) -> Parameters {
+        let mut parameters = Parameters::new();
+        parameters.insert("fire".to_owned(), self.fire.to_string());
+        parameters.insert("damage".to_owned(), self.damage.to_string());
+        parameters
+    }
+}
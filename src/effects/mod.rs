@@ -1,5 +1,26 @@
 use entities::Entity;
+use map::Action;
+use registry::Parameters;
+use std::any;
+use uuid::Uuid;
 
-pub trait Effect {
+pub mod builtin;
+
+//effects are shared between cells and threads
+pub trait Effect: Send + Sync {
     fn apply(&self, entity: &mut Entity) -> ();
+
+    //stable name used for looking up the effect in a `Registry`; defaults to the name of the type without its path,
+    //so effects registered under any other name have to override it
+    fn type_name(&self) -> &str {
+        any::type_name::<Self>().rsplit("::").next().unwrap_or_default()
+    }
+
+    //values needed for recreating the effect through a `Registry`
+    fn parameters(&self) -> Parameters {
+        Parameters::new()
+    }
+
+    //requests grid changes that are applied at the end of the current sweep
+    fn enqueue_actions(&self, _entity: &Entity, _id: &Uuid, _at: (usize, usize), _actions: &mut Vec<Action>) {}
 }
@@ -5,14 +5,34 @@ pub mod resource;
 pub mod doodad;
 pub mod walker;
 
-#[derive(Debug)]
+//lifecycle of an entity on the grid; destroyed entities stay on the grid but no longer take part in the simulation,
+//deleted ones are removed at the end of the sweep
+#[derive(Eq, PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
 pub enum State {
+    #[default]
     Active,
     Affected,
     Destroyed,
     Deleted,
 }
 
+impl State {
+    pub fn can_become(&self, next: &State) -> bool {
+        match (self, next) {
+            (&State::Active, &State::Affected) | (&State::Affected, &State::Active) => true,
+            (&State::Active, &State::Destroyed) | (&State::Affected, &State::Destroyed) => true,
+            (&State::Deleted, _) => false,
+            (_, &State::Deleted) => true,
+            _ => false
+        }
+    }
+
+    //checks if the entity still produces, moves and spawns walkers
+    pub fn is_operational(&self) -> bool {
+        *self == State::Active || *self == State::Affected
+    }
+}
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum EntityType {
     Road,
@@ -34,13 +54,13 @@ pub enum Entity {
     Resource {
         props: resource::ResourceProperties,
         state: resource::ResourceState,
-        producer: Option<Box<Producer>>,
+        producer: Option<Box<dyn Producer>>,
     },
 
     Structure {
         props: structure::StructureProperties,
         state: structure::StructureState,
-        producer: Option<Box<Producer>>,
+        producer: Option<Box<dyn Producer>>,
     },
 
     Walker {
@@ -48,3 +68,16 @@ pub enum Entity {
         state: walker::WalkerState,
     },
 }
+
+impl Entity {
+    pub fn entity_type(&self) -> EntityType {
+        match *self {
+            Entity::Road => EntityType::Road,
+            Entity::Roadblock => EntityType::Roadblock,
+            Entity::Doodad { .. } => EntityType::Doodad,
+            Entity::Resource { .. } => EntityType::Resource,
+            Entity::Structure { .. } => EntityType::Structure,
+            Entity::Walker { .. } => EntityType::Walker,
+        }
+    }
+}
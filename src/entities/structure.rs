@@ -1,12 +1,13 @@
+use std::cmp;
 use std::collections::HashMap;
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct Risk {
     pub fire: u8,
     pub damage: u8,
 }
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub enum Type {
     Housing,
     Entertainment,
@@ -17,27 +18,186 @@ pub enum Type {
     Education,
     HealthCare,
     CivilService,
+    Engineering,
 }
 
-#[derive(PartialEq, Clone, Debug)]
+//services provided to nearby structures by the patrol walkers of a structure;
+//fire and maintenance services also reset the fire and damage risks of the structures they reach
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum Service {
+    Fire,
+    Maintenance,
+    Health,
+    Religion,
+    Education,
+    Entertainment,
+    CivilService,
+}
+
+//coverage level of a service right after one of its walkers passed by
+pub const MAX_COVERAGE: u8 = 100;
+
+//levels of access to each service; they go down a little at the end of every sweep
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Coverage {
+    pub fire: u8,
+    pub maintenance: u8,
+    pub health: u8,
+    pub religion: u8,
+    pub education: u8,
+    pub entertainment: u8,
+    pub civil_service: u8,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct Size {
     pub width: u8,
     pub height: u8,
 }
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct StructureProperties {
     pub name: String,
     pub size: Size,
     pub max_employees: u8,
+    //number of people that can live in the structure; only used by housing
+    #[serde(default)]
+    pub max_residents: u8,
     pub cost: u32,
+    //(base, step, step size, range, min, max)
+    //- `base` is applied to the cells of the structure and to all cells next to it
+    //- for every `step` cells further away, `step size` is added to the value
+    //- cells further away than `range` are not affected
+    //- the value is always kept between `min` and `max`
     pub desirability: (i8, i8, i8, i8, i8, i8),
     pub structure_type: Type,
+    //multi-cell structures are normally only placed on level ground
+    #[serde(default)]
+    pub allows_uneven_ground: bool,
 }
 
-#[derive(PartialEq, Clone, Debug)]
+impl StructureProperties {
+    //calculates the desirability the structure contributes to a cell that is `distance` cells away from it
+    pub fn desirability_at(&self, distance: usize) -> i8 {
+        let (base, step, step_size, range, min, max) = self.desirability;
+
+        if range < 0 || distance > range as usize {
+            0
+        } else {
+            let steps = if step > 0 { distance.saturating_sub(1) / step as usize } else { 0 };
+            let value = i64::from(base) + steps as i64 * i64::from(step_size);
+
+            cmp::min(cmp::max(value, i64::from(min)), i64::from(max)) as i8
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct StructureState {
     pub current_employees: u8,
+    #[serde(default)]
+    pub current_residents: u8,
+    //work done by the employees of an understaffed structure towards its next production cycle
+    #[serde(default)]
+    pub shift_progress: u8,
     pub risk: Risk,
+    #[serde(default)]
+    pub coverage: Coverage,
+    #[serde(serialize_with = "::persistence::serialize_ordered")]
     pub commodities: HashMap<String, u32>,
 }
+
+impl Type {
+    //the service provided by walkers of the structure type, if any; military structures keep watch for fires
+    pub fn service(&self) -> Option<Service> {
+        match *self {
+            Type::Military => Some(Service::Fire),
+            Type::Engineering => Some(Service::Maintenance),
+            Type::HealthCare => Some(Service::Health),
+            Type::Religion => Some(Service::Religion),
+            Type::Education => Some(Service::Education),
+            Type::Entertainment => Some(Service::Entertainment),
+            Type::CivilService => Some(Service::CivilService),
+            _ => None
+        }
+    }
+
+    //the order in which structures of the type are staffed when there are not enough workers; higher goes first
+    pub fn labour_priority(&self) -> u8 {
+        match *self {
+            Type::Military | Type::Engineering => 4,
+            Type::HealthCare | Type::CivilService => 3,
+            Type::Industry => 2,
+            Type::Housing => 0,
+            _ => 1,
+        }
+    }
+
+    //the fire and damage risk a structure of the type gains at the end of every sweep
+    pub fn risk_increase(&self) -> Risk {
+        match *self {
+            Type::Housing => Risk { fire: 2, damage: 1 },
+            Type::Industry => Risk { fire: 3, damage: 2 },
+            Type::Military | Type::Monument | Type::Engineering => Risk { fire: 0, damage: 1 },
+            _ => Risk { fire: 1, damage: 1 },
+        }
+    }
+}
+
+impl Coverage {
+    pub fn level(&self, service: &Service) -> u8 {
+        match *service {
+            Service::Fire => self.fire,
+            Service::Maintenance => self.maintenance,
+            Service::Health => self.health,
+            Service::Religion => self.religion,
+            Service::Education => self.education,
+            Service::Entertainment => self.entertainment,
+            Service::CivilService => self.civil_service,
+        }
+    }
+
+    pub fn refresh(&mut self, service: &Service) {
+        let level = match *service {
+            Service::Fire => &mut self.fire,
+            Service::Maintenance => &mut self.maintenance,
+            Service::Health => &mut self.health,
+            Service::Religion => &mut self.religion,
+            Service::Education => &mut self.education,
+            Service::Entertainment => &mut self.entertainment,
+            Service::CivilService => &mut self.civil_service,
+        };
+
+        *level = MAX_COVERAGE;
+    }
+
+    pub fn decay(&mut self, amount: u8) {
+        for level in &mut [
+            &mut self.fire,
+            &mut self.maintenance,
+            &mut self.health,
+            &mut self.religion,
+            &mut self.education,
+            &mut self.entertainment,
+            &mut self.civil_service,
+        ] {
+            **level = level.saturating_sub(amount);
+        }
+    }
+
+    //checks if all coverage levels are at least as high as the required ones
+    pub fn satisfies(&self, required: &Coverage) -> bool {
+        self.fire >= required.fire
+            && self.maintenance >= required.maintenance
+            && self.health >= required.health
+            && self.religion >= required.religion
+            && self.education >= required.education
+            && self.entertainment >= required.entertainment
+            && self.civil_service >= required.civil_service
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Coverage::default()
+    }
+}
@@ -1,14 +1,47 @@
-use std::collections::HashMap;
+use map::TraversalType;
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct WalkerProperties {
     pub name: String,
+    //number of cells walked along roads before returning to the origin structure
     pub patrol: Option<u8>,
     pub max_life: Option<u16>,
+    pub movement_speed: u8,
+    pub traversal: TraversalType,
 }
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct WalkerState {
+    #[serde(serialize_with = "::persistence::serialize_ordered")]
     pub commodities: HashMap<String, u32>,
     pub current_life: Option<u16>,
+    pub destination: Option<(usize, usize)>,
+    //the structure that spawned the walker
+    #[serde(default)]
+    pub origin: Option<Uuid>,
+    #[serde(default)]
+    pub patrol: Option<PatrolState>,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum PatrolStage {
+    Outbound,
+    Returning,
+}
+
+//progress of a walker wandering along roads; it returns to the cell it started in once it has walked far enough
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct PatrolState {
+    pub start: (usize, usize),
+    pub stage: PatrolStage,
+    pub distance: u8,
+    pub previous: Option<(usize, usize)>,
+    //state of the generator choosing between roads at junctions
+    pub seed: u64,
+    //all cells entered, in order
+    pub cells: Vec<(usize, usize)>,
+    //all structures next to the entered cells
+    pub structures: BTreeSet<Uuid>,
 }
@@ -2,9 +2,18 @@
 extern crate ndarray;
 extern crate uuid;
 extern crate pathfinding;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate bincode;
+extern crate toml;
 
 pub mod map;
 pub mod effects;
 pub mod entities;
 pub mod production;
 pub mod movement;
+pub mod registry;
+pub mod persistence;
+pub mod scenario;
@@ -1,5 +1,158 @@
 extern crate owe;
 
+use owe::entities::Entity;
+use owe::map::{Cursor, Grid, LifecycleEvent};
+use owe::production::exchange::CommodityExchange;
+use owe::registry::Registry;
+use owe::scenario::Scenario;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::process;
+
+const USAGE: &str = "usage: owebin run <scenario.toml> [sweeps]";
+
+fn registry() -> Registry {
+    //producers and effects referenced by scenarios are registered here
+    Registry::with_builtins()
+}
+
+fn load_scenario(path: &str) -> Result<Scenario, String> {
+    let mut data = String::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut data))
+        .map_err(|e| format!("failed to read scenario [{}]: {}", path, e))?;
+
+    Scenario::from_toml(&data).map_err(|e| format!("failed to parse scenario [{}]: {:?}", path, e))
+}
+
+fn print_summary(scenario: &Scenario, sweeps: usize, grid: &Grid, cursor: &Cursor, exchange: &CommodityExchange, events: &[(usize, LifecycleEvent)]) {
+    let mut entities = BTreeMap::new();
+    let mut structures = Vec::new();
+    let mut stored: BTreeMap<String, u32> = BTreeMap::new();
+    let mut carried: BTreeMap<String, u32> = BTreeMap::new();
+
+    //the grid holds the current state of every entity, so commodities are counted from it
+    for (id, entity) in grid.entities_by_id() {
+        let entity_type = match *entity {
+            Entity::Road => "roads",
+            Entity::Roadblock => "roadblocks",
+            Entity::Doodad { .. } => "doodads",
+            Entity::Resource { .. } => "resources",
+            Entity::Structure { .. } => "structures",
+            Entity::Walker { .. } => "walkers",
+        };
+
+        *entities.entry(entity_type).or_insert(0) += 1;
+
+        match *entity {
+            Entity::Structure { ref props, ref state, .. } => {
+                let commodities: BTreeMap<&String, &u32> = state.commodities.iter().filter(|&(_, amount)| *amount > 0).collect();
+
+                for (name, amount) in &commodities {
+                    *stored.entry((*name).clone()).or_insert(0) += **amount;
+                }
+
+                let commodities: Vec<String> = commodities.into_iter().map(|(name, amount)| format!("{} {}", name, amount)).collect();
+                structures.push((props.name.clone(), id, commodities.join(", ")));
+            }
+
+            Entity::Walker { ref state, .. } => {
+                for (name, amount) in &state.commodities {
+                    *carried.entry(name.clone()).or_insert(0) += *amount;
+                }
+            }
+
+            _ => ()
+        }
+    }
+
+    structures.sort();
+
+    println!("scenario:  {}", scenario.name);
+    println!("grid:      {}x{}", grid.width(), grid.height());
+    println!("sweeps:    {}", sweeps);
+    println!("cursor:    {:?}", cursor.position());
+
+    println!("entities:");
+    for (entity_type, count) in entities {
+        println!("  {:<12}{}", entity_type, count);
+    }
+
+    println!("structures:");
+    for (name, id, commodities) in structures {
+        println!("  {:<12}{}  {}", name, id, commodities);
+    }
+
+    let mut commodities = exchange.commodities();
+    commodities.extend(stored.keys().chain(carried.keys()).cloned());
+    commodities.sort();
+    commodities.dedup();
+
+    println!("commodities: (stored / carried / used / lost)");
+    for commodity in commodities {
+        println!(
+            "  {:<12}{} / {} / {} / {}",
+            commodity,
+            stored.get(&commodity).unwrap_or(&0),
+            carried.get(&commodity).unwrap_or(&0),
+            exchange.amount_used_of(&commodity),
+            exchange.amount_lost_of(&commodity)
+        );
+    }
+
+    println!("lifecycle events:");
+    for &(sweep, ref event) in events {
+        println!("  sweep {:<6}{} at {:?}: {:?} -> {:?}", sweep, event.entity, event.at, event.from, event.to);
+    }
+}
+
+fn run(path: &str, sweeps: Option<usize>) -> Result<(), String> {
+    let scenario = load_scenario(path)?;
+    let sweeps = sweeps.unwrap_or(scenario.sweeps);
+
+    let (mut grid, mut cursor, mut exchange) = scenario.build(&registry())
+        .map_err(|e| format!("failed to build scenario [{}]: {:?}", path, e))?;
+
+    let mut events = Vec::new();
+
+    for sweep in 0..sweeps {
+        cursor.process_sweep(&mut grid, &mut exchange)
+            .map_err(|e| format!("failed to process sweep [{}]: {:?}", sweep, e))?;
+
+        for result in cursor.take_action_results() {
+            if let Err(e) = result {
+                eprintln!("action failed during sweep [{}]: {:?}", sweep, e);
+            }
+        }
+
+        events.extend(grid.take_lifecycle_events().into_iter().map(|event| (sweep, event)));
+    }
+
+    print_summary(&scenario, sweeps, &grid, &cursor, &exchange, &events);
+
+    Ok(())
+}
+
 fn main() {
-    println!("Nothing to see here, move along!");
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.iter().map(|arg| arg.as_str()).collect::<Vec<_>>().as_slice() {
+        ["run", path] => run(path, None),
+
+        ["run", path, sweeps] => {
+            sweeps.parse()
+                .map_err(|_| format!("invalid number of sweeps [{}]", sweeps))
+                .and_then(|sweeps| run(path, Some(sweeps)))
+        }
+
+        _ => Err(USAGE.to_owned())
+    };
+
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
 }
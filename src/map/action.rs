@@ -0,0 +1,196 @@
+use entities::{Entity, State};
+use map::{Action, ActionError, Grid, GridError};
+use production::Commodity;
+use production::exchange::{CommodityExchange, CommodityState, ExchangeError};
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl Grid {
+    pub fn enqueue_action(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    pub fn pending_actions(&self) -> usize {
+        self.actions.len()
+    }
+
+    //applies all queued actions, in the order they were added; actions queued while processing are left for the next call
+    pub fn process_actions(&mut self, exchange: &mut CommodityExchange) -> Vec<Result<(), ActionError>> {
+        let actions = mem::take(&mut self.actions);
+
+        actions.into_iter()
+            .map(|action| self.process_action(action, exchange))
+            .collect()
+    }
+
+    fn process_action(&mut self, action: Action, exchange: &mut CommodityExchange) -> Result<(), ActionError> {
+        match action {
+            Action::AddEntity { at, entity } => {
+                self.add_entity(at, entity).map(|_| ()).map_err(|e| ActionError::ForGrid { e })
+            }
+
+            Action::RemoveEntity { at, id } => {
+                self.remove_entity(at, &id).map(|_| ()).map_err(|e| ActionError::ForGrid { e })
+            }
+
+            Action::AddCellEffect { at, effect } => {
+                self.add_cell_effect(at, effect).map(|_| ()).map_err(|e| ActionError::ForGrid { e })
+            }
+
+            Action::RemoveCellEffect { at, effect } => {
+                self.remove_cell_effect(at, &effect).map(|_| ()).map_err(|e| ActionError::ForGrid { e })
+            }
+
+            Action::AddGlobalEffect { effect } => {
+                self.add_global_effect(effect).map_err(|e| ActionError::ForGrid { e })
+            }
+
+            Action::RemoveGlobalEffect { effect } => {
+                self.remove_global_effect(&effect).map_err(|e| ActionError::ForGrid { e })
+            }
+
+            Action::MoveWalker { from, id, to } => {
+                self.move_entity(from, &id, to).map(|_| ()).map_err(|e| ActionError::ForGrid { e })
+            }
+
+            Action::TransferCommodity { from, to, commodity } => {
+                self.transfer_commodity(from, to, &commodity, exchange)
+            }
+
+            Action::DeliverCommodity { from, to, commodity } => {
+                match self.structure_in(to) {
+                    Some(target) => self.transfer_commodity(from, (to, target), &commodity, exchange),
+                    None => Err(ActionError::ForGrid { e: GridError::EntityMissing })
+                }
+            }
+
+            Action::DestroyStructure { at, id } => {
+                self.destroy_structure(at, &id, exchange)
+            }
+
+            Action::SetEntityState { at, id, state } => {
+                self.set_entity_state(at, &id, state).map_err(|e| ActionError::ForGrid { e })
+            }
+        }
+    }
+
+    fn commodities_of(entity: &mut Entity) -> Option<&mut HashMap<String, u32>> {
+        match *entity {
+            Entity::Structure { ref mut state, .. } => Some(&mut state.commodities),
+            Entity::Walker { ref mut state, .. } => Some(&mut state.commodities),
+            _ => None
+        }
+    }
+
+    fn structure_in(&self, at: (usize, usize)) -> Option<Uuid> {
+        self.cells.get(at)?
+            .entities.iter()
+            .find(|&(_, grid_entity)| matches!(*grid_entity.entity, Entity::Structure { .. }))
+            .map(|(id, _)| *id)
+    }
+
+    fn transfer_commodity(
+        &mut self,
+        (source_cell, source_id): ((usize, usize), Uuid),
+        (target_cell, target_id): ((usize, usize), Uuid),
+        commodity: &Commodity,
+        exchange: &mut CommodityExchange,
+    ) -> Result<(), ActionError> {
+        if source_id == target_id {
+            return Ok(()); //nothing to transfer
+        }
+
+        let source = self.entity(source_cell, &source_id);
+        let target = self.entity(target_cell, &target_id);
+
+        match (source, target) {
+            (Some(source), Some(target)) => {
+                //structures can only deliver to each other through the road network
+                if let (&Entity::Structure { .. }, &Entity::Structure { .. }) = (&*source, &*target) {
+                    if !self.is_reachable_by_road(&source_id, &target_id) {
+                        return Err(ActionError::ForGrid { e: GridError::RoadUnreachable });
+                    }
+                }
+
+                let mut updated_source = (*source).clone();
+                let mut updated_target = (*target).clone();
+
+                let (source_amount, target_amount) = match (Self::commodities_of(&mut updated_source), Self::commodities_of(&mut updated_target)) {
+                    (Some(source_commodities), Some(target_commodities)) => {
+                        let stored = source_commodities.entry(commodity.name.clone()).or_insert(0);
+
+                        if *stored < commodity.amount {
+                            return Err(ActionError::ForGrid { e: GridError::CommodityUnavailable });
+                        }
+
+                        *stored -= commodity.amount;
+
+                        let received = target_commodities.entry(commodity.name.clone()).or_insert(0);
+                        *received += commodity.amount;
+
+                        (*stored, *received)
+                    }
+
+                    _ => return Err(ActionError::ForGrid { e: GridError::UnexpectedEntity })
+                };
+
+                let updated_source = Arc::new(updated_source);
+                let updated_target = Arc::new(updated_target);
+
+                self.replace_entity(source_cell, &source_id, updated_source.clone());
+                self.replace_entity(target_cell, &target_id, updated_target.clone());
+
+                let results: Vec<Result<(), ExchangeError>> = vec![(updated_source, source_id, source_amount), (updated_target, target_id, target_amount)]
+                    .into_iter()
+                    .map(|(entity, id, amount)| {
+                        let state = match *entity {
+                            Entity::Walker { .. } => CommodityState::InTransit,
+                            _ => CommodityState::Available
+                        };
+
+                        exchange.update_state(entity.clone(), &id, &Commodity { name: commodity.name.clone(), amount }, state)
+                    })
+                    .collect();
+
+                //both sides are updated even if one of them fails; the first failure is reported
+                results.into_iter()
+                    .collect::<Result<(), ExchangeError>>()
+                    .map_err(|e| ActionError::ForExchange { e })
+            }
+
+            _ => Err(ActionError::ForGrid { e: GridError::EntityMissing })
+        }
+    }
+
+    fn destroy_structure(&mut self, at: (usize, usize), id: &Uuid, exchange: &mut CommodityExchange) -> Result<(), ActionError> {
+        let entity = self.entity(at, id).ok_or(ActionError::ForGrid { e: GridError::EntityMissing })?;
+
+        let stored = match *entity {
+            Entity::Structure { ref state, .. } => state.commodities.clone(),
+            _ => return Err(ActionError::ForGrid { e: GridError::UnexpectedEntity })
+        };
+
+        let parent = self.locate(id).map(|location| location.parent).unwrap_or(at);
+
+        //the structure goes through the rest of its lifecycle and is purged right away to free its cells
+        self.set_entity_state(at, id, State::Destroyed)
+            .and_then(|_| self.set_entity_state(at, id, State::Deleted))
+            .map_err(|e| ActionError::ForGrid { e })?;
+
+        //all commodities stored in a destroyed structure are lost
+        let results: Vec<Result<(), ExchangeError>> = stored.into_iter()
+            .filter(|&(_, amount)| amount > 0)
+            .map(|(name, amount)| {
+                exchange.update_state(entity.clone(), id, &Commodity { name, amount }, CommodityState::Lost)
+            })
+            .collect();
+
+        self.purge_entity(parent, id, exchange);
+
+        results.into_iter()
+            .collect::<Result<(), ExchangeError>>()
+            .map_err(|e| ActionError::ForExchange { e })
+    }
+}
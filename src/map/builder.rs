@@ -0,0 +1,72 @@
+use effects::Effect;
+use entities::Entity;
+use map::{Grid, GridBuilder, GridError, IdGenerator, Terrain};
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl GridBuilder {
+    pub fn new(width: usize, height: usize) -> GridBuilder {
+        GridBuilder {
+            width,
+            height,
+            id_generator: IdGenerator::Random,
+            global_effects: Vec::new(),
+            terrain: Vec::new(),
+            elevation: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    pub fn with_id_generator(mut self, id_generator: IdGenerator) -> GridBuilder {
+        self.id_generator = id_generator;
+        self
+    }
+
+    pub fn with_global_effect(mut self, effect: Arc<dyn Effect>) -> GridBuilder {
+        self.global_effects.push(effect);
+        self
+    }
+
+    pub fn with_terrain(mut self, at: (usize, usize), terrain: Terrain) -> GridBuilder {
+        self.terrain.push((at, terrain));
+        self
+    }
+
+    pub fn with_elevation(mut self, at: (usize, usize), elevation: u8) -> GridBuilder {
+        self.elevation.push((at, elevation));
+        self
+    }
+
+    pub fn with_entity(mut self, at: (usize, usize), entity: Entity) -> GridBuilder {
+        self.entities.push((at, entity));
+        self
+    }
+
+    //creates the grid, sets the terrain and elevation and places all entities, in the order they were added;
+    //the IDs of the placed entities are returned in the same order
+    pub fn build(self) -> Result<(Grid, Vec<Uuid>), GridError> {
+        let mut grid = Grid::with_dimensions(self.width, self.height);
+        grid.set_id_generator(self.id_generator);
+
+        for effect in self.global_effects {
+            grid.add_global_effect(effect)?;
+        }
+
+        for (at, terrain) in self.terrain {
+            grid.set_terrain(at, terrain)?;
+        }
+
+        for (at, elevation) in self.elevation {
+            grid.set_elevation(at, elevation)?;
+        }
+
+        let mut ids = Vec::with_capacity(self.entities.len());
+
+        for (at, entity) in self.entities {
+            let (id, _) = grid.add_entity(at, entity)?;
+            ids.push(id);
+        }
+
+        Ok((grid, ids))
+    }
+}
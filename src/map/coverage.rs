@@ -0,0 +1,101 @@
+use entities::Entity;
+use entities::structure::Service;
+use map::Grid;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+//amount by which all coverage levels go down at the end of every sweep
+pub const COVERAGE_DECAY: u8 = 1;
+
+impl Grid {
+    //the highest coverage level of the service among the structures in the cell; `None` if there are no structures in it
+    pub fn coverage_at(&self, cell: (usize, usize), service: &Service) -> Option<u8> {
+        self.cells.get(cell).and_then(|cell_data| {
+            cell_data.entities.values()
+                .filter_map(|grid_entity| {
+                    match *grid_entity.entity {
+                        Entity::Structure { ref state, .. } => Some(state.coverage.level(service)),
+                        _ => None
+                    }
+                })
+                .max()
+        })
+    }
+
+    //coverage levels of the service for all cells, row by row
+    pub fn coverage_heatmap(&self, service: &Service) -> Vec<Option<u8>> {
+        let mut heatmap = Vec::with_capacity(self.width * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                heatmap.push(self.coverage_at((x, y), service));
+            }
+        }
+
+        heatmap
+    }
+
+    //the service provided by the walkers of a structure
+    pub(super) fn service_of(&self, structure_id: &Uuid) -> Option<Service> {
+        self.entity_by_id(structure_id).and_then(|entity| {
+            match *entity {
+                Entity::Structure { ref props, .. } => props.structure_type.service(),
+                _ => None
+            }
+        })
+    }
+
+    pub(super) fn refresh_coverage(&mut self, structures: &BTreeSet<Uuid>, service: &Service) {
+        for id in structures {
+            let parent = match self.locate(id) {
+                Some(location) => location.parent,
+                None => continue
+            };
+
+            if let Some(entity) = self.entity(parent, id) {
+                let mut updated_entity = (*entity).clone();
+
+                if let Entity::Structure { ref mut state, .. } = updated_entity {
+                    state.coverage.refresh(service);
+
+                    match *service {
+                        Service::Fire => state.risk.fire = 0,
+                        Service::Maintenance => state.risk.damage = 0,
+                        _ => ()
+                    }
+                }
+
+                self.replace_entity(parent, id, Arc::new(updated_entity));
+            }
+        }
+    }
+
+    //lowers the coverage levels of all structures that have any coverage left
+    pub(super) fn decay_coverage(&mut self) {
+        let covered: Vec<((usize, usize), Uuid)> = self.cells.indexed_iter()
+            .flat_map(|(index, cell)| {
+                cell.entities.iter()
+                    .filter(move |&(_, grid_entity)| {
+                        match *grid_entity.entity {
+                            Entity::Structure { ref state, .. } => grid_entity.parent == index && !state.coverage.is_empty(),
+                            _ => false
+                        }
+                    })
+                    .map(move |(id, _)| (index, *id))
+            })
+            .collect();
+
+        for (parent, id) in covered {
+            if let Some(entity) = self.entity(parent, &id) {
+                let mut updated_entity = (*entity).clone();
+
+                if let Entity::Structure { ref mut state, .. } = updated_entity {
+                    state.coverage.decay(COVERAGE_DECAY);
+                }
+
+                self.replace_entity(parent, &id, Arc::new(updated_entity));
+            }
+        }
+    }
+}
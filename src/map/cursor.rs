@@ -1,7 +1,19 @@
 use entities::Entity;
-use map::{Cell, Cursor, CursorError, Direction, Grid};
-use production::exchange::{CommodityExchange, CommodityState};
-use std::rc::Rc;
+use entities::walker::WalkerState;
+use effects::Effect;
+use map::{Action, ActionError, Cell, Cursor, CursorError, Direction, FinishedPatrol, Grid, GridEntity, GridError, SpawnedWalker};
+use ndarray::{ArrayViewMut2, Axis};
+use production::{Commodity, WalkerStage};
+use production::exchange::{CommodityExchange, CommodityState, ExchangeError};
+use std::cmp;
+use std::collections::HashMap;
+use std::mem;
+use std::sync::Arc;
+use std::thread;
+use uuid::Uuid;
+
+//an entity, its ID and the state of one of its commodities, to be passed to the exchange
+pub(super) type ExchangeUpdate = (Arc<Entity>, Uuid, Commodity, CommodityState);
 
 impl Cursor {
     pub fn new(range: usize, direction: Direction, start: (usize, usize)) -> Cursor {
@@ -9,6 +21,10 @@ impl Cursor {
             cell: start,
             direction,
             range,
+            threads: 1,
+            spawned_walkers: Vec::new(),
+            finished_patrols: Vec::new(),
+            action_results: Vec::new(),
         }
     }
 
@@ -16,7 +32,29 @@ impl Cursor {
         (self.cell.0, self.cell.1)
     }
 
-    fn calculate_next_cell(cell_x: isize, cell_y: isize, grid_width: isize, grid_height: isize, direction: &Direction) -> (usize, usize) {
+    //sets the number of threads used for processing sweeps started at the first cell of the grid and for applying
+    //global effects; `process_and_advance` always processes its cell on the calling thread;
+    //the results are the same for any number of threads
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = cmp::max(threads, 1);
+    }
+
+    //returns all walkers spawned since the last call
+    pub fn take_spawned_walkers(&mut self) -> Vec<SpawnedWalker> {
+        mem::take(&mut self.spawned_walkers)
+    }
+
+    //returns all patrols finished since the last call
+    pub fn take_finished_patrols(&mut self) -> Vec<FinishedPatrol> {
+        mem::take(&mut self.finished_patrols)
+    }
+
+    //returns the results of all queued actions processed since the last call
+    pub fn take_action_results(&mut self) -> Vec<Result<(), ActionError>> {
+        mem::take(&mut self.action_results)
+    }
+
+    pub(super) fn calculate_next_cell(cell_x: isize, cell_y: isize, grid_width: isize, grid_height: isize, direction: &Direction) -> (usize, usize) {
         let (next_cell_x, next_cell_y) = match direction {
             //cursor moves up and left
             &Direction::Up => {
@@ -118,6 +156,332 @@ impl Cursor {
         (next_cell_x as usize, next_cell_y as usize)
     }
 
+    //moves all walkers that have a destination along their path to it
+    fn process_movement(grid: &mut Grid) {
+        let walkers = grid.cells.indexed_iter()
+            .fold(vec![], |mut acc, (index, cell)| {
+                cell.entities.iter().filter(|&(_, grid_entity)| grid_entity.state.is_operational()).for_each(|(id, grid_entity)| {
+                    if let Entity::Walker { ref props, ref state } = *grid_entity.entity {
+                        if let Some(destination) = state.destination {
+                            acc.push((index, *id, grid_entity.entity.clone(), destination, props.movement_speed));
+                        }
+                    }
+                });
+
+                acc
+            });
+
+        for (cell, id, walker, destination, speed) in walkers {
+            let next_cell = if cell == destination {
+                Some(cell)
+            } else {
+                grid.walker_path(cell, destination, &*walker).map(|(path, _)| {
+                    path[cmp::min(speed as usize, path.len() - 1)]
+                })
+            };
+
+            //a walker without a path stays in place and tries again after the next sweep
+            if let Some(next_cell) = next_cell {
+                let current_cell = if next_cell != cell && grid.move_entity(cell, &id, next_cell).is_ok() {
+                    next_cell
+                } else {
+                    cell
+                };
+
+                if current_cell == destination {
+                    if let Some(grid_entity) = grid.cells[current_cell].entities.get_mut(&id) {
+                        let mut updated_entity = (*grid_entity.entity).clone();
+
+                        if let Entity::Walker { ref mut state, .. } = updated_entity {
+                            state.destination = None;
+                        }
+
+                        grid_entity.replace_entity(updated_entity);
+                    }
+                }
+            }
+        }
+    }
+
+    //applies the effect to a single entity, replacing it with its updated copy
+    pub(super) fn apply_effect(effect: &Arc<dyn Effect>, id: &Uuid, grid_entity: &mut GridEntity, actions: &mut Vec<Action>) {
+        let mut updated_entity = (*grid_entity.entity).clone();
+        effect.apply(&mut updated_entity);
+        effect.enqueue_actions(&updated_entity, id, grid_entity.parent, actions);
+        grid_entity.replace_entity(updated_entity);
+    }
+
+    //runs the production of a single entity, replacing it with its updated copy; the exchange updates
+    //for the copy and the walker it wants to spawn are returned
+    pub(super) fn produce(
+        at: (usize, usize),
+        id: &Uuid,
+        grid_entity: &mut GridEntity,
+        is_output_staffed: bool,
+        actions: &mut Vec<Action>,
+    ) -> (Vec<(Commodity, CommodityState)>, Option<WalkerStage>) {
+        //destroyed and deleted entities no longer produce anything
+        if !grid_entity.state.is_operational() {
+            return (Vec::new(), None);
+        }
+
+        //structures covering several cells are staffed and produce only once per sweep, at their parent cell
+        if let Entity::Structure { .. } = *grid_entity.entity {
+            if grid_entity.parent != at {
+                return (Vec::new(), None);
+            }
+        }
+
+        let mut updated_entity = (*grid_entity.entity).clone();
+        let mut walker_stage = None;
+
+        let exchange_updates = match updated_entity {
+            Entity::Resource { ref props, ref mut producer, ref mut state, .. } => {
+                producer.as_mut()
+                    .and_then(|p| {
+                        let exchange_update = p.produce_commodity(&*grid_entity.entity)
+                            .map(|stage| {
+                                if state.current_amount >= stage.commodity.amount {
+                                    state.current_amount -= stage.commodity.amount;
+                                } else {
+                                    state.current_amount = 0;
+                                }
+
+                                vec![(stage.commodity, CommodityState::Available)]
+                            });
+
+                        props.replenish_amount
+                            .map(|amount| {
+                                if state.current_amount + amount < props.max_amount {
+                                    state.current_amount += amount;
+                                } else {
+                                    state.current_amount = props.max_amount;
+                                }
+                            });
+
+                        p.enqueue_actions(&grid_entity.entity, id, grid_entity.parent, actions);
+
+                        exchange_update
+                    })
+            }
+
+            Entity::Structure { ref props, ref mut producer, ref mut state, .. } => {
+                //understaffed structures collect the work of their employees over several visits and only run
+                //a production cycle (using inputs, producing outputs and walkers) once it adds up to a full one
+                let is_staffed = if is_output_staffed && props.max_employees > 0 {
+                    let work = u16::from(state.shift_progress) + u16::from(state.current_employees);
+                    let full_shift = u16::from(props.max_employees);
+
+                    if work >= full_shift {
+                        state.shift_progress = ((work - full_shift) % full_shift) as u8;
+                        true
+                    } else {
+                        state.shift_progress = work as u8;
+                        false
+                    }
+                } else {
+                    true
+                };
+
+                producer.as_mut()
+                    .filter(|_| is_staffed)
+                    .and_then(|p| {
+                        let exchange_update = p.produce_commodity(&*grid_entity.entity)
+                            .map(|stage| {
+                                let existing = state.commodities
+                                    .entry(stage.commodity.name.clone())
+                                    .or_insert(0);
+
+                                *existing += stage.commodity.amount;
+
+                                let mut updates = stage.required.into_iter()
+                                    .map(|c| (c, CommodityState::Required)).collect::<Vec<_>>();
+
+                                updates.extend(stage.used.into_iter()
+                                    .map(|c| (c, CommodityState::Used)).collect::<Vec<_>>());
+
+                                updates.push((stage.commodity, CommodityState::Available));
+
+                                updates
+                            });
+
+                        //TODO - add walker effects to grid
+                        walker_stage = p.produce_walker(&grid_entity.entity);
+
+                        p.enqueue_actions(&grid_entity.entity, id, grid_entity.parent, actions);
+
+                        exchange_update
+                    })
+            }
+
+            Entity::Walker { ref state, .. } => {
+                //TODO - update state
+                //TODO - process interaction with nearby entities
+                //       (work, attack, get/leave commodities)
+
+                //keeps the exchange up-to-date with the commodities the walker is carrying
+                Some(
+                    state.commodities.iter()
+                        .filter(|&(_, amount)| *amount > 0)
+                        .map(|(name, amount)| {
+                            (Commodity { name: name.clone(), amount: *amount }, CommodityState::InTransit)
+                        })
+                        .collect()
+                )
+            }
+
+            _ => None //do nothing
+        }.unwrap_or(Vec::new());
+
+        grid_entity.replace_entity(updated_entity);
+
+        (exchange_updates, walker_stage)
+    }
+
+    //applies the effects to all entities in the supplied cells; the enqueued actions are returned per effect
+    fn apply_effects(mut cells: ArrayViewMut2<Cell>, effects: &[Arc<dyn Effect>]) -> Vec<Vec<Action>> {
+        effects.iter()
+            .map(|effect| {
+                let mut actions = Vec::new();
+
+                for affected_cell in cells.iter_mut() {
+                    affected_cell.entities.iter_mut().for_each(|(id, grid_entity)| {
+                        Self::apply_effect(effect, id, grid_entity, &mut actions);
+                    });
+                }
+
+                actions
+            })
+            .collect()
+    }
+
+    //applies global effects; with more than one thread, the grid is split into chunks of columns that are
+    //processed concurrently and the enqueued actions are merged in the order of a sequential pass
+    fn apply_global_effects(grid: &mut Grid, threads: usize) {
+        let effects = &grid.active_effects;
+        let cells = &mut grid.cells;
+        let actions = &mut grid.actions;
+
+        let mut chunk_actions = if threads > 1 && grid.width > 1 {
+            let chunk_size = grid.width.div_ceil(threads);
+
+            thread::scope(|scope| {
+                let handles: Vec<_> = cells.axis_chunks_iter_mut(Axis(0), chunk_size)
+                    .map(|chunk| scope.spawn(move || Self::apply_effects(chunk, effects)))
+                    .collect();
+
+                handles.into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect::<Vec<_>>()
+            })
+        } else {
+            vec![Self::apply_effects(cells.view_mut(), effects)]
+        };
+
+        for i in 0..effects.len() {
+            for effect_actions in &mut chunk_actions {
+                actions.append(&mut effect_actions[i]);
+            }
+        }
+    }
+
+    //places a produced walker on the first road next to its origin structure; the carried commodities are only
+    //taken from the origin once the walker has been placed; the exchange updates for both are returned
+    pub(super) fn place_walker(grid: &mut Grid, origin_cell: (usize, usize), origin: Uuid, origin_entity: &Entity, stage: WalkerStage) -> Result<(SpawnedWalker, Vec<ExchangeUpdate>), GridError> {
+        //structures without road access cannot spawn walkers
+        let road = grid.find_first_adjacent_road(origin_cell, &origin).ok_or(GridError::RoadUnreachable)?;
+
+        let mut carried: HashMap<String, u32> = HashMap::new();
+        let mut stored_amounts: HashMap<String, u32> = HashMap::new();
+        let mut updated_origin = origin_entity.clone();
+
+        if let Entity::Structure { ref mut state, .. } = updated_origin {
+            //walkers can only take the commodities that are currently stored in their origin structure
+            for commodity in stage.carried {
+                let stored = state.commodities.entry(commodity.name.clone()).or_insert(0);
+                let amount = cmp::min(*stored, commodity.amount);
+                *stored -= amount;
+                *carried.entry(commodity.name.clone()).or_insert(0) += amount;
+                stored_amounts.insert(commodity.name, *stored);
+            }
+        }
+
+        carried.retain(|_, amount| *amount > 0);
+
+        let is_patrolling = stage.walker.patrol.is_some();
+
+        let walker = Entity::Walker {
+            state: WalkerState {
+                commodities: carried.clone(),
+                current_life: stage.walker.max_life,
+                destination: None,
+                origin: Some(origin),
+                patrol: None,
+            },
+            props: stage.walker,
+        };
+
+        let (walker_id, _) = grid.add_entity(road, walker)?;
+
+        if is_patrolling {
+            let _ = grid.start_patrol(road, &walker_id);
+        }
+
+        let updated_origin = Arc::new(updated_origin);
+        grid.replace_entity(origin_cell, &origin, updated_origin.clone());
+
+        let mut updates: Vec<ExchangeUpdate> = stored_amounts.into_iter()
+            .filter(|(name, _)| carried.contains_key(name))
+            .map(|(name, amount)| (updated_origin.clone(), origin, Commodity { name, amount }, CommodityState::Available))
+            .collect();
+
+        let walker_ref = grid.entity(road, &walker_id).unwrap();
+
+        updates.extend(
+            carried.into_iter()
+                .map(|(name, amount)| (walker_ref.clone(), walker_id, Commodity { name, amount }, CommodityState::InTransit))
+        );
+
+        Ok((SpawnedWalker { walker: walker_id, origin, cell: road }, updates))
+    }
+
+    fn spawn_walker(&mut self, grid: &mut Grid, exchange: &mut CommodityExchange, origin: Uuid, stage: WalkerStage) -> Result<Vec<ExchangeError>, GridError> {
+        let origin_entity = grid.entity(self.cell, &origin).ok_or(GridError::EntityMissing)?;
+        let (spawned_walker, updates) = Self::place_walker(grid, self.cell, origin, &origin_entity, stage)?;
+        self.spawned_walkers.push(spawned_walker);
+
+        Ok(
+            updates.into_iter()
+                .filter_map(|(entity, id, commodity, state)| exchange.update_state(entity, &id, &commodity, state).err())
+                .collect()
+        )
+    }
+
+    //processes cells until the cursor is back at the first cell of the grid, finishing the current sweep;
+    //the first failure of any of the cells is returned
+    pub fn process_sweep(&mut self, grid: &mut Grid, exchange: &mut CommodityExchange) -> Result<(), CursorError> {
+        if self.threads > 1 && self.cell == (0, 0) {
+            return self.process_sweep_concurrently(grid, exchange);
+        }
+
+        let mut failure = None;
+
+        loop {
+            let result = self.process_and_advance(grid, exchange);
+
+            if failure.is_none() {
+                failure = result.err();
+            }
+
+            if self.cell == (0, 0) {
+                return match failure {
+                    Some(e) => Err(e),
+                    None => Ok(())
+                };
+            }
+        }
+    }
+
     //processes all effects for the current cell and moves to the next cell in the grid
     pub fn process_and_advance(&mut self, grid: &mut Grid, exchange: &mut CommodityExchange) -> Result<(), CursorError> {
         let cell_x = self.cell.0 as isize;
@@ -147,117 +511,57 @@ impl Cursor {
             //applies cell effects
             let cell_effects = &grid.cells[self.cell].active_effects.clone();
             let mut effect_area = grid.cells.slice_mut(s![rows, cols]);
+            let actions = &mut grid.actions;
 
             for effect in cell_effects {
                 for affected_cell in effect_area.iter_mut() {
-                    affected_cell.entities.iter_mut().for_each(|(_, grid_entity)| {
-                        let mut updated_entity = (*grid_entity.entity).clone();
-                        effect.apply(&mut updated_entity);
-                        grid_entity.replace_entity(updated_entity);
+                    affected_cell.entities.iter_mut().for_each(|(id, grid_entity)| {
+                        Self::apply_effect(effect, id, grid_entity, actions);
                     });
                 }
             }
         }
 
         if next_cell == (0, 0) {
-            //applies global effects
-            for effect in &grid.active_effects {
-                for affected_cell in grid.cells.iter_mut() {
-                    affected_cell.entities.iter_mut().for_each(|(_, grid_entity)| {
-                        let mut updated_entity = (*grid_entity.entity).clone();
-                        effect.apply(&mut updated_entity);
-                        grid_entity.replace_entity(updated_entity);
-                    });
-                }
-            }
-
-            //TODO - process movement
-            //TODO - process action queue
-            //TODO - process desirability changes for cells
-        }
-
-        let processing_failures = {
-            //process current cell production and state updates
-            let affected_cell: &mut Cell = grid.cells.get_mut(self.cell).unwrap();
-            affected_cell.entities.iter_mut().fold(vec![], |mut acc, (id, grid_entity)| {
-                let mut updated_entity = (*grid_entity.entity).clone();
-
-                let exchange_updates = match updated_entity {
-                    Entity::Resource { ref props, ref mut producer, ref mut state, .. } => {
-                        producer.as_mut()
-                            .and_then(|p| {
-                                let exchange_update = p.produce_commodity(&*grid_entity.entity)
-                                    .map(|stage| {
-                                        if state.current_amount >= stage.commodity.amount {
-                                            state.current_amount -= stage.commodity.amount;
-                                        } else {
-                                            state.current_amount = 0;
-                                        }
-
-                                        vec![(stage.commodity, CommodityState::Available)]
-                                    });
-
-                                props.replenish_amount
-                                    .map(|amount| {
-                                        if state.current_amount + amount < props.max_amount {
-                                            state.current_amount += amount;
-                                        } else {
-                                            state.current_amount = props.max_amount;
-                                        }
-                                    });
-
-                                exchange_update
-                            })
-                    }
-
-                    Entity::Structure { ref mut producer, ref mut state, .. } => {
-                        producer.as_mut()
-                            .and_then(|p| {
-                                let exchange_update = p.produce_commodity(&*grid_entity.entity)
-                                    .map(|stage| {
-                                        let existing = state.commodities
-                                            .entry(stage.commodity.name.clone())
-                                            .or_insert(0);
-
-                                        *existing += stage.commodity.amount;
+            Self::apply_global_effects(grid, self.threads);
+            grid.decay_coverage();
+            Self::process_movement(grid);
 
-                                        let mut updates = stage.required.into_iter()
-                                            .map(|c| (c, CommodityState::Required)).collect::<Vec<_>>();
+            let mut finished_patrols = grid.advance_patrols();
+            self.finished_patrols.append(&mut finished_patrols);
 
-                                        updates.extend(stage.used.into_iter()
-                                            .map(|c| (c, CommodityState::Used)).collect::<Vec<_>>());
+            grid.update_risks();
 
-                                        updates.push((stage.commodity, CommodityState::Available));
+            let mut action_results = grid.process_actions(exchange);
+            self.action_results.append(&mut action_results);
 
-                                        updates
-                                    });
+            grid.purge_deleted(exchange);
 
-                                p.produce_walker(&*grid_entity.entity).map(|walker| {
-                                    //TODO - add walker to grid
-                                    //TODO - add walker effects to grid
-                                });
+            grid.update_desirability();
+            grid.update_housing();
+            grid.update_labour();
+        }
 
-                                //TODO - update current employees count
+        let mut walker_stages = Vec::new();
 
-                                exchange_update
-                            })
-                    }
+        //with the labour market enabled, structures only produce as often as their staff allows
+        let is_output_staffed = grid.labour_rules.is_some();
 
-                    Entity::Walker { ref mut state, .. } => {
-                        //TODO - update state
-                        //TODO - process interaction with nearby entities
-                        //       (work, attack, get/leave commodities)
-                        None
-                    }
+        let mut processing_failures = {
+            //process current cell production and state updates
+            let affected_cell: &mut Cell = grid.cells.get_mut(self.cell).unwrap();
+            let actions = &mut grid.actions;
+            affected_cell.entities.iter_mut().fold(vec![], |mut acc, (id, grid_entity)| {
+                let (exchange_updates, walker_stage) = Self::produce(self.cell, id, grid_entity, is_output_staffed, actions);
 
-                    _ => None //do nothing
-                }.unwrap_or(Vec::new());
+                if let Some(stage) = walker_stage {
+                    walker_stages.push((*id, stage));
+                }
 
-                let updated_entity = Rc::new(updated_entity);
                 let mut failed_updates = exchange_updates.into_iter()
                     .map(|update| {
                         exchange.update_state(
-                            updated_entity.clone(),
+                            grid_entity.entity.clone(),
                             id,
                             &update.0,
                             update.1,
@@ -271,8 +575,6 @@ impl Cursor {
                     })
                     .collect::<Vec<_>>();
 
-                grid_entity.replace_ref(updated_entity);
-
                 if failed_updates.is_empty() {
                     acc
                 } else {
@@ -282,10 +584,21 @@ impl Cursor {
             })
         };
 
+        let mut placement_failure = None;
+
+        for (origin, stage) in walker_stages {
+            match self.spawn_walker(grid, exchange, origin, stage) {
+                Ok(mut spawning_failures) => processing_failures.append(&mut spawning_failures),
+                Err(e) => placement_failure = Some(e)
+            }
+        }
+
         //resets the cursor position
         self.cell = next_cell;
 
-        if processing_failures.is_empty() {
+        if let Some(e) = placement_failure {
+            Err(CursorError::ForGrid { e })
+        } else if processing_failures.is_empty() {
             Ok(())
         } else {
             Err(CursorError::ForExchange { errors: processing_failures })
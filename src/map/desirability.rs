@@ -0,0 +1,66 @@
+use entities::Entity;
+use map::{Cell, Grid};
+use std::cmp;
+use std::collections::HashSet;
+
+impl Grid {
+    //desirability is only recalculated at the end of a full sweep, after structures have been added or removed
+    pub fn desirability_at(&self, cell: (usize, usize)) -> Option<i8> {
+        self.cells.get(cell).map(|cell: &Cell| cell.desirability)
+    }
+
+    pub fn is_desirability_outdated(&self) -> bool {
+        self.is_desirability_outdated
+    }
+
+    //recalculates the desirability of all cells, if any structures were added, removed or changed their lifecycle state
+    //since the last update
+    pub fn update_desirability(&mut self) {
+        if !self.is_desirability_outdated {
+            return;
+        }
+
+        let mut field = vec![0i32; self.width * self.height];
+        let mut processed = HashSet::new();
+
+        for cell in self.cells.iter() {
+            for (id, grid_entity) in &cell.entities {
+                if let Entity::Structure { ref props, .. } = *grid_entity.entity {
+                    //destroyed structures no longer add to (or take from) the desirability around them
+                    if !grid_entity.state.is_operational() {
+                        continue;
+                    }
+
+                    if !processed.insert(*id) {
+                        continue; //multi-cell structures are only processed once
+                    }
+
+                    let (start_x, start_y) = grid_entity.parent;
+                    let end_x = start_x + props.size.width as usize - 1;
+                    let end_y = start_y + props.size.height as usize - 1;
+                    let range = cmp::max(props.desirability.3, 0) as usize;
+
+                    let area_x = start_x.saturating_sub(range)..cmp::min(end_x + range + 1, self.width);
+                    let area_y = start_y.saturating_sub(range)..cmp::min(end_y + range + 1, self.height);
+
+                    for x in area_x {
+                        for y in area_y.clone() {
+                            let distance_x = if x < start_x { start_x - x } else { x.saturating_sub(end_x) };
+                            let distance_y = if y < start_y { start_y - y } else { y.saturating_sub(end_y) };
+
+                            field[x * self.height + y] += i32::from(props.desirability_at(cmp::max(distance_x, distance_y)));
+                        }
+                    }
+                }
+            }
+        }
+
+        let height = self.height;
+
+        for ((x, y), cell) in self.cells.indexed_iter_mut() {
+            cell.desirability = cmp::min(cmp::max(field[x * height + y], i32::from(i8::MIN)), i32::from(i8::MAX)) as i8;
+        }
+
+        self.is_desirability_outdated = false;
+    }
+}
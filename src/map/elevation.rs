@@ -0,0 +1,104 @@
+use map::{CellState, Grid, GridError};
+use std::cmp;
+
+impl Grid {
+    pub fn elevation_at(&self, at: (usize, usize)) -> Option<u8> {
+        self.cells.get(at).map(|cell| cell.elevation)
+    }
+
+    //changes the elevation of a cell; entities already in the cell are not affected
+    pub fn set_elevation(&mut self, at: (usize, usize), elevation: u8) -> Result<CellState, GridError> {
+        match self.cell_state(at) {
+            CellState::OutOfBounds => {
+                Err(GridError::CellUnavailable)
+            }
+
+            state => {
+                self.cells[at].elevation = elevation;
+                self.invalidate_paths_at(&[at]);
+                Ok(state)
+            }
+        }
+    }
+
+    //difference in elevation between two cells; `None` if either cell is outside the grid
+    pub fn slope_between(&self, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        match (self.elevation_at(from), self.elevation_at(to)) {
+            (Some(from), Some(to)) => Some((i16::from(from) - i16::from(to)).unsigned_abs() as usize),
+            _ => None
+        }
+    }
+
+    //cost of moving from one cell to a neighbouring one; climbing or descending adds to the cost of the terrain
+    pub fn movement_cost_between(&self, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        match (self.movement_cost(to), self.slope_between(from, to)) {
+            (Some(cost), Some(slope)) => Some(cost + slope),
+            _ => None
+        }
+    }
+
+    pub(super) fn is_ground_level(&self, cells: &[(usize, usize)]) -> bool {
+        let elevations: Vec<u8> = cells.iter().filter_map(|cell| self.elevation_at(*cell)).collect();
+
+        match (elevations.iter().min(), elevations.iter().max()) {
+            (Some(min), Some(max)) => min == max,
+            _ => true
+        }
+    }
+
+    //checks if an observer standing at `from` can see `to`; the view is blocked by any cell
+    //in between that rises above the straight line connecting the two cells
+    pub fn has_line_of_sight(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let (from_elevation, to_elevation) = match (self.elevation_at(from), self.elevation_at(to)) {
+            (Some(from_elevation), Some(to_elevation)) => (f64::from(from_elevation), f64::from(to_elevation)),
+            _ => return false
+        };
+
+        let total_distance = Self::distance_between(&from, &to);
+
+        Self::cells_on_line(from, to).into_iter()
+            .filter(|cell| *cell != from && *cell != to)
+            .all(|cell| {
+                let progress = Self::distance_between(&from, &cell) / total_distance;
+                let sight_line = from_elevation + (to_elevation - from_elevation) * progress;
+
+                self.elevation_at(cell).is_some_and(|elevation| f64::from(elevation) <= sight_line)
+            })
+    }
+
+    //all cells crossed by a straight line between two cells (Bresenham's algorithm)
+    pub fn cells_on_line(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+        let (mut x, mut y) = (from.0 as isize, from.1 as isize);
+        let (end_x, end_y) = (to.0 as isize, to.1 as isize);
+
+        let dx = (end_x - x).abs();
+        let dy = -(end_y - y).abs();
+        let step_x = if x < end_x { 1 } else { -1 };
+        let step_y = if y < end_y { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        let mut cells = Vec::with_capacity(cmp::max(dx, -dy) as usize + 1);
+
+        loop {
+            cells.push((x as usize, y as usize));
+
+            if x == end_x && y == end_y {
+                break;
+            }
+
+            let doubled_error = 2 * error;
+
+            if doubled_error >= dy {
+                error += dy;
+                x += step_x;
+            }
+
+            if doubled_error <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+
+        cells
+    }
+}
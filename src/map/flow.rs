@@ -0,0 +1,220 @@
+use map::{Direction, FlowField, FlowFieldEntry, FlowStep, Grid, PathPolicy};
+use ndarray::Array2;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+use std::sync::Arc;
+
+impl FlowField {
+    pub fn target(&self) -> (usize, usize) {
+        self.target
+    }
+
+    pub fn policy(&self) -> &PathPolicy {
+        &self.policy
+    }
+
+    pub fn step_at(&self, cell: (usize, usize)) -> Option<&FlowStep> {
+        self.steps.get(cell).and_then(|step| step.as_ref())
+    }
+
+    pub fn next_step(&self, cell: (usize, usize)) -> Option<(usize, usize)> {
+        self.step_at(cell).map(|step| step.next)
+    }
+
+    pub fn cost_at(&self, cell: (usize, usize)) -> Option<usize> {
+        self.step_at(cell).map(|step| step.cost)
+    }
+
+    //directions of the next step; diagonal steps have both a horizontal and a vertical direction,
+    //while the target itself has none
+    pub fn directions_at(&self, cell: (usize, usize)) -> Option<Vec<Direction>> {
+        self.next_step(cell).map(|(next_x, next_y)| {
+            let mut directions = Vec::with_capacity(2);
+
+            if next_x < cell.0 {
+                directions.push(Direction::Left);
+            } else if next_x > cell.0 {
+                directions.push(Direction::Right);
+            }
+
+            if next_y < cell.1 {
+                directions.push(Direction::Up);
+            } else if next_y > cell.1 {
+                directions.push(Direction::Down);
+            }
+
+            directions
+        })
+    }
+
+    //all cells between the supplied cell and the target, including both
+    pub fn path_from(&self, cell: (usize, usize)) -> Option<Vec<(usize, usize)>> {
+        let mut path = vec![cell];
+        let mut current = cell;
+
+        while current != self.target {
+            current = self.next_step(current)?;
+            path.push(current);
+        }
+
+        Some(path)
+    }
+
+    //number of cells that were cleared and searched again when the field was last updated
+    pub fn recomputed_cells(&self) -> usize {
+        self.recomputed
+    }
+
+    //replaces the step of a cell, keeping track of which cells lead through the next one
+    fn set_step(&mut self, cell: (usize, usize), step: Option<FlowStep>) {
+        if let Some(previous) = self.steps[cell].take() {
+            if let Some(dependents) = self.dependents.get_mut(&previous.next) {
+                dependents.remove(&cell);
+
+                if dependents.is_empty() {
+                    self.dependents.remove(&previous.next);
+                }
+            }
+        }
+
+        if let Some(ref step) = step {
+            if step.next != cell {
+                self.dependents.entry(step.next).or_default().insert(cell);
+            }
+        }
+
+        self.steps[cell] = step;
+    }
+
+    //the supplied cells and all cells with a path to the target that crosses any of them
+    fn cells_leading_through(&self, cells: &BTreeSet<(usize, usize)>) -> BTreeSet<(usize, usize)> {
+        let mut found = BTreeSet::new();
+        let mut pending: Vec<(usize, usize)> = cells.iter()
+            .filter(|cell| self.steps.get(**cell).is_some())
+            .cloned()
+            .collect();
+
+        while let Some(cell) = pending.pop() {
+            if found.insert(cell) {
+                if let Some(dependents) = self.dependents.get(&cell) {
+                    pending.extend(dependents.iter().cloned());
+                }
+            }
+        }
+
+        found
+    }
+}
+
+impl Grid {
+    //retrieves the flow field leading to the target, creating it on first use; fields are kept up to date
+    //with changes to the grid, and only the cells affected by those changes are updated
+    pub fn flow_field_to(&mut self, target: (usize, usize), policy: PathPolicy) -> Option<Arc<FlowField>> {
+        if !self.is_cell_in_grid(target) {
+            return None;
+        }
+
+        let key = (target, policy.clone());
+
+        let mut entry = match self.flow_fields.remove(&key) {
+            Some(entry) => entry,
+
+            None => {
+                FlowFieldEntry {
+                    field: Arc::new(FlowField {
+                        target,
+                        policy,
+                        steps: Array2::from_elem((self.width, self.height), None),
+                        dependents: HashMap::new(),
+                        recomputed: 0,
+                    }),
+                    changed: vec![target].into_iter().collect(),
+                }
+            }
+        };
+
+        if !entry.changed.is_empty() {
+            self.update_flow_field(Arc::make_mut(&mut entry.field), &entry.changed);
+            entry.changed.clear();
+        }
+
+        let field = entry.field.clone();
+        self.flow_fields.insert(key, entry);
+
+        Some(field)
+    }
+
+    pub fn clear_flow_fields(&mut self) {
+        self.flow_fields.clear();
+    }
+
+    //cells next to the supplied one that can be entered under the policy
+    fn policy_neighbours_of(&self, cell: &(usize, usize), policy: &PathPolicy) -> Vec<(usize, usize)> {
+        match *policy {
+            PathPolicy::Passable => self.passable_neighbours_of(cell),
+
+            PathPolicy::Walker { ref traversal, has_destination } => {
+                Self::neighbours_of(cell, traversal.allows_diagonal_movement()).into_iter()
+                    .flatten()
+                    .filter(|neighbour| self.is_cell_traversable_by(*neighbour, &|entity| traversal.allows(entity, has_destination)))
+                    .collect()
+            }
+        }
+    }
+
+    fn is_cell_allowed_by(&self, cell: (usize, usize), policy: &PathPolicy) -> bool {
+        match *policy {
+            PathPolicy::Passable => self.is_cell_passable(cell),
+            PathPolicy::Walker { ref traversal, has_destination } => {
+                self.is_cell_traversable_by(cell, &|entity| traversal.allows(entity, has_destination))
+            }
+        }
+    }
+
+    //clears the steps of all cells that lead through the changed cells and searches from the cells around them
+    //again; costs that can be lowered are updated as well, so that fields stay the same as when created from scratch
+    fn update_flow_field(&self, field: &mut FlowField, changed: &BTreeSet<(usize, usize)>) {
+        let cleared = field.cells_leading_through(changed);
+
+        for cell in &cleared {
+            field.set_step(*cell, None);
+        }
+
+        field.recomputed = cleared.len();
+
+        let mut queue = BinaryHeap::new();
+
+        if changed.contains(&field.target) && self.is_cell_allowed_by(field.target, &field.policy) {
+            field.set_step(field.target, Some(FlowStep { next: field.target, cost: 0 }));
+        }
+
+        let frontier: BTreeSet<(usize, usize)> = cleared.iter()
+            .flat_map(|cell| Self::neighbours_of(cell, true).into_iter().flatten())
+            .chain(vec![field.target])
+            .filter(|cell| field.step_at(*cell).is_some())
+            .collect();
+
+        for cell in frontier {
+            if let Some(cost) = field.cost_at(cell) {
+                queue.push(Reverse((cost, cell)));
+            }
+        }
+
+        while let Some(Reverse((cost, cell))) = queue.pop() {
+            if field.cost_at(cell).is_some_and(|current| current < cost) {
+                continue;
+            }
+
+            for neighbour in self.policy_neighbours_of(&cell, &field.policy) {
+                if let Some(step_cost) = self.movement_cost_between(neighbour, cell) {
+                    let total = cost + step_cost;
+
+                    if field.cost_at(neighbour).is_none_or(|current| total < current) {
+                        field.set_step(neighbour, Some(FlowStep { next: cell, cost: total }));
+                        queue.push(Reverse((total, neighbour)));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,36 +1,66 @@
 use effects::Effect;
-use entities::{Entity, EntityType};
+use entities::{Entity, EntityType, State};
 use entities::structure;
-use map::{Cell, CellState, Grid, GridEntity, GridError};
+use map::{Cell, CellState, Grid, GridEntity, GridError, IdGenerator, LabourStats, LifecycleEvent, PathCache, RoadNetwork};
+use movement::Movable;
 use ndarray::Array2;
 use pathfinding::dijkstra;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::f64;
-use std::rc::Rc;
+use std::sync::Arc;
 use uuid::Uuid;
 
 impl Grid {
     //TODO - limit grid size to prevent cursor overflow when casting coords to isize (?)
     pub fn new(size: usize) -> Grid {
+        Self::with_dimensions(size, size)
+    }
+
+    pub fn with_dimensions(width: usize, height: usize) -> Grid {
         Grid {
-            cells: Array2::from_shape_fn((size, size), |_| Cell::empty()),
+            cells: Array2::from_shape_fn((width, height), |_| Cell::empty()),
+            index: HashMap::new(),
+            buckets: HashMap::new(),
+            id_generator: IdGenerator::Random,
+            path_cache: PathCache::new(),
+            roads: RoadNetwork::new(),
+            flow_fields: HashMap::new(),
+            risk_rules: None,
+            housing_ladder: None,
+            disaster_events: Vec::new(),
+            housing_events: Vec::new(),
+            labour_rules: None,
+            labour_stats: LabourStats::default(),
+            lifecycle_events: Vec::new(),
             active_effects: Vec::new(),
-            width: size,
-            height: size,
+            actions: Vec::new(),
+            is_desirability_outdated: false,
+            width,
+            height,
         }
     }
 
-    pub fn with_global_effects(size: usize, effects: Vec<Rc<Effect>>) -> Grid {
-        Grid {
-            cells: Array2::from_shape_fn((size, size), |_| Cell::empty()),
-            active_effects: effects,
-            width: size,
-            height: size,
-        }
+    pub fn with_global_effects(size: usize, effects: Vec<Arc<dyn Effect>>) -> Grid {
+        let mut grid = Self::new(size);
+        grid.active_effects = effects;
+        grid
     }
 
-    fn entity_cells(entity_size: &structure::Size, cell: (usize, usize)) -> Vec<(usize, usize)> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    //replaces the source of IDs for all entities added from now on
+    pub fn set_id_generator(&mut self, id_generator: IdGenerator) {
+        self.id_generator = id_generator;
+    }
+
+    pub(super) fn entity_cells(entity_size: &structure::Size, cell: (usize, usize)) -> Vec<(usize, usize)> {
         let cells: Vec<Vec<(usize, usize)>> = (cell.0..(cell.0 + entity_size.width as usize))
             .map(|x| {
                 (cell.1..(cell.1 + entity_size.height as usize)).map(|y| {
@@ -71,8 +101,8 @@ impl Grid {
 
         match cell_state {
             CellState::AvailableEmpty | CellState::AvailableOccupied => {
-                let entity_ref = Rc::new(entity);
-                let entity_id = Uuid::new_v4();
+                let entity_ref = Arc::new(entity);
+                let entity_id = self.id_generator.next_id();
 
                 match *entity_ref {
                     Entity::Structure { ref props, .. } => {
@@ -82,11 +112,23 @@ impl Grid {
                             let current_cell_state = self.cell_state(*c);
                             current_cell_state == CellState::AvailableEmpty || current_cell_state == CellState::AvailableOccupied
                         }) {
-                            for cell in cells {
-                                let cell_data = &mut self.cells[cell];
-                                cell_data.entities.insert(entity_id, GridEntity { entity: entity_ref.clone(), parent: at });
+                            if !self.is_terrain_buildable(&cells) {
+                                return Err(GridError::TerrainUnsuitable);
+                            }
+
+                            if !props.allows_uneven_ground && !self.is_ground_level(&cells) {
+                                return Err(GridError::GroundUneven);
+                            }
+
+                            for cell in &cells {
+                                let cell_data = &mut self.cells[*cell];
+                                cell_data.entities.insert(entity_id, GridEntity { entity: entity_ref.clone(), parent: at, state: State::Active });
                             }
 
+                            self.invalidate_paths_at(&cells);
+                            self.index_entity(entity_id, at, cells, entity_ref.entity_type());
+                            self.is_desirability_outdated = true;
+
                             Ok((entity_id, cell_state))
                         } else {
                             Err(GridError::CellUnavailable)
@@ -94,7 +136,28 @@ impl Grid {
                     }
 
                     _ => {
-                        self.cells[at].entities.insert(entity_id, GridEntity { entity: entity_ref.clone(), parent: at });
+                        //walkers only need a cell they can move through, everything else needs buildable terrain
+                        let is_terrain_suitable = match *entity_ref {
+                            Entity::Walker { .. } => self.movement_cost(at).is_some(),
+                            _ => self.is_terrain_buildable(&[at])
+                        };
+
+                        if !is_terrain_suitable {
+                            return Err(GridError::TerrainUnsuitable);
+                        }
+
+                        //walkers never block other walkers
+                        if entity_ref.entity_type() != EntityType::Walker {
+                            self.invalidate_paths_at(&[at]);
+                        }
+
+                        self.cells[at].entities.insert(entity_id, GridEntity { entity: entity_ref.clone(), parent: at, state: State::Active });
+                        self.index_entity(entity_id, at, vec![at], entity_ref.entity_type());
+
+                        if *entity_ref == Entity::Road {
+                            self.update_road_network(at);
+                        }
+
                         Ok((entity_id, cell_state))
                     }
                 }
@@ -112,10 +175,27 @@ impl Grid {
         match cell_state {
             CellState::AvailableOccupied | CellState::UnavailableOccupied => {
                 let grid_entity = self.cells[at].entities.get(id).map(|grid_entity| {
-                    (grid_entity.entity.clone(), grid_entity.parent)
+                    (grid_entity.entity.clone(), grid_entity.parent, grid_entity.state.clone())
                 });
 
-                grid_entity.map(|(entity, parent)| {
+                grid_entity.map(|(entity, parent, state)| {
+                    if let Entity::Structure { .. } = *entity {
+                        self.is_desirability_outdated = true;
+                    }
+
+                    //entities removed directly skip the rest of their lifecycle
+                    if state != State::Deleted {
+                        self.lifecycle_events.push(LifecycleEvent { entity: *id, at: parent, from: state, to: State::Deleted });
+                    }
+
+                    if let Some(location) = self.locate(id).cloned() {
+                        if location.entity_type != EntityType::Walker {
+                            self.invalidate_paths_at(&location.cells);
+                        }
+                    }
+
+                    self.unindex_entity(id);
+
                     match *entity {
                         Entity::Structure { ref props, .. } if props.size.width * props.size.height > 1 => {
                             let cells = Self::entity_cells(&props.size, parent);
@@ -130,6 +210,11 @@ impl Grid {
 
                         _ => {
                             self.cells[at].entities.remove(id);
+
+                            if *entity == Entity::Road {
+                                self.update_road_network(at);
+                            }
+
                             Ok(cell_state)
                         }
                     }
@@ -142,7 +227,115 @@ impl Grid {
         }
     }
 
-    pub fn add_cell_effect(&mut self, at: (usize, usize), effect: Rc<Effect>) -> Result<CellState, GridError> {
+    pub fn move_entity(&mut self, from: (usize, usize), id: &Uuid, to: (usize, usize)) -> Result<CellState, GridError> {
+        let entity = self.entity(from, id);
+
+        match entity {
+            Some(entity) => {
+                match *entity {
+                    Entity::Walker { .. } => {
+                        if self.is_cell_passable(to) {
+                            let cell_state = self.cell_state(to);
+
+                            self.cells[from].entities.remove(id)
+                                .map(|grid_entity| {
+                                    self.cells[to].entities.insert(*id, GridEntity { entity: grid_entity.entity, parent: to, state: grid_entity.state });
+                                    self.index_entity(*id, to, vec![to], EntityType::Walker);
+                                    Ok(cell_state)
+                                })
+                                .unwrap_or(Err(GridError::EntityMissing))
+                        } else {
+                            Err(GridError::CellUnavailable)
+                        }
+                    }
+
+                    _ => {
+                        Err(GridError::EntityImmovable)
+                    }
+                }
+            }
+
+            None => {
+                Err(GridError::EntityMissing)
+            }
+        }
+    }
+
+    //replaces the entity in all cells it occupies
+    pub(super) fn replace_entity(&mut self, at: (usize, usize), id: &Uuid, entity: Arc<Entity>) {
+        let cells = match self.cells.get(at).and_then(|cell| cell.entities.get(id)) {
+            Some(grid_entity) => {
+                match *grid_entity.entity {
+                    Entity::Structure { ref props, .. } => Self::entity_cells(&props.size, grid_entity.parent),
+                    _ => vec![at]
+                }
+            }
+
+            None => vec![]
+        };
+
+        for cell in cells {
+            if let Some(grid_entity) = self.cells[cell].entities.get_mut(id) {
+                grid_entity.replace_ref(entity.clone());
+            }
+        }
+    }
+
+    //replaces a structure with an updated one of a different size, keeping its parent cell and lifecycle state;
+    //the structure is only resized if all additional cells can be built on
+    pub(super) fn resize_structure(&mut self, parent: (usize, usize), id: &Uuid, entity: Arc<Entity>) -> Result<(), GridError> {
+        let (current_cells, state) = match self.cells.get(parent).and_then(|cell| cell.entities.get(id)) {
+            Some(grid_entity) => {
+                match *grid_entity.entity {
+                    Entity::Structure { ref props, .. } => (Self::entity_cells(&props.size, grid_entity.parent), grid_entity.state.clone()),
+                    _ => return Err(GridError::UnexpectedEntity)
+                }
+            }
+
+            None => return Err(GridError::EntityMissing)
+        };
+
+        let (cells, allows_uneven_ground) = match *entity {
+            Entity::Structure { ref props, .. } => (Self::entity_cells(&props.size, parent), props.allows_uneven_ground),
+            _ => return Err(GridError::UnexpectedEntity)
+        };
+
+        let added: Vec<(usize, usize)> = cells.iter().filter(|cell| !current_cells.contains(cell)).cloned().collect();
+
+        if !added.iter().all(|cell| {
+            let cell_state = self.cell_state(*cell);
+            cell_state == CellState::AvailableEmpty || cell_state == CellState::AvailableOccupied
+        }) {
+            return Err(GridError::CellUnavailable);
+        }
+
+        if !self.is_terrain_buildable(&added) {
+            return Err(GridError::TerrainUnsuitable);
+        }
+
+        if !allows_uneven_ground && !self.is_ground_level(&cells) {
+            return Err(GridError::GroundUneven);
+        }
+
+        for cell in &current_cells {
+            self.cells[*cell].entities.remove(id);
+        }
+
+        for cell in &cells {
+            self.cells[*cell].entities.insert(*id, GridEntity { entity: entity.clone(), parent, state: state.clone() });
+        }
+
+        let mut affected = current_cells;
+        affected.extend(added);
+
+        self.invalidate_paths_at(&affected);
+        self.index_entity(*id, parent, cells, EntityType::Structure);
+        self.is_desirability_outdated = true;
+
+        Ok(())
+    }
+
+    pub fn add_cell_effect(&mut self, at: (usize, usize), effect: Arc<dyn Effect>) -> Result<CellState, GridError> {
         match self.cell_state(at) {
             CellState::OutOfBounds => {
                 Err(GridError::CellUnavailable)
@@ -159,7 +352,7 @@ impl Grid {
         }
     }
 
-    pub fn remove_cell_effect(&mut self, at: (usize, usize), effect: &Rc<Effect>) -> Result<CellState, GridError> {
+    pub fn remove_cell_effect(&mut self, at: (usize, usize), effect: &Arc<dyn Effect>) -> Result<CellState, GridError> {
         match self.cell_state(at) {
             CellState::OutOfBounds => {
                 Err(GridError::CellUnavailable)
@@ -168,7 +361,7 @@ impl Grid {
             state => {
                 match self.cells[at].active_effects.iter()
                     .position(|e| {
-                        Rc::ptr_eq(e, effect)
+                        Arc::ptr_eq(e, effect)
                     })
                     .map(|i| {
                         self.cells[at].active_effects.remove(i)
@@ -194,7 +387,7 @@ impl Grid {
         }
     }
 
-    pub fn add_global_effect(&mut self, effect: Rc<Effect>) -> Result<(), GridError> {
+    pub fn add_global_effect(&mut self, effect: Arc<dyn Effect>) -> Result<(), GridError> {
         if self.is_effect_global(&effect) {
             Err(GridError::EffectPresent)
         } else {
@@ -203,10 +396,10 @@ impl Grid {
         }
     }
 
-    pub fn remove_global_effect(&mut self, effect: &Rc<Effect>) -> Result<(), GridError> {
+    pub fn remove_global_effect(&mut self, effect: &Arc<dyn Effect>) -> Result<(), GridError> {
         match self.active_effects.iter()
             .position(|e| {
-                Rc::ptr_eq(e, effect)
+                Arc::ptr_eq(e, effect)
             })
             .map(|i| {
                 self.active_effects.remove(i)
@@ -220,7 +413,7 @@ impl Grid {
         self.active_effects.clear()
     }
 
-    pub fn entity(&self, at: (usize, usize), id: &Uuid) -> Option<Rc<Entity>> {
+    pub fn entity(&self, at: (usize, usize), id: &Uuid) -> Option<Arc<Entity>> {
         self.cells.get(at)
             .and_then(|cell| {
                 cell.entities.get(id).as_ref().map(|grid_entity| grid_entity.entity.clone())
@@ -299,8 +492,8 @@ impl Grid {
     pub fn find_named_entities(&self, entity_type: EntityType, with_name: String) -> Vec<(usize, usize)> {
         self.cells.indexed_iter()
             .fold(
-                HashMap::new(),
-                |mut acc: HashMap<Uuid, (usize, usize)>, (index, cell)| {
+                BTreeMap::new(),
+                |mut acc: BTreeMap<Uuid, (usize, usize)>, (index, cell)| {
                     cell.entities.iter().for_each(|(id, grid_entity)| {
                         match *grid_entity.entity {
                             Entity::Doodad { ref props, .. } if entity_type == EntityType::Doodad && props.name == with_name => {
@@ -366,7 +559,7 @@ impl Grid {
         }).is_none()
     }
 
-    pub fn is_effect_in_cell(&self, cell: (usize, usize), effect: &Rc<Effect>) -> bool {
+    pub fn is_effect_in_cell(&self, cell: (usize, usize), effect: &Arc<dyn Effect>) -> bool {
         match self.cell_state(cell) {
             CellState::OutOfBounds => {
                 false
@@ -375,7 +568,7 @@ impl Grid {
             _ => {
                 match self.cells[cell].active_effects.iter()
                     .position(|e| {
-                        Rc::ptr_eq(e, &effect)
+                        Arc::ptr_eq(e, effect)
                     }) {
                     Some(_) => true,
                     None => false
@@ -384,29 +577,74 @@ impl Grid {
         }
     }
 
-    pub fn is_effect_global(&self, effect: &Rc<Effect>) -> bool {
+    pub fn is_effect_global(&self, effect: &Arc<dyn Effect>) -> bool {
         match self.active_effects.iter()
             .position(|e| {
-                Rc::ptr_eq(e, effect)
+                Arc::ptr_eq(e, effect)
             }) {
             Some(_) => true,
             None => false
         }
     }
 
+    pub fn is_cell_traversable(&self, cell: (usize, usize), movable: &dyn Movable) -> bool {
+        self.is_cell_traversable_by(cell, &|entity| movable.can_traverse(entity))
+    }
+
+    pub(super) fn is_cell_traversable_by(&self, cell: (usize, usize), can_traverse: &dyn Fn(Option<&Entity>) -> bool) -> bool {
+        self.is_cell_in_grid(cell) && {
+            let mut obstacles = self.cells[cell].entities.values()
+                .filter(|grid_entity| !matches!(*grid_entity.entity, Entity::Walker { .. }))
+                .peekable();
+
+            if obstacles.peek().is_some() {
+                obstacles.all(|grid_entity| can_traverse(Some(&*grid_entity.entity)))
+            } else {
+                can_traverse(None)
+            }
+        }
+    }
+
+    //corner neighbours are always considered; see `traversable_neighbours_of` for walker-specific rules
     pub fn passable_neighbours_of(&self, cell: &(usize, usize)) -> Vec<(usize, usize)> {
-        //TODO - allow corner neighbors only for specific walkers that don't need roads
         Self::neighbours_of(cell, true).into_iter()
             .filter(|opt| opt.map_or(false, |c| self.is_cell_passable(c)))
             .map(|opt| opt.unwrap())
             .collect()
     }
 
+    pub fn traversable_neighbours_of(&self, cell: &(usize, usize), movable: &dyn Movable) -> Vec<(usize, usize)> {
+        Self::neighbours_of(cell, movable.can_move_diagonally()).into_iter()
+            .filter(|opt| opt.is_some_and(|c| self.is_cell_traversable(c, movable)))
+            .map(|opt| opt.unwrap())
+            .collect()
+    }
+
+    //the cost of a path is the sum of the movement costs of all cells entered along it,
+    //including the differences in elevation between them
     pub fn path_between(&self, start: (usize, usize), end: (usize, usize)) -> Option<(Vec<(usize, usize)>, usize)> {
         if self.is_cell_in_grid(start) && self.is_cell_in_grid(end) {
             dijkstra(
                 &start,
-                |cell| self.passable_neighbours_of(cell).into_iter().map(|c| (c, 1)),
+                |&cell| {
+                    self.passable_neighbours_of(&cell).into_iter()
+                        .filter_map(move |c| self.movement_cost_between(cell, c).map(|cost| (c, cost)))
+                },
+                |cell| *cell == end,
+            )
+        } else {
+            None
+        }
+    }
+
+    pub fn path_for(&self, start: (usize, usize), end: (usize, usize), movable: &dyn Movable) -> Option<(Vec<(usize, usize)>, usize)> {
+        if self.is_cell_in_grid(start) && self.is_cell_in_grid(end) {
+            dijkstra(
+                &start,
+                |&cell| {
+                    self.traversable_neighbours_of(&cell, movable).into_iter()
+                        .filter_map(move |c| self.movement_cost_between(cell, c).map(|cost| (c, cost)))
+                },
                 |cell| *cell == end,
             )
         } else {
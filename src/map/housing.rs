@@ -0,0 +1,162 @@
+use entities::Entity;
+use entities::structure::{StructureState, Type};
+use map::{Grid, HousingEvent, HousingLadder, HousingLevel};
+use std::cmp;
+use std::mem;
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl Grid {
+    //enables the housing evolution; houses keep their level and nobody moves in when no ladder is set
+    pub fn set_housing_ladder(&mut self, ladder: Option<HousingLadder>) {
+        self.housing_ladder = ladder;
+    }
+
+    pub fn housing_ladder(&self) -> Option<&HousingLadder> {
+        self.housing_ladder.as_ref()
+    }
+
+    //returns all houses that changed their level since the last call
+    pub fn take_housing_events(&mut self) -> Vec<HousingEvent> {
+        mem::take(&mut self.housing_events)
+    }
+
+    //number of people living in all houses
+    pub fn population(&self) -> u32 {
+        self.houses().iter()
+            .filter_map(|&(parent, id)| self.entity(parent, &id))
+            .map(|entity| {
+                match *entity {
+                    Entity::Structure { ref state, .. } => u32::from(state.current_residents),
+                    _ => 0
+                }
+            })
+            .sum()
+    }
+
+    //number of people that can live in all houses
+    pub fn housing_capacity(&self) -> u32 {
+        self.houses().iter()
+            .filter_map(|&(parent, id)| self.entity(parent, &id))
+            .map(|entity| {
+                match *entity {
+                    Entity::Structure { ref props, .. } => u32::from(props.max_residents),
+                    _ => 0
+                }
+            })
+            .sum()
+    }
+
+    //parent cells and IDs of all housing structures that are still in use
+    pub(super) fn houses(&self) -> Vec<((usize, usize), Uuid)> {
+        self.cells.indexed_iter()
+            .flat_map(|(index, cell)| {
+                cell.entities.iter()
+                    .filter(move |&(_, grid_entity)| {
+                        match *grid_entity.entity {
+                            Entity::Structure { ref props, .. } => {
+                                props.structure_type == Type::Housing && grid_entity.parent == index && grid_entity.state.is_operational()
+                            }
+
+                            _ => false
+                        }
+                    })
+                    .map(move |(id, _)| (index, *id))
+            })
+            .collect()
+    }
+
+    fn meets_housing_requirements(&self, cells: &[(usize, usize)], state: &StructureState, level: &HousingLevel) -> bool {
+        let desirability = cells.iter().filter_map(|cell| self.desirability_at(*cell)).min().unwrap_or(0);
+
+        desirability >= level.min_desirability
+            && state.coverage.satisfies(&level.min_coverage)
+            && level.commodities.iter().all(|(name, amount)| state.commodities.get(name).cloned().unwrap_or(0) >= *amount)
+    }
+
+    //creates a copy of the house with the properties of the new level (if any) and with new residents moved in;
+    //returns the updated house and the number of residents that had to move out
+    fn updated_house(entity: &Entity, level: Option<&HousingLevel>, growth: u8) -> (Entity, u8) {
+        let mut updated_entity = entity.clone();
+        let mut evicted = 0;
+
+        if let Entity::Structure { ref mut props, ref mut state, .. } = updated_entity {
+            if let Some(level) = level {
+                props.name = level.name.clone();
+                props.size = level.size.clone();
+                props.max_residents = level.max_residents;
+            }
+
+            evicted = state.current_residents.saturating_sub(props.max_residents);
+            state.current_residents = cmp::min(state.current_residents.saturating_add(growth), props.max_residents);
+        }
+
+        (updated_entity, evicted)
+    }
+
+    //moves every house on the ladder up or down by one level, based on the desirability of its cells, its service
+    //coverage and its commodities, and lets people move into houses with free capacity; houses without the room to
+    //grow into the next level keep their current one
+    pub(super) fn update_housing(&mut self) {
+        let ladder = match self.housing_ladder.clone() {
+            Some(ladder) => ladder,
+            None => return
+        };
+
+        for (parent, id) in self.houses() {
+            let entity = match self.entity(parent, &id) {
+                Some(entity) => entity,
+                None => continue
+            };
+
+            let current = match *entity {
+                Entity::Structure { ref props, ref state, .. } => {
+                    ladder.levels.iter().position(|level| level.name == props.name).map(|level| {
+                        let cells = Self::entity_cells(&props.size, parent);
+
+                        let next = level + 1;
+                        let can_evolve = next < ladder.levels.len()
+                            && self.meets_housing_requirements(&cells, state, &ladder.levels[next]);
+
+                        let must_devolve = level > 0
+                            && !self.meets_housing_requirements(&cells, state, &ladder.levels[level]);
+
+                        if can_evolve {
+                            (level, next)
+                        } else if must_devolve {
+                            (level, level - 1)
+                        } else {
+                            (level, level)
+                        }
+                    })
+                }
+
+                _ => None
+            };
+
+            let (level, target) = match current {
+                Some(levels) => levels,
+                None => continue
+            };
+
+            if level != target {
+                let (house, evicted) = Self::updated_house(&entity, Some(&ladder.levels[target]), ladder.growth);
+
+                if self.resize_structure(parent, &id, Arc::new(house)).is_ok() {
+                    self.housing_events.push(HousingEvent {
+                        structure: id,
+                        at: parent,
+                        from: ladder.levels[level].name.clone(),
+                        to: ladder.levels[target].name.clone(),
+                        evicted,
+                    });
+
+                    continue;
+                }
+            }
+
+            let (house, _) = Self::updated_house(&entity, None, ladder.growth);
+            self.replace_entity(parent, &id, Arc::new(house));
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use map::IdGenerator;
+use uuid::Uuid;
+
+impl IdGenerator {
+    pub fn seeded(seed: u64) -> IdGenerator {
+        IdGenerator::Seeded { state: seed }
+    }
+
+    pub fn next_id(&mut self) -> Uuid {
+        match *self {
+            IdGenerator::Random => Uuid::new_v4(),
+
+            IdGenerator::Seeded { ref mut state } => {
+                let mut bytes = [0u8; 16];
+
+                let values = [Self::next_value(state), Self::next_value(state)];
+
+                for (i, value) in values.iter().enumerate() {
+                    for j in 0..8 {
+                        bytes[i * 8 + j] = (value >> (56 - j * 8)) as u8;
+                    }
+                }
+
+                //marks the ID as a random (v4) UUID
+                bytes[6] = (bytes[6] & 0x0f) | 0x40;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+                Uuid::from_bytes(&bytes).unwrap()
+            }
+        }
+    }
+
+    //splitmix64; small, fast and gives the same sequence on every platform
+    pub(super) fn next_value(state: &mut u64) -> u64 {
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
@@ -0,0 +1,80 @@
+use entities::{Entity, EntityType};
+use map::{CellState, EntityLocation, Grid, GridError};
+use std::cmp;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+//width and height of the blocks of cells used by the spatial index
+pub const BUCKET_SIZE: usize = 8;
+
+impl Grid {
+    pub(super) fn index_entity(&mut self, id: Uuid, parent: (usize, usize), cells: Vec<(usize, usize)>, entity_type: EntityType) {
+        self.unindex_entity(&id);
+
+        for bucket in Self::buckets_of(&cells) {
+            self.buckets.entry(bucket).or_default().insert(id);
+        }
+
+        self.index.insert(id, EntityLocation { parent, cells, entity_type });
+    }
+
+    pub(super) fn unindex_entity(&mut self, id: &Uuid) {
+        if let Some(location) = self.index.remove(id) {
+            for bucket in Self::buckets_of(&location.cells) {
+                let is_empty = match self.buckets.get_mut(&bucket) {
+                    Some(ids) => {
+                        ids.remove(id);
+                        ids.is_empty()
+                    }
+
+                    None => false
+                };
+
+                if is_empty {
+                    self.buckets.remove(&bucket);
+                }
+            }
+        }
+    }
+
+    //retrieves the IDs of all entities that may occupy cells between `from` and `to` (inclusive);
+    //the result can contain entities that are only close to the area
+    pub(super) fn indexed_candidates(&self, from: (usize, usize), to: (usize, usize)) -> BTreeSet<Uuid> {
+        let mut candidates = BTreeSet::new();
+
+        if self.width == 0 || self.height == 0 || from.0 >= self.width || from.1 >= self.height {
+            return candidates;
+        }
+
+        //areas reaching past the grid would otherwise visit buckets that can never hold any entities
+        let to = (cmp::min(to.0, self.width - 1), cmp::min(to.1, self.height - 1));
+
+        for bucket_x in (from.0 / BUCKET_SIZE)..(to.0 / BUCKET_SIZE + 1) {
+            for bucket_y in (from.1 / BUCKET_SIZE)..(to.1 / BUCKET_SIZE + 1) {
+                if let Some(ids) = self.buckets.get(&(bucket_x, bucket_y)) {
+                    candidates.extend(ids.iter().cloned());
+                }
+            }
+        }
+
+        candidates
+    }
+
+    fn buckets_of(cells: &[(usize, usize)]) -> BTreeSet<(usize, usize)> {
+        cells.iter().map(|&(x, y)| (x / BUCKET_SIZE, y / BUCKET_SIZE)).collect()
+    }
+
+    pub fn locate(&self, id: &Uuid) -> Option<&EntityLocation> {
+        self.index.get(id)
+    }
+
+    pub fn entity_by_id(&self, id: &Uuid) -> Option<Arc<Entity>> {
+        self.locate(id).and_then(|location| self.entity(location.parent, id))
+    }
+
+    pub fn remove_by_id(&mut self, id: &Uuid) -> Result<CellState, GridError> {
+        let parent = self.locate(id).map(|location| location.parent).ok_or(GridError::EntityMissing)?;
+        self.remove_entity(parent, id)
+    }
+}
@@ -0,0 +1,128 @@
+use entities::Entity;
+use entities::structure::Type;
+use map::{Grid, LabourRules, LabourStats};
+use std::cmp;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl Default for LabourRules {
+    fn default() -> LabourRules {
+        LabourRules {
+            workforce_share: 50,
+        }
+    }
+}
+
+impl Grid {
+    //enables the labour market; employee counts are left as they are when no rules are set
+    pub fn set_labour_rules(&mut self, rules: Option<LabourRules>) {
+        self.labour_rules = rules;
+    }
+
+    pub fn labour_rules(&self) -> Option<&LabourRules> {
+        self.labour_rules.as_ref()
+    }
+
+    pub fn labour_stats(&self) -> &LabourStats {
+        &self.labour_stats
+    }
+
+    //parent cells and IDs of all structures that can employ workers, in the order they are staffed
+    fn employers(&self) -> Vec<((usize, usize), Uuid)> {
+        let mut employers: Vec<(u8, (usize, usize), Uuid)> = self.cells.indexed_iter()
+            .flat_map(|(index, cell)| {
+                cell.entities.iter()
+                    .filter_map(move |(id, grid_entity)| {
+                        match *grid_entity.entity {
+                            Entity::Structure { ref props, .. } => {
+                                if props.structure_type != Type::Housing
+                                    && props.max_employees > 0
+                                    && grid_entity.parent == index
+                                    && grid_entity.state.is_operational() {
+                                    Some((props.structure_type.labour_priority(), index, *id))
+                                } else {
+                                    None
+                                }
+                            }
+
+                            _ => None
+                        }
+                    })
+            })
+            .collect();
+
+        employers.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+        employers.into_iter().map(|(_, parent, id)| (parent, id)).collect()
+    }
+
+    //residents of all houses look for work in structures next to any of the road networks around their house;
+    //structures with a higher labour priority are staffed first and their employee counts are updated
+    pub(super) fn update_labour(&mut self) {
+        let rules = match self.labour_rules.clone() {
+            Some(rules) => rules,
+            None => return
+        };
+
+        let mut stats = LabourStats::default();
+        let mut available: Vec<(BTreeSet<usize>, u32)> = Vec::new();
+
+        for (parent, id) in self.houses() {
+            let workers = match self.entity(parent, &id) {
+                Some(entity) => {
+                    match *entity {
+                        Entity::Structure { ref state, .. } => {
+                            u32::from(state.current_residents) * u32::from(cmp::min(rules.workforce_share, 100)) / 100
+                        }
+
+                        _ => continue
+                    }
+                }
+
+                None => continue
+            };
+
+            stats.workforce += workers;
+
+            available.push((self.road_components_next_to(&id), workers));
+        }
+
+        for (parent, id) in self.employers() {
+            let entity = match self.entity(parent, &id) {
+                Some(entity) => entity,
+                None => continue
+            };
+
+            let mut updated_entity = (*entity).clone();
+
+            if let Entity::Structure { ref props, ref mut state, .. } = updated_entity {
+                let mut employees = 0u32;
+
+                let components = self.road_components_next_to(&id);
+
+                for &mut (ref house_components, ref mut workers) in available.iter_mut() {
+                    if !house_components.is_disjoint(&components) {
+                        let hired = cmp::min(*workers, u32::from(props.max_employees) - employees);
+                        *workers -= hired;
+                        employees += hired;
+                    }
+                }
+
+                stats.jobs += u32::from(props.max_employees);
+                stats.employed += employees;
+
+                if u32::from(state.current_employees) == employees {
+                    continue;
+                }
+
+                state.current_employees = employees as u8;
+            }
+
+            self.replace_entity(parent, &id, Arc::new(updated_entity));
+        }
+
+        stats.unemployed = stats.workforce - stats.employed;
+        stats.shortage = stats.jobs - stats.employed;
+        self.labour_stats = stats;
+    }
+}
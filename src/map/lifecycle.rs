@@ -0,0 +1,70 @@
+use entities::{Entity, State};
+use map::{Grid, GridError, LifecycleEvent};
+use production::exchange::CommodityExchange;
+use std::mem;
+use uuid::Uuid;
+
+impl Grid {
+    pub fn entity_state(&self, at: (usize, usize), id: &Uuid) -> Option<State> {
+        self.cells.get(at)
+            .and_then(|cell| cell.entities.get(id))
+            .map(|grid_entity| grid_entity.state.clone())
+    }
+
+    //moves the entity to the next state of its lifecycle; setting the current state again has no effect
+    pub fn set_entity_state(&mut self, at: (usize, usize), id: &Uuid, state: State) -> Result<(), GridError> {
+        let current = self.entity_state(at, id).ok_or(GridError::EntityMissing)?;
+
+        if current == state {
+            return Ok(());
+        }
+
+        if !current.can_become(&state) {
+            return Err(GridError::TransitionInvalid { from: current, to: state });
+        }
+
+        let (parent, cells) = match self.locate(id) {
+            Some(location) => (location.parent, location.cells.clone()),
+            None => (at, vec![at])
+        };
+
+        for cell in cells {
+            if let Some(grid_entity) = self.cells[cell].entities.get_mut(id) {
+                grid_entity.state = state.clone();
+
+                if let Entity::Structure { .. } = *grid_entity.entity {
+                    self.is_desirability_outdated = true;
+                }
+            }
+        }
+
+        self.lifecycle_events.push(LifecycleEvent { entity: *id, at: parent, from: current, to: state });
+        Ok(())
+    }
+
+    //returns all lifecycle state changes since the last call
+    pub fn take_lifecycle_events(&mut self) -> Vec<LifecycleEvent> {
+        mem::take(&mut self.lifecycle_events)
+    }
+
+    //removes all deleted entities from the grid and from the exchange
+    pub(super) fn purge_deleted(&mut self, exchange: &mut CommodityExchange) {
+        let deleted: Vec<((usize, usize), Uuid)> = self.cells.indexed_iter()
+            .flat_map(|(index, cell)| {
+                cell.entities.iter()
+                    .filter(move |&(_, grid_entity)| grid_entity.parent == index && grid_entity.state == State::Deleted)
+                    .map(move |(id, _)| (index, *id))
+            })
+            .collect();
+
+        for (parent, id) in deleted {
+            self.purge_entity(parent, &id, exchange);
+        }
+    }
+
+    //removes a single deleted entity from the grid and from the exchange
+    pub(super) fn purge_entity(&mut self, parent: (usize, usize), id: &Uuid, exchange: &mut CommodityExchange) {
+        let _ = self.remove_entity(parent, id);
+        exchange.remove_entity(id);
+    }
+}
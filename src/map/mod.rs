@@ -1,41 +1,79 @@
 use effects::Effect;
-use entities::Entity;
+use entities::{Entity, EntityType, State};
+use entities::structure;
+use movement::Movable;
+use movement::cost::PathCost;
 use ndarray::Array2;
+use production::Commodity;
 use production::exchange::ExchangeError;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
-use std::rc::Rc;
+use std::sync::Arc;
 use uuid::Uuid;
 
+pub mod action;
+pub mod builder;
+pub mod coverage;
 pub mod cursor;
+pub mod desirability;
+pub mod elevation;
+pub mod flow;
 pub mod grid;
+pub mod housing;
+pub mod id;
+pub mod index;
+pub mod labour;
+pub mod lifecycle;
+pub mod path;
+pub mod path_cache;
+pub mod patrol;
+pub mod persistence;
+pub mod query;
+pub mod risk;
+pub mod road;
+pub mod sweep;
+pub mod terrain;
 
 #[derive(Clone, Debug)]
 struct GridEntity {
-    entity: Rc<Entity>,
+    entity: Arc<Entity>,
     parent: (usize, usize),
+    state: State,
 }
 
 impl GridEntity {
     pub fn replace_entity(&mut self, entity: Entity) {
-        self.entity = Rc::new(entity);
+        self.entity = Arc::new(entity);
     }
 
-    pub fn replace_ref(&mut self, entity: Rc<Entity>) {
+    pub fn replace_ref(&mut self, entity: Arc<Entity>) {
         self.entity = entity;
     }
 }
 
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum Terrain {
+    Grass,
+    Water,
+    Sand,
+    Rock,
+    Marsh,
+    Floodplain,
+}
+
 #[derive(Clone)]
 struct Cell {
-    entities: HashMap<Uuid, GridEntity>,
+    //ordered by ID, so that entities are always processed in the same order
+    entities: BTreeMap<Uuid, GridEntity>,
     desirability: i8,
-    active_effects: Vec<Rc<Effect>>,
+    terrain: Terrain,
+    elevation: u8,
+    active_effects: Vec<Arc<dyn Effect>>,
 }
 
 impl Cell {
     fn empty() -> Cell {
-        Cell { entities: HashMap::new(), desirability: 0, active_effects: Vec::new() }
+        Cell { entities: BTreeMap::new(), desirability: 0, terrain: Terrain::Grass, elevation: 0, active_effects: Vec::new() }
     }
 }
 
@@ -43,8 +81,8 @@ impl fmt::Debug for Cell {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         write!(
             formatter,
-            "Cell {{ entities: {:?}, desirability: {}, active_effects: {} }}",
-            self.entities, self.desirability, self.active_effects.len()
+            "Cell {{ entities: {:?}, desirability: {}, terrain: {:?}, elevation: {}, active_effects: {} }}",
+            self.entities, self.desirability, self.terrain, self.elevation, self.active_effects.len()
         )
     }
 }
@@ -57,7 +95,7 @@ pub enum CellState {
     OutOfBounds,
 }
 
-#[derive(Debug)]
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Serialize, Deserialize)]
 pub enum TraversalType {
     RoadOnly,
     EmptyOnly,
@@ -68,18 +106,292 @@ pub enum TraversalType {
 pub enum GridError {
     CellUnavailable,
     EntityMissing,
+    EntityImmovable,
+    UnexpectedEntity,
     EffectPresent,
     EffectMissing,
+    CommodityUnavailable,
+    RoadUnreachable,
+    TerrainUnsuitable,
+    GroundUneven,
+    TransitionInvalid { from: State, to: State },
+}
+
+pub enum Action {
+    AddEntity { at: (usize, usize), entity: Entity },
+    RemoveEntity { at: (usize, usize), id: Uuid },
+    AddCellEffect { at: (usize, usize), effect: Arc<dyn Effect> },
+    RemoveCellEffect { at: (usize, usize), effect: Arc<dyn Effect> },
+    AddGlobalEffect { effect: Arc<dyn Effect> },
+    RemoveGlobalEffect { effect: Arc<dyn Effect> },
+    MoveWalker { from: (usize, usize), id: Uuid, to: (usize, usize) },
+    TransferCommodity { from: ((usize, usize), Uuid), to: ((usize, usize), Uuid), commodity: Commodity },
+    //transfers a commodity to whichever structure occupies the target cell
+    DeliverCommodity { from: ((usize, usize), Uuid), to: (usize, usize), commodity: Commodity },
+    DestroyStructure { at: (usize, usize), id: Uuid },
+    SetEntityState { at: (usize, usize), id: Uuid, state: State },
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum ActionError {
+    ForGrid { e: GridError },
+    ForExchange { e: ExchangeError },
+}
+
+//where an entity is placed on the grid; multi-cell structures occupy all cells starting from their parent cell
+#[derive(PartialEq, Clone, Debug)]
+pub struct EntityLocation {
+    pub parent: (usize, usize),
+    pub cells: Vec<(usize, usize)>,
+    pub entity_type: EntityType,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum DistanceMetric {
+    Chebyshev,
+    Manhattan,
+    Euclidean,
+}
+
+//restricts the results of spatial queries; all set conditions must match
+pub struct EntityFilter<'a> {
+    pub entity_type: Option<EntityType>,
+    pub structure_type: Option<structure::Type>,
+    pub predicate: Option<&'a dyn Fn(&Entity) -> bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EntityMatch {
+    pub id: Uuid,
+    pub parent: (usize, usize),
+    pub entity: Arc<Entity>,
+}
+
+//controls how `Grid::find_path` searches for a path
+pub struct PathOptions<'a> {
+    pub cost: &'a dyn PathCost,
+    //when set, only cells the movable can traverse are considered; otherwise, all passable cells are
+    pub movable: Option<&'a dyn Movable>,
+    //cells further away from the start than this are never explored
+    pub max_distance: Option<usize>,
+}
+
+//the rules a cached path was found with; walkers with a destination can pass through roadblocks
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub enum PathPolicy {
+    Passable,
+    Walker { traversal: TraversalType, has_destination: bool },
+}
+
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+struct PathKey {
+    start: (usize, usize),
+    end: (usize, usize),
+    policy: PathPolicy,
+}
+
+struct CachedPath {
+    cells: Vec<(usize, usize)>,
+    //cost of reaching each cell of the path from its first cell
+    costs: Vec<usize>,
+    //area containing the path and all cells next to it
+    bounds: ((usize, usize), (usize, usize)),
+    //lowest cost a single step can have with the costs used for the path
+    min_step_cost: usize,
+}
+
+//stores found paths so that every part of them can be reused, until the grid changes around them
+struct PathCache {
+    paths: HashMap<u64, CachedPath>,
+    //path key -> (path ID, offset of the key's start cell in the path)
+    entries: HashMap<PathKey, (u64, usize)>,
+    failures: HashSet<PathKey>,
+    next_path: u64,
+    stats: PathCacheStats,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct PathCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub invalidated: usize,
+    pub cached_paths: usize,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct FlowStep {
+    pub next: (usize, usize),
+    //remaining cost of reaching the target
+    pub cost: usize,
+}
+
+//the next step towards a common target for every cell the target can be reached from
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    target: (usize, usize),
+    policy: PathPolicy,
+    steps: Array2<Option<FlowStep>>,
+    //the cells whose next step is the key cell
+    dependents: HashMap<(usize, usize), BTreeSet<(usize, usize)>>,
+    //number of cells that were cleared and searched again during the last update
+    recomputed: usize,
+}
+
+struct FlowFieldEntry {
+    field: Arc<FlowField>,
+    //cells changed since the field was last updated
+    changed: BTreeSet<(usize, usize)>,
+}
+
+//changes to the road network that other systems may need to react to
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum RoadEvent {
+    //a removed road divided a component; the first of the parts keeps its ID
+    Split { component: usize, into: Vec<usize> },
+}
+
+//road cells grouped by the orthogonally connected networks they belong to
+struct RoadNetwork {
+    components: HashMap<(usize, usize), usize>,
+    members: HashMap<usize, BTreeSet<(usize, usize)>>,
+    next_component: usize,
+    events: Vec<RoadEvent>,
+}
+
+//a change to the lifecycle state of an entity
+#[derive(PartialEq, Clone, Debug)]
+pub struct LifecycleEvent {
+    pub entity: Uuid,
+    pub at: (usize, usize),
+    pub from: State,
+    pub to: State,
+}
+
+//settings of the built-in fire and collapse simulation
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct RiskRules {
+    //structures catch fire once their fire risk reaches this value
+    pub fire_threshold: u8,
+    //structures collapse once their damage risk reaches this value
+    pub collapse_threshold: u8,
+    //fire risk added to the structures next to a burning structure; fires do not spread when not set
+    pub fire_spread: Option<u8>,
+}
+
+//one level of the housing ladder; a house takes the name, size and capacity of its level
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct HousingLevel {
+    pub name: String,
+    pub size: structure::Size,
+    pub max_residents: u8,
+    //lowest desirability of all cells of the house needed to reach or keep the level
+    #[serde(default)]
+    pub min_desirability: i8,
+    //lowest coverage level of each service needed to reach or keep the level
+    #[serde(default)]
+    pub min_coverage: structure::Coverage,
+    //amounts of commodities the house must have available to reach or keep the level
+    #[serde(default, serialize_with = "::persistence::serialize_ordered")]
+    pub commodities: HashMap<String, u32>,
+}
+
+//settings of the built-in housing evolution; houses are matched to the levels by their name
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct HousingLadder {
+    //from the lowest level to the highest; the requirements of the first level are ignored
+    pub levels: Vec<HousingLevel>,
+    //number of people moving into every house with free capacity at the end of every sweep
+    pub growth: u8,
+}
+
+//a house that moved to another level of the housing ladder
+#[derive(PartialEq, Clone, Debug)]
+pub struct HousingEvent {
+    pub structure: Uuid,
+    pub at: (usize, usize),
+    pub from: String,
+    pub to: String,
+    //residents that had to move out because the new level has a lower capacity
+    pub evicted: u8,
+}
+
+//settings of the built-in labour market
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct LabourRules {
+    //percentage of the residents of a house that are looking for work
+    pub workforce_share: u8,
+}
+
+//state of the labour market after the last sweep
+#[derive(PartialEq, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LabourStats {
+    //number of residents looking for work
+    pub workforce: u32,
+    pub employed: u32,
+    //workers that found no job, including those living in houses without road access
+    pub unemployed: u32,
+    //number of workers all structures could employ
+    pub jobs: u32,
+    //jobs that were left unfilled
+    pub shortage: u32,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Disaster {
+    Fire,
+    Collapse,
+}
+
+//a structure that was destroyed by a disaster; it is replaced by rubble at the end of the sweep
+#[derive(PartialEq, Clone, Debug)]
+pub struct DisasterEvent {
+    pub structure: Uuid,
+    pub at: (usize, usize),
+    pub disaster: Disaster,
+    pub state: State,
+}
+
+//source of entity IDs; a seeded generator produces the same IDs in the same order on every run
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum IdGenerator {
+    Random,
+    Seeded { state: u64 },
 }
 
 pub struct Grid {
     cells: Array2<Cell>,
-    active_effects: Vec<Rc<Effect>>,
+    index: HashMap<Uuid, EntityLocation>,
+    //entity IDs grouped by fixed-size blocks of cells, for spatial queries
+    buckets: HashMap<(usize, usize), BTreeSet<Uuid>>,
+    id_generator: IdGenerator,
+    path_cache: PathCache,
+    roads: RoadNetwork,
+    flow_fields: HashMap<((usize, usize), PathPolicy), FlowFieldEntry>,
+    risk_rules: Option<RiskRules>,
+    disaster_events: Vec<DisasterEvent>,
+    housing_ladder: Option<HousingLadder>,
+    housing_events: Vec<HousingEvent>,
+    labour_rules: Option<LabourRules>,
+    labour_stats: LabourStats,
+    lifecycle_events: Vec<LifecycleEvent>,
+    active_effects: Vec<Arc<dyn Effect>>,
+    actions: Vec<Action>,
+    is_desirability_outdated: bool,
     width: usize,
     height: usize,
 }
 
-#[derive(Debug)]
+pub struct GridBuilder {
+    width: usize,
+    height: usize,
+    id_generator: IdGenerator,
+    global_effects: Vec<Arc<dyn Effect>>,
+    terrain: Vec<((usize, usize), Terrain)>,
+    elevation: Vec<((usize, usize), u8)>,
+    entities: Vec<((usize, usize), Entity)>,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Down,
@@ -93,8 +405,28 @@ pub enum CursorError {
     ForExchange { errors: Vec<ExchangeError> },
 }
 
+#[derive(Eq, PartialEq, Debug)]
+pub struct SpawnedWalker {
+    pub walker: Uuid,
+    pub origin: Uuid,
+    pub cell: (usize, usize),
+}
+
+//a patrol that led its walker back to where it started; the walker is removed at the end of the sweep
+#[derive(Eq, PartialEq, Debug)]
+pub struct FinishedPatrol {
+    pub walker: Uuid,
+    pub origin: Option<Uuid>,
+    pub cells: Vec<(usize, usize)>,
+    pub structures: BTreeSet<Uuid>,
+}
+
 pub struct Cursor {
     cell: (usize, usize),
     direction: Direction,
     range: usize,
+    threads: usize,
+    spawned_walkers: Vec<SpawnedWalker>,
+    finished_patrols: Vec<FinishedPatrol>,
+    action_results: Vec<Result<(), ActionError>>,
 }
\ No newline at end of file
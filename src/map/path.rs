@@ -0,0 +1,61 @@
+use map::{Grid, PathOptions};
+use movement::Movable;
+use movement::cost::{PathCost, PATH_COST_SCALE};
+use pathfinding::astar;
+use std::f64;
+
+impl<'a> PathOptions<'a> {
+    pub fn new(cost: &'a dyn PathCost) -> PathOptions<'a> {
+        PathOptions {
+            cost,
+            movable: None,
+            max_distance: None,
+        }
+    }
+
+    pub fn for_movable(mut self, movable: &'a dyn Movable) -> PathOptions<'a> {
+        self.movable = Some(movable);
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: usize) -> PathOptions<'a> {
+        self.max_distance = Some(max_distance);
+        self
+    }
+}
+
+impl Grid {
+    //A* search between two cells; the cost of the path is in fractions of a step (see `PATH_COST_SCALE`)
+    pub fn find_path(&self, start: (usize, usize), end: (usize, usize), options: &PathOptions) -> Option<(Vec<(usize, usize)>, usize)> {
+        if !self.is_cell_in_grid(start) || !self.is_cell_in_grid(end) {
+            return None;
+        }
+
+        let is_within_limit = |cell: &(usize, usize)| {
+            options.max_distance.is_none_or(|max_distance| Self::distance_between(&start, cell) <= max_distance as f64)
+        };
+
+        if !is_within_limit(&end) {
+            return None;
+        }
+
+        astar(
+            &start,
+            |&cell| {
+                let neighbours = match options.movable {
+                    Some(movable) => self.traversable_neighbours_of(&cell, movable),
+                    None => self.passable_neighbours_of(&cell)
+                };
+
+                neighbours.into_iter()
+                    .filter(|neighbour| is_within_limit(neighbour))
+                    .filter_map(|neighbour| options.cost.cost(self, cell, neighbour).map(|cost| (neighbour, cost)))
+                    .collect::<Vec<_>>()
+            },
+            //a single step covers at most sqrt(2) of distance and costs at least one full step,
+            //so the heuristic never overestimates the remaining cost
+            |cell| (Self::distance_between(cell, &end) / f64::consts::SQRT_2 * PATH_COST_SCALE as f64) as usize,
+            |cell| *cell == end,
+        )
+    }
+}
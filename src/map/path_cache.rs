@@ -0,0 +1,213 @@
+use map::{CachedPath, Grid, PathCache, PathCacheStats, PathKey, PathOptions, PathPolicy};
+use movement::Movable;
+use movement::cost::{PathCost, TerrainCost, PATH_COST_SCALE};
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+
+//all paths are dropped once the cache holds this many
+const PATH_CACHE_CAPACITY: usize = 1024;
+
+//cells of the path, with its total cost
+type FoundPath = (Vec<(usize, usize)>, usize);
+
+impl PathPolicy {
+    //`None` if the movable cannot move on its own
+    pub fn of(movable: &dyn Movable) -> Option<PathPolicy> {
+        movable.traversal().map(|traversal| {
+            PathPolicy::Walker { traversal: traversal.clone(), has_destination: movable.destination().is_some() }
+        })
+    }
+}
+
+impl CachedPath {
+    //checks if a path through the cell could be cheaper than the path, or any part of it, even at the lowest step cost
+    fn can_be_shortened_through(&self, cell: (usize, usize)) -> bool {
+        let distance = |a: (usize, usize), b: (usize, usize)| cmp::max(a.0.abs_diff(b.0), a.1.abs_diff(b.1));
+
+        let end = self.cells[self.cells.len() - 1];
+        let total = self.costs[self.costs.len() - 1];
+        let to_end = distance(cell, end);
+
+        self.cells.iter().zip(self.costs.iter()).any(|(start, cost)| {
+            (distance(*start, cell) + to_end) * self.min_step_cost < total - cost
+        })
+    }
+}
+
+impl PathCache {
+    pub(super) fn new() -> PathCache {
+        PathCache {
+            paths: HashMap::new(),
+            entries: HashMap::new(),
+            failures: HashSet::new(),
+            next_path: 0,
+            stats: PathCacheStats::default(),
+        }
+    }
+
+    //`None` on a miss; `Some(None)` if it is already known that there is no path
+    fn get(&mut self, key: &PathKey) -> Option<Option<FoundPath>> {
+        let found = if self.failures.contains(key) {
+            Some(None)
+        } else {
+            self.entries.get(key).and_then(|&(path, offset)| {
+                self.paths.get(&path).map(|path| {
+                    Some((path.cells[offset..].to_vec(), path.costs[path.costs.len() - 1] - path.costs[offset]))
+                })
+            })
+        };
+
+        if found.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+
+        found
+    }
+
+    //every part of a path leading to the same end is a path on its own, so it is stored under the keys of all its cells
+    fn insert_path(&mut self, key: PathKey, cells: Vec<(usize, usize)>, costs: Vec<usize>, min_step_cost: usize) {
+        if self.paths.len() >= PATH_CACHE_CAPACITY {
+            self.clear();
+        }
+
+        let id = self.next_path;
+        self.next_path += 1;
+
+        for (offset, cell) in cells.iter().enumerate() {
+            self.entries.insert(PathKey { start: *cell, end: key.end, policy: key.policy.clone() }, (id, offset));
+        }
+
+        let bounds = cells.iter().fold(((usize::MAX, usize::MAX), (0, 0)), |(min, max), &(x, y)| {
+            (
+                (cmp::min(min.0, x.saturating_sub(1)), cmp::min(min.1, y.saturating_sub(1))),
+                (cmp::max(max.0, x + 1), cmp::max(max.1, y + 1)),
+            )
+        });
+
+        self.paths.insert(id, CachedPath { cells, costs, bounds, min_step_cost });
+    }
+
+    fn insert_failure(&mut self, key: PathKey) {
+        self.failures.insert(key);
+    }
+
+    //drops all paths that pass through or next to the cell, and all paths that a cheaper path through the cell could
+    //replace if the change opened it up or made it cheaper; any change can open up new paths, so all failed searches
+    //are dropped as well
+    fn invalidate(&mut self, cell: (usize, usize)) {
+        let affected: HashSet<u64> = self.paths.iter()
+            .filter(|&(_, path)| {
+                let ((min_x, min_y), (max_x, max_y)) = path.bounds;
+                let is_next_to_path = cell.0 >= min_x && cell.0 <= max_x && cell.1 >= min_y && cell.1 <= max_y;
+
+                is_next_to_path || path.can_be_shortened_through(cell)
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        if !affected.is_empty() {
+            self.paths.retain(|id, _| !affected.contains(id));
+            self.entries.retain(|_, &mut (id, _)| !affected.contains(&id));
+            self.stats.invalidated += affected.len();
+        }
+
+        self.failures.clear();
+    }
+
+    fn clear(&mut self) {
+        self.paths.clear();
+        self.entries.clear();
+        self.failures.clear();
+    }
+}
+
+impl Grid {
+    //same as `path_between`, but paths found earlier are reused for as long as no change on the grid can make them
+    //blocked, more expensive or replaceable by a cheaper path
+    pub fn cached_path_between(&mut self, start: (usize, usize), end: (usize, usize)) -> Option<FoundPath> {
+        self.cached_path(
+            PathKey { start, end, policy: PathPolicy::Passable },
+            1,
+            |grid| grid.path_between(start, end),
+            |grid, from, to| grid.movement_cost_between(from, to),
+        )
+    }
+
+    //the path a walker takes to its destination; same as `find_path` with `TerrainCost`,
+    //but paths found earlier are reused (see `cached_path_between`)
+    pub fn walker_path(&mut self, start: (usize, usize), end: (usize, usize), movable: &dyn Movable) -> Option<FoundPath> {
+        match PathPolicy::of(movable) {
+            Some(policy) => {
+                self.cached_path(
+                    PathKey { start, end, policy },
+                    PATH_COST_SCALE,
+                    |grid| grid.find_path(start, end, &PathOptions::new(&TerrainCost).for_movable(movable)),
+                    |grid, from, to| TerrainCost.cost(grid, from, to),
+                )
+            }
+
+            None => self.find_path(start, end, &PathOptions::new(&TerrainCost).for_movable(movable))
+        }
+    }
+
+    pub fn path_cache_stats(&self) -> PathCacheStats {
+        PathCacheStats { cached_paths: self.path_cache.paths.len(), ..self.path_cache.stats.clone() }
+    }
+
+    pub fn clear_path_cache(&mut self) {
+        self.path_cache.clear();
+    }
+
+    //called whenever the passability or the movement cost of cells changes; also marks the cells for flow field updates
+    pub(super) fn invalidate_paths_at(&mut self, cells: &[(usize, usize)]) {
+        for cell in cells {
+            self.path_cache.invalidate(*cell);
+        }
+
+        for entry in self.flow_fields.values_mut() {
+            entry.changed.extend(cells.iter().cloned());
+        }
+    }
+
+    fn cached_path<F, C>(&mut self, key: PathKey, min_step_cost: usize, find: F, cost: C) -> Option<FoundPath>
+        where F: Fn(&Grid) -> Option<FoundPath>, C: Fn(&Grid, (usize, usize), (usize, usize)) -> Option<usize>
+    {
+        if let Some(path) = self.path_cache.get(&key) {
+            return path;
+        }
+
+        let path = find(self);
+
+        match path {
+            Some((ref cells, _)) => {
+                if let Some(costs) = self.costs_along(cells, |from, to| cost(self, from, to)) {
+                    self.path_cache.insert_path(key, cells.clone(), costs, min_step_cost);
+                }
+            }
+
+            None => self.path_cache.insert_failure(key)
+        }
+
+        path
+    }
+
+    //cost of reaching each cell of the path from its first cell; `None` if any step is not allowed
+    fn costs_along<F>(&self, cells: &[(usize, usize)], cost: F) -> Option<Vec<usize>>
+        where F: Fn((usize, usize), (usize, usize)) -> Option<usize>
+    {
+        let mut costs = Vec::with_capacity(cells.len());
+        let mut total = 0;
+
+        for (index, cell) in cells.iter().enumerate() {
+            if index > 0 {
+                total += cost(cells[index - 1], *cell)?;
+            }
+
+            costs.push(total);
+        }
+
+        Some(costs)
+    }
+}
@@ -0,0 +1,190 @@
+use entities::{Entity, EntityType};
+use entities::walker::{PatrolStage, PatrolState};
+use map::{Action, FinishedPatrol, Grid, GridError, IdGenerator};
+use movement::Movable;
+use production::Commodity;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl Grid {
+    //sends a walker with a patrol distance on a patrol from its current cell; its route is chosen based on its ID,
+    //so the same walker always takes the same route through the same roads
+    pub fn start_patrol(&mut self, at: (usize, usize), id: &Uuid) -> Result<(), GridError> {
+        let entity = self.entity(at, id).ok_or(GridError::EntityMissing)?;
+        let mut updated_entity = (*entity).clone();
+
+        match updated_entity {
+            Entity::Walker { ref props, ref mut state } if props.patrol.is_some() => {
+                state.destination = None;
+                state.patrol = Some(PatrolState {
+                    start: at,
+                    stage: PatrolStage::Outbound,
+                    distance: 0,
+                    previous: None,
+                    seed: id.as_bytes().iter().fold(0u64, |seed, byte| seed.rotate_left(8) ^ u64::from(*byte)),
+                    cells: vec![at],
+                    structures: self.structures_next_to(at),
+                });
+            }
+
+            _ => return Err(GridError::UnexpectedEntity)
+        }
+
+        self.replace_entity(at, id, Arc::new(updated_entity));
+        Ok(())
+    }
+
+    //IDs of all structures in the cell or next to it (including corners)
+    fn structures_next_to(&self, cell: (usize, usize)) -> BTreeSet<Uuid> {
+        Self::neighbours_of(&cell, true).into_iter()
+            .flatten()
+            .chain(Some(cell))
+            .filter_map(|neighbour| self.cells.get(neighbour))
+            .flat_map(|neighbour| {
+                neighbour.entities.iter()
+                    .filter(|&(_, grid_entity)| grid_entity.entity.entity_type() == EntityType::Structure)
+                    .map(|(id, _)| *id)
+            })
+            .collect()
+    }
+
+    //picks a random road next to the walker, avoiding going back unless it reached a dead end
+    fn next_patrol_cell(&self, current: (usize, usize), patrol: &mut PatrolState, walker: &dyn Movable) -> Option<(usize, usize)> {
+        let candidates: Vec<(usize, usize)> = Self::neighbours_of(&current, false).into_iter()
+            .flatten()
+            .filter(|&neighbour| self.road_component_at(neighbour).is_some() && self.is_cell_traversable(neighbour, walker))
+            .collect();
+
+        let forward: Vec<(usize, usize)> = candidates.iter()
+            .filter(|&&neighbour| Some(neighbour) != patrol.previous)
+            .cloned()
+            .collect();
+
+        let choices = if forward.is_empty() { candidates } else { forward };
+
+        if choices.is_empty() {
+            None
+        } else {
+            let choice = IdGenerator::next_value(&mut patrol.seed) % choices.len() as u64;
+            Some(choices[choice as usize])
+        }
+    }
+
+    //moves all patrolling walkers by up to their movement speed, refreshing the coverage of the service of their origin
+    //structure around them; walkers that are back where they started return their commodities to their origin structure
+    //and are removed at the end of the sweep
+    pub(super) fn advance_patrols(&mut self) -> Vec<FinishedPatrol> {
+        let walkers: Vec<((usize, usize), Uuid)> = self.cells.indexed_iter()
+            .flat_map(|(index, cell)| {
+                cell.entities.iter()
+                    .filter(|&(_, grid_entity)| {
+                        match *grid_entity.entity {
+                            Entity::Walker { ref state, .. } => state.patrol.is_some() && grid_entity.state.is_operational(),
+                            _ => false
+                        }
+                    })
+                    .map(move |(id, _)| (index, *id))
+            })
+            .collect();
+
+        let mut finished = Vec::new();
+
+        for (cell, id) in walkers {
+            let walker = match self.entity(cell, &id) {
+                Some(walker) => walker,
+                None => continue
+            };
+
+            let (props, mut state) = match *walker {
+                Entity::Walker { ref props, ref state } => (props.clone(), state.clone()),
+                _ => continue
+            };
+
+            let mut patrol = match state.patrol.take() {
+                Some(patrol) => patrol,
+                None => continue
+            };
+
+            let service = state.origin.and_then(|origin| self.service_of(&origin));
+            let max_distance = props.patrol.unwrap_or(0);
+            let mut current = cell;
+
+            for _ in 0..props.movement_speed {
+                if patrol.stage == PatrolStage::Outbound && patrol.distance >= max_distance {
+                    patrol.stage = PatrolStage::Returning;
+                }
+
+                if patrol.stage == PatrolStage::Returning && current == patrol.start {
+                    break;
+                }
+
+                let next = match patrol.stage {
+                    PatrolStage::Outbound => self.next_patrol_cell(current, &mut patrol, &*walker),
+                    PatrolStage::Returning => self.walker_path(current, patrol.start, &*walker).and_then(|(path, _)| path.get(1).cloned()),
+                };
+
+                match next {
+                    Some(next) if self.move_entity(current, &id, next).is_ok() => {
+                        if patrol.stage == PatrolStage::Outbound {
+                            patrol.distance = patrol.distance.saturating_add(1);
+                        }
+
+                        patrol.previous = Some(current);
+                        patrol.cells.push(next);
+                        let nearby = self.structures_next_to(next);
+
+                        if let Some(ref service) = service {
+                            self.refresh_coverage(&nearby, service);
+                        }
+
+                        patrol.structures.extend(nearby);
+                        current = next;
+                    }
+
+                    //a walker with nowhere to go heads back; one without a way back tries again after the next sweep
+                    _ => {
+                        if patrol.stage == PatrolStage::Outbound {
+                            patrol.stage = PatrolStage::Returning;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            let is_finished = patrol.stage == PatrolStage::Returning && current == patrol.start;
+
+            if is_finished {
+                if let Some(origin) = state.origin {
+                    if let Some(origin_cell) = self.locate(&origin).map(|location| location.parent) {
+                        let mut carried: Vec<(&String, &u32)> = state.commodities.iter().filter(|&(_, amount)| *amount > 0).collect();
+                        carried.sort();
+
+                        for (name, amount) in carried {
+                            self.actions.push(Action::TransferCommodity {
+                                from: (current, id),
+                                to: (origin_cell, origin),
+                                commodity: Commodity { name: name.clone(), amount: *amount },
+                            });
+                        }
+                    }
+                }
+
+                self.actions.push(Action::RemoveEntity { at: current, id });
+
+                finished.push(FinishedPatrol {
+                    walker: id,
+                    origin: state.origin,
+                    cells: patrol.cells.clone(),
+                    structures: patrol.structures.clone(),
+                });
+            }
+
+            state.patrol = Some(patrol);
+            self.replace_entity(current, &id, Arc::new(Entity::Walker { props, state }));
+        }
+
+        finished
+    }
+}
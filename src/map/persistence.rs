@@ -0,0 +1,164 @@
+use effects::Effect;
+use entities::Entity;
+use map::{Cursor, Grid, GridEntity, GridError};
+use persistence::{PersistenceError, SavedBehavior, SavedCell, SavedCursor, SavedEntity, SavedGrid, SavedGridEntity};
+use registry::Registry;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl Grid {
+    pub fn to_saved(&self) -> SavedGrid {
+        let mut effects: Vec<Arc<dyn Effect>> = Vec::new();
+
+        //effects shared between cells (or with the global list) are only stored once
+        let mut effect_index = |effect: &Arc<dyn Effect>| {
+            match effects.iter().position(|e| Arc::ptr_eq(e, effect)) {
+                Some(index) => index,
+                None => {
+                    effects.push(effect.clone());
+                    effects.len() - 1
+                }
+            }
+        };
+
+        let global_effects = self.active_effects.iter().map(&mut effect_index).collect();
+
+        let mut entities = Vec::new();
+        let mut cells = Vec::with_capacity(self.width * self.height);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = &self.cells[(x, y)];
+
+                entities.extend(
+                    cell.entities.iter()
+                        .filter(|&(_, grid_entity)| grid_entity.parent == (x, y))
+                        .map(|(id, grid_entity)| {
+                            SavedGridEntity {
+                                id: *id,
+                                parent: grid_entity.parent,
+                                entity: SavedEntity::from_entity(&grid_entity.entity),
+                                state: grid_entity.state.clone(),
+                            }
+                        })
+                );
+
+                cells.push(SavedCell {
+                    desirability: cell.desirability,
+                    terrain: cell.terrain.clone(),
+                    elevation: cell.elevation,
+                    effects: cell.active_effects.iter().map(&mut effect_index).collect(),
+                });
+            }
+        }
+
+        SavedGrid {
+            width: self.width,
+            height: self.height,
+            entities,
+            cells,
+            effects: effects.iter().map(|e| SavedBehavior { type_name: e.type_name().to_owned(), parameters: e.parameters() }).collect(),
+            global_effects,
+            is_desirability_outdated: self.is_desirability_outdated,
+            id_generator: self.id_generator.clone(),
+            risk_rules: self.risk_rules.clone(),
+            housing_ladder: self.housing_ladder.clone(),
+            labour_rules: self.labour_rules.clone(),
+            labour_stats: self.labour_stats.clone(),
+        }
+    }
+
+    pub fn from_saved(saved: SavedGrid, registry: &Registry) -> Result<Grid, PersistenceError> {
+        if saved.cells.len() != saved.width * saved.height {
+            return Err(PersistenceError::Format { message: format!("expected [{}] cells but found [{}]", saved.width * saved.height, saved.cells.len()) });
+        }
+
+        let effects = saved.effects.into_iter()
+            .map(|effect| {
+                registry.create_effect(&effect.type_name, &effect.parameters).map_err(|e| PersistenceError::ForRegistry { e })
+            })
+            .collect::<Result<Vec<Arc<dyn Effect>>, PersistenceError>>()?;
+
+        let effect_at = |index: usize| {
+            effects.get(index)
+                .cloned()
+                .ok_or(PersistenceError::Format { message: format!("unexpected effect index [{}]", index) })
+        };
+
+        let mut grid = Grid::with_dimensions(saved.width, saved.height);
+
+        grid.active_effects = saved.global_effects.into_iter()
+            .map(&effect_at)
+            .collect::<Result<Vec<Arc<dyn Effect>>, PersistenceError>>()?;
+
+        for (index, saved_cell) in saved.cells.into_iter().enumerate() {
+            let cell = &mut grid.cells[(index % saved.width, index / saved.width)];
+            cell.desirability = saved_cell.desirability;
+            cell.terrain = saved_cell.terrain;
+            cell.elevation = saved_cell.elevation;
+            cell.active_effects = saved_cell.effects.into_iter()
+                .map(&effect_at)
+                .collect::<Result<Vec<Arc<dyn Effect>>, PersistenceError>>()?;
+        }
+
+        for saved_entity in saved.entities {
+            let entity = Arc::new(saved_entity.entity.into_entity(registry)?);
+            let parent = saved_entity.parent;
+
+            let cells = match *entity {
+                Entity::Structure { ref props, .. } => Self::entity_cells(&props.size, parent),
+                _ => vec![parent]
+            };
+
+            if !cells.iter().all(|c| grid.is_cell_in_grid(*c)) {
+                return Err(PersistenceError::ForGrid { e: GridError::CellUnavailable });
+            }
+
+            for cell in &cells {
+                grid.cells[*cell].entities.insert(saved_entity.id, GridEntity { entity: entity.clone(), parent, state: saved_entity.state.clone() });
+            }
+
+            grid.index_entity(saved_entity.id, parent, cells, entity.entity_type());
+        }
+
+        grid.rebuild_road_network();
+        grid.is_desirability_outdated = saved.is_desirability_outdated;
+        grid.id_generator = saved.id_generator;
+        grid.risk_rules = saved.risk_rules;
+        grid.housing_ladder = saved.housing_ladder;
+        grid.labour_rules = saved.labour_rules;
+        grid.labour_stats = saved.labour_stats;
+
+        Ok(grid)
+    }
+
+    //collects references to all entities on the grid, keyed by their IDs; structures covering several cells
+    //are taken from their parent cell, where they produce
+    pub fn entities_by_id(&self) -> HashMap<Uuid, Arc<Entity>> {
+        self.cells.indexed_iter()
+            .flat_map(|(index, cell)| {
+                cell.entities.iter()
+                    .filter(move |&(_, grid_entity)| grid_entity.parent == index)
+            })
+            .map(|(id, grid_entity)| (*id, grid_entity.entity.clone()))
+            .collect()
+    }
+}
+
+impl Cursor {
+    pub fn to_saved(&self) -> SavedCursor {
+        SavedCursor {
+            cell: self.cell,
+            direction: self.direction.clone(),
+            range: self.range,
+            threads: self.threads,
+        }
+    }
+
+    pub fn from_saved(saved: SavedCursor) -> Cursor {
+        let mut cursor = Cursor::new(saved.range, saved.direction, saved.cell);
+        cursor.set_threads(saved.threads);
+        cursor
+    }
+}
@@ -0,0 +1,119 @@
+use entities::{Entity, EntityType};
+use entities::structure;
+use map::{DistanceMetric, EntityFilter, EntityMatch, Grid};
+use std::cmp;
+use std::collections::HashSet;
+
+impl DistanceMetric {
+    pub fn distance(&self, from: (usize, usize), to: (usize, usize)) -> f64 {
+        let dx = (from.0 as f64 - to.0 as f64).abs();
+        let dy = (from.1 as f64 - to.1 as f64).abs();
+
+        match *self {
+            DistanceMetric::Chebyshev => dx.max(dy),
+            DistanceMetric::Manhattan => dx + dy,
+            DistanceMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+        }
+    }
+}
+
+impl<'a> EntityFilter<'a> {
+    //matches all entities
+    pub fn any() -> EntityFilter<'a> {
+        EntityFilter {
+            entity_type: None,
+            structure_type: None,
+            predicate: None,
+        }
+    }
+
+    pub fn with_entity_type(mut self, entity_type: EntityType) -> EntityFilter<'a> {
+        self.entity_type = Some(entity_type);
+        self
+    }
+
+    //only structures of the specified type will match
+    pub fn with_structure_type(mut self, structure_type: structure::Type) -> EntityFilter<'a> {
+        self.structure_type = Some(structure_type);
+        self
+    }
+
+    pub fn with_predicate(mut self, predicate: &'a dyn Fn(&Entity) -> bool) -> EntityFilter<'a> {
+        self.predicate = Some(predicate);
+        self
+    }
+
+    pub fn matches(&self, entity: &Entity) -> bool {
+        let is_expected_entity_type = self.entity_type.as_ref().is_none_or(|expected| entity.entity_type() == *expected);
+
+        let is_expected_structure_type = self.structure_type.as_ref().is_none_or(|expected| {
+            match *entity {
+                Entity::Structure { ref props, .. } => props.structure_type == *expected,
+                _ => false
+            }
+        });
+
+        is_expected_entity_type && is_expected_structure_type && self.predicate.is_none_or(|predicate| predicate(entity))
+    }
+}
+
+impl Grid {
+    //retrieves all matching entities occupying at least one cell between `from` and `to` (inclusive), ordered by ID
+    pub fn entities_in_rect(&self, from: (usize, usize), to: (usize, usize), filter: &EntityFilter) -> Vec<EntityMatch> {
+        let (from, to) = (
+            (cmp::min(from.0, to.0), cmp::min(from.1, to.1)),
+            (cmp::max(from.0, to.0), cmp::max(from.1, to.1)),
+        );
+
+        self.matching_entities(from, to, filter, |cell| {
+            cell.0 >= from.0 && cell.0 <= to.0 && cell.1 >= from.1 && cell.1 <= to.1
+        })
+    }
+
+    //retrieves all matching entities with at least one cell no further than `radius` from `center`, ordered by ID
+    pub fn entities_within(&self, center: (usize, usize), radius: usize, metric: DistanceMetric, filter: &EntityFilter) -> Vec<EntityMatch> {
+        let from = (center.0.saturating_sub(radius), center.1.saturating_sub(radius));
+        let to = (center.0.saturating_add(radius), center.1.saturating_add(radius));
+
+        self.matching_entities(from, to, filter, |cell| metric.distance(center, cell) <= radius as f64)
+    }
+
+    //retrieves all matching entities occupying cells on the path, in the order they are first encountered
+    pub fn entities_along(&self, path: &[(usize, usize)], filter: &EntityFilter) -> Vec<EntityMatch> {
+        let mut visited = HashSet::new();
+
+        path.iter()
+            .filter(|cell| self.is_cell_in_grid(**cell))
+            .flat_map(|cell| self.cells[*cell].entities.iter())
+            .filter(|&(id, _)| visited.insert(*id))
+            .filter(|&(_, grid_entity)| filter.matches(&grid_entity.entity))
+            .map(|(id, grid_entity)| {
+                EntityMatch {
+                    id: *id,
+                    parent: grid_entity.parent,
+                    entity: grid_entity.entity.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn matching_entities<F>(&self, from: (usize, usize), to: (usize, usize), filter: &EntityFilter, is_cell_included: F) -> Vec<EntityMatch>
+        where F: Fn((usize, usize)) -> bool
+    {
+        self.indexed_candidates(from, to)
+            .into_iter()
+            .filter_map(|id| self.locate(&id).map(|location| (id, location)))
+            .filter(|&(_, location)| location.cells.iter().any(|cell| is_cell_included(*cell)))
+            .filter_map(|(id, location)| {
+                self.entity(location.parent, &id).map(|entity| {
+                    EntityMatch {
+                        id,
+                        parent: location.parent,
+                        entity,
+                    }
+                })
+            })
+            .filter(|found| filter.matches(&found.entity))
+            .collect()
+    }
+}
@@ -0,0 +1,134 @@
+use entities::{Entity, State};
+use entities::doodad::Doodad;
+use map::{Action, Disaster, DisasterEvent, Grid, RiskRules};
+use std::collections::BTreeSet;
+use std::mem;
+use std::sync::Arc;
+use uuid::Uuid;
+
+//name of the doodads left behind by destroyed structures
+pub const RUBBLE: &str = "rubble";
+
+impl Default for RiskRules {
+    fn default() -> RiskRules {
+        RiskRules {
+            fire_threshold: 100,
+            collapse_threshold: 100,
+            fire_spread: None,
+        }
+    }
+}
+
+impl Grid {
+    //enables the fire and collapse simulation; it is disabled when no rules are set
+    pub fn set_risk_rules(&mut self, rules: Option<RiskRules>) {
+        self.risk_rules = rules;
+    }
+
+    pub fn risk_rules(&self) -> Option<&RiskRules> {
+        self.risk_rules.as_ref()
+    }
+
+    //returns all structures destroyed by disasters since the last call
+    pub fn take_disaster_events(&mut self) -> Vec<DisasterEvent> {
+        mem::take(&mut self.disaster_events)
+    }
+
+    //increases the risks of all structures based on their type; structures that reached a threshold are destroyed
+    //and replaced by rubble when the queued actions are processed
+    pub(super) fn update_risks(&mut self) {
+        let rules = match self.risk_rules.clone() {
+            Some(rules) => rules,
+            None => return
+        };
+
+        let structures: Vec<((usize, usize), Uuid)> = self.cells.indexed_iter()
+            .flat_map(|(index, cell)| {
+                cell.entities.iter()
+                    .filter(move |&(_, grid_entity)| {
+                        match *grid_entity.entity {
+                            Entity::Structure { .. } => grid_entity.parent == index && grid_entity.state.is_operational(),
+                            _ => false
+                        }
+                    })
+                    .map(move |(id, _)| (index, *id))
+            })
+            .collect();
+
+        let mut disasters = Vec::new();
+
+        for (parent, id) in structures {
+            let entity = match self.entity(parent, &id) {
+                Some(entity) => entity,
+                None => continue
+            };
+
+            let mut updated_entity = (*entity).clone();
+
+            if let Entity::Structure { ref props, ref mut state, .. } = updated_entity {
+                let increase = props.structure_type.risk_increase();
+                state.risk.fire = state.risk.fire.saturating_add(increase.fire);
+                state.risk.damage = state.risk.damage.saturating_add(increase.damage);
+
+                if state.risk.fire >= rules.fire_threshold {
+                    disasters.push((parent, id, Self::entity_cells(&props.size, parent), Disaster::Fire));
+                } else if state.risk.damage >= rules.collapse_threshold {
+                    disasters.push((parent, id, Self::entity_cells(&props.size, parent), Disaster::Collapse));
+                }
+            }
+
+            self.replace_entity(parent, &id, Arc::new(updated_entity));
+        }
+
+        let destroyed: BTreeSet<Uuid> = disasters.iter().map(|&(_, id, _, _)| id).collect();
+
+        for (parent, id, cells, disaster) in disasters {
+            let _ = self.set_entity_state(parent, &id, State::Destroyed);
+            self.actions.push(Action::DestroyStructure { at: parent, id });
+
+            for cell in &cells {
+                self.actions.push(Action::AddEntity {
+                    at: *cell,
+                    entity: Entity::Doodad { props: Doodad { name: RUBBLE.to_owned(), is_removable: true } },
+                });
+            }
+
+            if let (Disaster::Fire, Some(spread)) = (&disaster, rules.fire_spread) {
+                self.spread_fire(&cells, spread, &destroyed);
+            }
+
+            self.disaster_events.push(DisasterEvent { structure: id, at: parent, disaster, state: State::Destroyed });
+        }
+    }
+
+    //adds fire risk to all other structures next to the burning cells
+    fn spread_fire(&mut self, burning: &[(usize, usize)], spread: u8, destroyed: &BTreeSet<Uuid>) {
+        let neighbours: BTreeSet<(Uuid, (usize, usize))> = burning.iter()
+            .flat_map(|cell| Self::neighbours_of(cell, false))
+            .flatten()
+            .filter_map(|neighbour| self.cells.get(neighbour))
+            .flat_map(|neighbour| {
+                neighbour.entities.iter()
+                    .filter(|&(id, grid_entity)| {
+                        match *grid_entity.entity {
+                            Entity::Structure { .. } => !destroyed.contains(id),
+                            _ => false
+                        }
+                    })
+                    .map(|(id, grid_entity)| (*id, grid_entity.parent))
+            })
+            .collect();
+
+        for (id, parent) in neighbours {
+            if let Some(entity) = self.entity(parent, &id) {
+                let mut updated_entity = (*entity).clone();
+
+                if let Entity::Structure { ref mut state, .. } = updated_entity {
+                    state.risk.fire = state.risk.fire.saturating_add(spread);
+                }
+
+                self.replace_entity(parent, &id, Arc::new(updated_entity));
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use entities::Entity;
+use map::{Grid, RoadEvent, RoadNetwork};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::mem;
+use uuid::Uuid;
+
+impl RoadNetwork {
+    pub(super) fn new() -> RoadNetwork {
+        RoadNetwork {
+            components: HashMap::new(),
+            members: HashMap::new(),
+            next_component: 0,
+            events: Vec::new(),
+        }
+    }
+
+    fn component_at(&self, cell: (usize, usize)) -> Option<usize> {
+        self.components.get(&cell).cloned()
+    }
+
+    fn road_neighbours_of(&self, cell: (usize, usize)) -> Vec<(usize, usize)> {
+        Grid::neighbours_of(&cell, false).into_iter()
+            .flatten()
+            .filter(|neighbour| self.components.contains_key(neighbour))
+            .collect()
+    }
+
+    fn new_component(&mut self, cells: BTreeSet<(usize, usize)>) -> usize {
+        let component = self.next_component;
+        self.next_component += 1;
+
+        for cell in &cells {
+            self.components.insert(*cell, component);
+        }
+
+        self.members.insert(component, cells);
+        component
+    }
+
+    //joins the road to the components next to it; all of them are merged into the one with the lowest ID
+    fn connect(&mut self, cell: (usize, usize)) {
+        if self.components.contains_key(&cell) {
+            return;
+        }
+
+        let neighbouring: BTreeSet<usize> = self.road_neighbours_of(cell).into_iter()
+            .filter_map(|neighbour| self.component_at(neighbour))
+            .collect();
+
+        match neighbouring.iter().next().cloned() {
+            Some(target) => {
+                for component in neighbouring.iter().skip(1) {
+                    let cells = self.members.remove(component).unwrap_or_default();
+
+                    for merged in &cells {
+                        self.components.insert(*merged, target);
+                    }
+
+                    self.members.entry(target).or_default().extend(cells);
+                }
+
+                self.components.insert(cell, target);
+                self.members.entry(target).or_default().insert(cell);
+            }
+
+            None => {
+                let mut cells = BTreeSet::new();
+                cells.insert(cell);
+                self.new_component(cells);
+            }
+        }
+    }
+
+    //removes the road from its component and splits the component if the road was connecting parts of it
+    fn disconnect(&mut self, cell: (usize, usize)) {
+        let component = match self.components.remove(&cell) {
+            Some(component) => component,
+            None => return
+        };
+
+        let mut remaining = self.members.remove(&component).unwrap_or_default();
+        remaining.remove(&cell);
+
+        let mut parts: Vec<BTreeSet<(usize, usize)>> = Vec::new();
+
+        for start in self.road_neighbours_of(cell) {
+            if parts.iter().any(|part| part.contains(&start)) {
+                continue;
+            }
+
+            let mut part = BTreeSet::new();
+            let mut queue = VecDeque::new();
+            part.insert(start);
+            queue.push_back(start);
+
+            while let Some(current) = queue.pop_front() {
+                for neighbour in self.road_neighbours_of(current) {
+                    if remaining.contains(&neighbour) && part.insert(neighbour) {
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+
+            parts.push(part);
+        }
+
+        if parts.is_empty() {
+            return; //the last road of the component was removed
+        }
+
+        let mut parts = parts.into_iter();
+        let first = parts.next().unwrap_or_default();
+        let mut into = vec![component];
+
+        for part in parts {
+            into.push(self.new_component(part));
+        }
+
+        self.members.insert(component, first);
+
+        if into.len() > 1 {
+            self.events.push(RoadEvent::Split { component, into });
+        }
+    }
+
+    fn clear(&mut self) {
+        self.components.clear();
+        self.members.clear();
+        self.next_component = 0;
+        self.events.clear();
+    }
+}
+
+impl Grid {
+    fn has_road(&self, cell: (usize, usize)) -> bool {
+        self.cells.get(cell)
+            .map(|cell_data| cell_data.entities.values().any(|grid_entity| *grid_entity.entity == Entity::Road))
+            .unwrap_or(false)
+    }
+
+    //keeps the road network up to date after an entity was added to or removed from the cell
+    pub(super) fn update_road_network(&mut self, cell: (usize, usize)) {
+        if self.has_road(cell) {
+            self.roads.connect(cell);
+        } else {
+            self.roads.disconnect(cell);
+        }
+    }
+
+    pub(super) fn rebuild_road_network(&mut self) {
+        self.roads.clear();
+
+        let roads: Vec<(usize, usize)> = self.cells.indexed_iter()
+            .filter(|&(_, cell)| cell.entities.values().any(|grid_entity| *grid_entity.entity == Entity::Road))
+            .map(|(index, _)| index)
+            .collect();
+
+        for road in roads {
+            self.roads.connect(road);
+        }
+    }
+
+    //the ID of the road network containing the cell; `None` if there is no road in it
+    pub fn road_component_at(&self, cell: (usize, usize)) -> Option<usize> {
+        self.roads.component_at(cell)
+    }
+
+    //checks if both cells contain roads that are connected to each other
+    pub fn are_road_connected(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        match (self.roads.component_at(a), self.roads.component_at(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false
+        }
+    }
+
+    //the road network a structure's walkers are spawned on; `None` if the structure has no road access
+    pub fn road_component_of(&self, structure_id: &Uuid) -> Option<usize> {
+        self.locate(structure_id)
+            .and_then(|location| self.find_first_adjacent_road(location.parent, structure_id))
+            .and_then(|road| self.roads.component_at(road))
+    }
+
+    //the road networks next to any of the structure's cells
+    pub(super) fn road_components_next_to(&self, structure_id: &Uuid) -> BTreeSet<usize> {
+        self.locate(structure_id)
+            .map(|location| {
+                location.cells.iter()
+                    .flat_map(|cell| Self::neighbours_of(cell, false))
+                    .flatten()
+                    .filter(|neighbour| !location.cells.contains(neighbour))
+                    .filter_map(|neighbour| self.roads.component_at(neighbour))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    //checks if both structures are next to the same road network; this is only enforced for commodity transfers
+    //between two structures (`Action::TransferCommodity`), deliveries to or from walkers and resources are not gated
+    pub fn is_reachable_by_road(&self, source_id: &Uuid, target_id: &Uuid) -> bool {
+        !self.road_components_next_to(source_id).is_disjoint(&self.road_components_next_to(target_id))
+    }
+
+    //returns all changes to the road network since the last call
+    pub fn take_road_events(&mut self) -> Vec<RoadEvent> {
+        mem::take(&mut self.roads.events)
+    }
+}
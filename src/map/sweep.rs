@@ -0,0 +1,304 @@
+use effects::Effect;
+use entities::Entity;
+use map::{Action, Cell, Cursor, CursorError, Direction, Grid, GridEntity};
+use map::cursor::ExchangeUpdate;
+use ndarray::{Array2, ArrayViewMut2, Axis};
+use production::WalkerStage;
+use production::exchange::{CommodityExchange, ExchangeError};
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::thread;
+use uuid::Uuid;
+
+//position of a change in a sequential sweep: step of the cursor, phase of the step, index of the effect,
+//affected cell, affected entity and position among the changes of the entity
+type SweepOrder = (usize, u8, usize, (usize, usize), Uuid, usize);
+
+const EFFECTS_PHASE: u8 = 0;
+const PRODUCTION_PHASE: u8 = 1;
+const SPAWNING_PHASE: u8 = 2;
+
+//something that happens to all entities of a cell during a sweep
+enum SweepEvent {
+    //the effects of a cell in range are applied
+    Effects { step: usize, source: (usize, usize) },
+    //the entities in the cell produce
+    Production { step: usize },
+}
+
+impl SweepEvent {
+    fn step(&self) -> usize {
+        match *self {
+            SweepEvent::Effects { step, .. } => step,
+            SweepEvent::Production { step } => step,
+        }
+    }
+
+    fn phase(&self) -> u8 {
+        match *self {
+            SweepEvent::Effects { .. } => EFFECTS_PHASE,
+            SweepEvent::Production { .. } => PRODUCTION_PHASE,
+        }
+    }
+}
+
+//a walker produced by its origin structure, with the origin as it was right after producing
+struct WalkerRequest {
+    step: usize,
+    cell: (usize, usize),
+    origin: Uuid,
+    origin_entity: Arc<Entity>,
+    stage: WalkerStage,
+}
+
+#[derive(Default)]
+struct SweepChanges {
+    actions: Vec<(SweepOrder, Action)>,
+    updates: Vec<(SweepOrder, ExchangeUpdate)>,
+    walkers: Vec<WalkerRequest>,
+}
+
+impl SweepChanges {
+    fn append(&mut self, mut other: SweepChanges) {
+        self.actions.append(&mut other.actions);
+        self.updates.append(&mut other.updates);
+        self.walkers.append(&mut other.walkers);
+    }
+
+    //drops the changes of the entity in the cells that were made after the step
+    fn discard(&mut self, id: &Uuid, cells: &[(usize, usize)], after_step: usize) {
+        let is_kept = |order: &SweepOrder| order.0 <= after_step || order.4 != *id || !cells.contains(&order.3);
+
+        self.actions.retain(|(order, _)| is_kept(order));
+        self.updates.retain(|(order, _)| is_kept(order));
+        self.walkers.retain(|request| request.step <= after_step || request.origin != *id || !cells.contains(&request.cell));
+    }
+
+    //removes the walker that is spawned first in a sequential sweep
+    fn next_walker(&mut self) -> Option<WalkerRequest> {
+        self.walkers.iter()
+            .enumerate()
+            .min_by_key(|&(_, request)| (request.step, request.origin))
+            .map(|(index, _)| index)
+            .map(|index| self.walkers.swap_remove(index))
+    }
+}
+
+//everything needed for processing the cells of a sweep independently of each other; nothing in it changes
+//until the last cell of the sweep is reached
+struct SweepPlan {
+    //position of each cell in the sweep
+    steps: Array2<usize>,
+    last_step: usize,
+    last_cell: (usize, usize),
+    range: usize,
+    effects: HashMap<(usize, usize), Vec<Arc<dyn Effect>>>,
+    is_output_staffed: bool,
+}
+
+impl SweepPlan {
+    fn new(grid: &Grid, direction: &Direction, range: usize) -> SweepPlan {
+        let mut steps = Array2::zeros((grid.width, grid.height));
+        let mut cell = (0, 0);
+        let mut last_cell = cell;
+
+        for step in 0..(grid.width * grid.height) {
+            steps[cell] = step;
+            last_cell = cell;
+            cell = Cursor::calculate_next_cell(cell.0 as isize, cell.1 as isize, grid.width as isize, grid.height as isize, direction);
+        }
+
+        SweepPlan {
+            steps,
+            last_step: grid.width * grid.height - 1,
+            last_cell,
+            range,
+            effects: grid.cells.indexed_iter()
+                .filter(|&(_, cell)| !cell.active_effects.is_empty())
+                .map(|(index, cell)| (index, cell.active_effects.clone()))
+                .collect(),
+            is_output_staffed: grid.labour_rules.is_some(),
+        }
+    }
+
+    //everything that happens to the cell before the cursor reaches the last cell of the sweep, in order
+    fn events(&self, cell: (usize, usize)) -> Vec<SweepEvent> {
+        let (width, height) = self.steps.dim();
+        let cols = cell.0.saturating_sub(self.range)..cmp::min(cell.0 + self.range + 1, width);
+
+        let mut events: Vec<SweepEvent> = cols
+            .flat_map(|x| {
+                (cell.1.saturating_sub(self.range)..cmp::min(cell.1 + self.range + 1, height)).map(move |y| (x, y))
+            })
+            .filter(|source| self.effects.contains_key(source))
+            .map(|source| SweepEvent::Effects { step: self.steps[source], source })
+            .chain(Some(SweepEvent::Production { step: self.steps[cell] }))
+            .filter(|event| event.step() < self.last_step)
+            .collect();
+
+        events.sort_by_key(|event| (event.step(), event.phase()));
+        events
+    }
+
+    //runs the events from the step onwards on the copy of the entity in the cell
+    fn process_entity(&self, cell: (usize, usize), events: &[SweepEvent], from_step: usize, id: &Uuid, grid_entity: &mut GridEntity, changes: &mut SweepChanges) {
+        for event in events.iter().filter(|event| event.step() >= from_step) {
+            let mut actions = Vec::new();
+
+            match *event {
+                SweepEvent::Effects { step, source } => {
+                    for (index, effect) in self.effects[&source].iter().enumerate() {
+                        Cursor::apply_effect(effect, id, grid_entity, &mut actions);
+
+                        changes.actions.extend(
+                            actions.drain(..).enumerate()
+                                .map(|(seq, action)| ((step, EFFECTS_PHASE, index, cell, *id, seq), action))
+                        );
+                    }
+                }
+
+                SweepEvent::Production { step } => {
+                    let (updates, walker_stage) = Cursor::produce(cell, id, grid_entity, self.is_output_staffed, &mut actions);
+
+                    changes.actions.extend(
+                        actions.into_iter().enumerate()
+                            .map(|(seq, action)| ((step, PRODUCTION_PHASE, 0, cell, *id, seq), action))
+                    );
+
+                    changes.updates.extend(
+                        updates.into_iter().enumerate()
+                            .map(|(seq, (commodity, state))| {
+                                ((step, PRODUCTION_PHASE, 0, cell, *id, seq), (grid_entity.entity.clone(), *id, commodity, state))
+                            })
+                    );
+
+                    if let Some(stage) = walker_stage {
+                        changes.walkers.push(WalkerRequest { step, cell, origin: *id, origin_entity: grid_entity.entity.clone(), stage });
+                    }
+                }
+            }
+        }
+    }
+
+    //runs the events of all cells in a chunk of columns starting at the supplied column
+    fn process_cells(&self, mut cells: ArrayViewMut2<Cell>, first_col: usize) -> SweepChanges {
+        let mut changes = SweepChanges::default();
+
+        for ((x, y), cell) in cells.indexed_iter_mut() {
+            if cell.entities.is_empty() {
+                continue;
+            }
+
+            let at = (x + first_col, y);
+            let events = self.events(at);
+
+            for (id, grid_entity) in cell.entities.iter_mut() {
+                self.process_entity(at, &events, 0, id, grid_entity, &mut changes);
+            }
+        }
+
+        changes
+    }
+}
+
+impl Cursor {
+    //processes a whole sweep, starting at the first cell of the grid; between two sweeps, every copy of an entity
+    //only changes when the cursor is in range of its cell, so chunks of columns are processed concurrently;
+    //walkers are then spawned and the changes committed in the order of a sequential sweep,
+    //giving the same results as calling `process_and_advance` for every cell
+    pub(super) fn process_sweep_concurrently(&mut self, grid: &mut Grid, exchange: &mut CommodityExchange) -> Result<(), CursorError> {
+        let plan = SweepPlan::new(grid, &self.direction, self.range);
+
+        let mut changes = {
+            let plan = &plan;
+            let chunk_size = grid.width.div_ceil(self.threads);
+
+            thread::scope(|scope| {
+                let handles: Vec<_> = grid.cells.axis_chunks_iter_mut(Axis(0), chunk_size)
+                    .enumerate()
+                    .map(|(i, chunk)| scope.spawn(move || plan.process_cells(chunk, i * chunk_size)))
+                    .collect();
+
+                handles.into_iter()
+                    .fold(SweepChanges::default(), |mut acc, handle| {
+                        acc.append(handle.join().unwrap());
+                        acc
+                    })
+            })
+        };
+
+        //walkers change the grid and take IDs from its generator, so they are spawned one by one; going through
+        //the rest of the sweep again can produce more of them
+        let mut placement_failures = BTreeMap::new();
+
+        while let Some(request) = changes.next_walker() {
+            let WalkerRequest { step, cell, origin, origin_entity, stage } = request;
+
+            let origin_cells = match (origin_entity.as_ref(), grid.cells[cell].entities.get(&origin)) {
+                (Entity::Structure { props, .. }, Some(grid_entity)) => Grid::entity_cells(&props.size, grid_entity.parent),
+                _ => vec![cell]
+            };
+
+            match Self::place_walker(grid, cell, origin, &origin_entity, stage) {
+                Ok((spawned_walker, updates)) => {
+                    changes.updates.extend(
+                        updates.into_iter().enumerate()
+                            .map(|(seq, update)| ((step, SPAWNING_PHASE, 0, cell, origin, seq), update))
+                    );
+
+                    //the origin and the new walker go through the rest of the sweep from their state after spawning
+                    changes.discard(&origin, &origin_cells, step);
+
+                    let copies = origin_cells.into_iter()
+                        .map(|origin_cell| (origin_cell, origin))
+                        .chain(Some((spawned_walker.cell, spawned_walker.walker)));
+
+                    for (copy_cell, id) in copies {
+                        let events = plan.events(copy_cell);
+
+                        if let Some(grid_entity) = grid.cells[copy_cell].entities.get_mut(&id) {
+                            plan.process_entity(copy_cell, &events, step + 1, &id, grid_entity, &mut changes);
+                        }
+                    }
+
+                    self.spawned_walkers.push(spawned_walker);
+                }
+
+                Err(e) => {
+                    placement_failures.insert(step, e);
+                }
+            }
+        }
+
+        changes.actions.sort_by_key(|&(order, _)| order);
+        grid.actions.extend(changes.actions.into_iter().map(|(_, action)| action));
+
+        changes.updates.sort_by_key(|&(order, _)| order);
+
+        let mut exchange_failures: BTreeMap<usize, Vec<ExchangeError>> = BTreeMap::new();
+
+        for ((step, ..), (entity, id, commodity, state)) in changes.updates {
+            if let Err(e) = exchange.update_state(entity, &id, &commodity, state) {
+                exchange_failures.entry(step).or_default().push(e);
+            }
+        }
+
+        //as with `process_and_advance`, a failed placement hides the exchange failures of its step
+        let failure = match (placement_failures.into_iter().next(), exchange_failures.into_iter().next()) {
+            (Some((step, e)), Some((exchange_step, _))) if step <= exchange_step => Some(CursorError::ForGrid { e }),
+            (Some((_, e)), None) => Some(CursorError::ForGrid { e }),
+            (_, Some((_, errors))) => Some(CursorError::ForExchange { errors }),
+            (None, None) => None
+        };
+
+        //the last cell also finishes the sweep, which changes the whole grid
+        self.cell = plan.last_cell;
+        let result = self.process_and_advance(grid, exchange);
+
+        match failure {
+            Some(e) => Err(e),
+            None => result
+        }
+    }
+}
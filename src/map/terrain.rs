@@ -0,0 +1,57 @@
+use entities::Entity;
+use map::{CellState, Grid, GridError, Terrain};
+
+impl Terrain {
+    //structures can only be placed on firm, dry ground
+    pub fn is_buildable(&self) -> bool {
+        match *self {
+            Terrain::Grass | Terrain::Sand | Terrain::Floodplain => true,
+            Terrain::Water | Terrain::Rock | Terrain::Marsh => false,
+        }
+    }
+
+    //cost of moving into a cell with this terrain; `None` if the terrain cannot be crossed on foot
+    pub fn movement_cost(&self) -> Option<usize> {
+        match *self {
+            Terrain::Grass | Terrain::Floodplain => Some(1),
+            Terrain::Sand => Some(2),
+            Terrain::Marsh => Some(3),
+            Terrain::Rock => Some(4),
+            Terrain::Water => None,
+        }
+    }
+}
+
+impl Grid {
+    pub fn terrain(&self, at: (usize, usize)) -> Option<Terrain> {
+        self.cells.get(at).map(|cell| cell.terrain.clone())
+    }
+
+    //changes the terrain of a cell; entities already in the cell are not affected
+    pub fn set_terrain(&mut self, at: (usize, usize), terrain: Terrain) -> Result<CellState, GridError> {
+        match self.cell_state(at) {
+            CellState::OutOfBounds => {
+                Err(GridError::CellUnavailable)
+            }
+
+            state => {
+                self.cells[at].terrain = terrain;
+                self.invalidate_paths_at(&[at]);
+                Ok(state)
+            }
+        }
+    }
+
+    //roads can always be crossed at the lowest cost, regardless of the terrain under them
+    pub fn movement_cost(&self, at: (usize, usize)) -> Option<usize> {
+        self.cells.get(at).and_then(|cell| {
+            let has_road = cell.entities.values().any(|grid_entity| *grid_entity.entity == Entity::Road);
+
+            if has_road { Some(1) } else { cell.terrain.movement_cost() }
+        })
+    }
+
+    pub(super) fn is_terrain_buildable(&self, cells: &[(usize, usize)]) -> bool {
+        cells.iter().all(|cell| self.cells.get(*cell).is_some_and(|cell| cell.terrain.is_buildable()))
+    }
+}
@@ -0,0 +1,100 @@
+use entities::{Entity, EntityType};
+use map::{DistanceMetric, EntityFilter, Grid};
+
+//all path costs are expressed in fractions of a single step on flat grass
+pub const PATH_COST_SCALE: usize = 100;
+
+//extra cost of a diagonal step on flat ground, so that it costs sqrt(2) steps in total
+const DIAGONAL_PENALTY: usize = 41;
+
+//decides how expensive it is to move between two neighbouring cells; `None` if the move is not allowed;
+//costs used for pathfinding have to be at least `PATH_COST_SCALE` for every step
+pub trait PathCost: Send + Sync {
+    fn cost(&self, grid: &Grid, from: (usize, usize), to: (usize, usize)) -> Option<usize>;
+}
+
+//every step costs the same, regardless of the terrain
+pub struct UnitCost;
+
+//the cost of the terrain and the slope between the cells; roads are always crossed at the lowest cost
+pub struct TerrainCost;
+
+//same as `TerrainCost`, but every step that does not end on a road costs `off_road_penalty` more
+pub struct RoadCost {
+    pub off_road_penalty: usize,
+}
+
+//adds to the cost of diagonal steps; meant to be combined with other costs
+pub struct DiagonalPenalty;
+
+//adds `penalty` to steps into cells with desirability below `min_desirability`; meant to be combined with other costs
+pub struct DesirabilityPenalty {
+    pub min_desirability: i8,
+    pub penalty: usize,
+}
+
+//adds `penalty` to steps into cells next to structures with a fire or damage risk above `max_risk`;
+//meant to be combined with other costs
+pub struct DangerPenalty {
+    pub max_risk: u8,
+    pub penalty: usize,
+}
+
+//sum of all costs; a move is only allowed if all of them allow it
+pub struct CombinedCost {
+    pub costs: Vec<Box<dyn PathCost>>,
+}
+
+impl PathCost for UnitCost {
+    fn cost(&self, grid: &Grid, _from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        if grid.is_cell_in_grid(to) { Some(PATH_COST_SCALE) } else { None }
+    }
+}
+
+impl PathCost for TerrainCost {
+    fn cost(&self, grid: &Grid, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        grid.movement_cost_between(from, to).map(|cost| cost * PATH_COST_SCALE)
+    }
+}
+
+impl PathCost for RoadCost {
+    fn cost(&self, grid: &Grid, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        let is_road = !grid.entities_along(&[to], &EntityFilter::any().with_entity_type(EntityType::Road)).is_empty();
+
+        TerrainCost.cost(grid, from, to).map(|cost| if is_road { cost } else { cost + self.off_road_penalty })
+    }
+}
+
+impl PathCost for DiagonalPenalty {
+    fn cost(&self, _grid: &Grid, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        if from.0 != to.0 && from.1 != to.1 { Some(DIAGONAL_PENALTY) } else { Some(0) }
+    }
+}
+
+impl PathCost for DesirabilityPenalty {
+    fn cost(&self, grid: &Grid, _from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        grid.desirability_at(to).map(|desirability| if desirability < self.min_desirability { self.penalty } else { 0 })
+    }
+}
+
+impl PathCost for DangerPenalty {
+    fn cost(&self, grid: &Grid, _from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        let is_dangerous = |entity: &Entity| {
+            match *entity {
+                Entity::Structure { ref state, .. } => state.risk.fire > self.max_risk || state.risk.damage > self.max_risk,
+                _ => false
+            }
+        };
+
+        let filter = EntityFilter::any().with_entity_type(EntityType::Structure).with_predicate(&is_dangerous);
+        let is_in_danger = !grid.entities_within(to, 1, DistanceMetric::Chebyshev, &filter).is_empty();
+
+        Some(if is_in_danger { self.penalty } else { 0 })
+    }
+}
+
+impl PathCost for CombinedCost {
+    fn cost(&self, grid: &Grid, from: (usize, usize), to: (usize, usize)) -> Option<usize> {
+        self.costs.iter().try_fold(0, |total, cost| cost.cost(grid, from, to).map(|cost| total + cost))
+    }
+}
@@ -1,7 +1,62 @@
 use entities::Entity;
+use map::TraversalType;
+
+pub mod cost;
+
+const NO_DESTINATION: Option<(usize, usize)> = None;
 
 //trait implemented on an entity that governs its movement
 pub trait Movable {
-    fn can_traverse(&self, entity: &Option<Entity>) -> bool;
+    fn can_traverse(&self, entity: Option<&Entity>) -> bool;
+    fn can_move_diagonally(&self) -> bool;
     fn destination(&self) -> &Option<(usize, usize)>;
+    fn traversal(&self) -> Option<&TraversalType>;
+}
+
+impl TraversalType {
+    //checks if a walker can enter a cell containing the entity (or an empty cell)
+    pub fn allows(&self, entity: Option<&Entity>, has_destination: bool) -> bool {
+        match entity {
+            None => *self != TraversalType::RoadOnly,
+            Some(&Entity::Road) => *self != TraversalType::EmptyOnly,
+            //roadblocks only let through walkers that are heading somewhere specific
+            Some(&Entity::Roadblock) => *self != TraversalType::EmptyOnly && has_destination,
+            Some(&Entity::Walker { .. }) => true,
+            Some(_) => false
+        }
+    }
+
+    pub fn allows_diagonal_movement(&self) -> bool {
+        *self != TraversalType::RoadOnly
+    }
+}
+
+impl Movable for Entity {
+    fn can_traverse(&self, entity: Option<&Entity>) -> bool {
+        match *self {
+            Entity::Walker { ref props, ref state } => props.traversal.allows(entity, state.destination.is_some()),
+            _ => false //only walkers can move
+        }
+    }
+
+    fn can_move_diagonally(&self) -> bool {
+        match *self {
+            Entity::Walker { ref props, .. } => props.traversal.allows_diagonal_movement(),
+            _ => false
+        }
+    }
+
+    fn destination(&self) -> &Option<(usize, usize)> {
+        match *self {
+            Entity::Walker { ref state, .. } => &state.destination,
+            _ => &NO_DESTINATION
+        }
+    }
+
+    fn traversal(&self) -> Option<&TraversalType> {
+        match *self {
+            Entity::Walker { ref props, .. } => Some(&props.traversal),
+            _ => None
+        }
+    }
 }
@@ -0,0 +1,252 @@
+use bincode;
+use entities::{doodad, resource, structure, walker};
+use entities::{Entity, State};
+use map::{Cursor, Direction, Grid, GridError, HousingLadder, IdGenerator, LabourRules, LabourStats, RiskRules, Terrain};
+use production::exchange::CommodityExchange;
+use registry::{Parameters, Registry, RegistryError};
+use serde::{Serialize, Serializer};
+use serde_json;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use uuid::Uuid;
+
+//incremented whenever the layout of `SavedState` changes; states saved with an earlier version are still loaded,
+//with fields added since then set to their defaults
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum PersistenceError {
+    UnsupportedVersion { version: u32 },
+    ForRegistry { e: RegistryError },
+    ForGrid { e: GridError },
+    Format { message: String },
+    PendingActions,
+}
+
+//identifies a producer or an effect in a `Registry`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedBehavior {
+    pub type_name: String,
+    #[serde(default)]
+    pub parameters: Parameters,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SavedEntity {
+    Road,
+
+    Roadblock,
+
+    Doodad { props: doodad::Doodad },
+
+    Resource {
+        props: resource::ResourceProperties,
+        state: resource::ResourceState,
+        producer: Option<SavedBehavior>,
+    },
+
+    Structure {
+        props: structure::StructureProperties,
+        state: structure::StructureState,
+        producer: Option<SavedBehavior>,
+    },
+
+    Walker {
+        props: walker::WalkerProperties,
+        state: walker::WalkerState,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedGridEntity {
+    pub id: Uuid,
+    pub parent: (usize, usize),
+    pub entity: SavedEntity,
+    #[serde(default)]
+    pub state: State,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedCell {
+    pub desirability: i8,
+    pub terrain: Terrain,
+    pub elevation: u8,
+    //indices into `SavedGrid.effects`
+    pub effects: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedGrid {
+    pub width: usize,
+    pub height: usize,
+    //every entity is stored once, at its parent cell
+    pub entities: Vec<SavedGridEntity>,
+    //cells are stored row by row
+    pub cells: Vec<SavedCell>,
+    //all distinct effects; effects shared between cells are stored once
+    pub effects: Vec<SavedBehavior>,
+    //indices into `effects`
+    pub global_effects: Vec<usize>,
+    pub is_desirability_outdated: bool,
+    pub id_generator: IdGenerator,
+    #[serde(default)]
+    pub risk_rules: Option<RiskRules>,
+    #[serde(default)]
+    pub housing_ladder: Option<HousingLadder>,
+    #[serde(default)]
+    pub labour_rules: Option<LabourRules>,
+    #[serde(default)]
+    pub labour_stats: LabourStats,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedCursor {
+    pub cell: (usize, usize),
+    pub direction: Direction,
+    pub range: usize,
+    #[serde(default)]
+    pub threads: usize,
+}
+
+//ordered maps are used so that the same state is always stored in the same way
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedExchange {
+    //commodity -> entity -> amount
+    pub required: BTreeMap<String, BTreeMap<Uuid, u32>>,
+    pub available: BTreeMap<String, BTreeMap<Uuid, u32>>,
+    pub in_transit: BTreeMap<String, BTreeMap<Uuid, u32>>,
+
+    //commodity -> entities
+    pub producers: BTreeMap<String, Vec<Uuid>>,
+    pub consumers: BTreeMap<String, Vec<Uuid>>,
+
+    pub used: BTreeMap<String, usize>,
+    pub lost: BTreeMap<String, usize>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedState {
+    pub version: u32,
+    pub grid: SavedGrid,
+    pub cursor: SavedCursor,
+    pub exchange: SavedExchange,
+}
+
+impl SavedEntity {
+    pub fn from_entity(entity: &Entity) -> SavedEntity {
+        match *entity {
+            Entity::Road => SavedEntity::Road,
+
+            Entity::Roadblock => SavedEntity::Roadblock,
+
+            Entity::Doodad { ref props } => SavedEntity::Doodad { props: props.clone() },
+
+            Entity::Resource { ref props, ref state, ref producer } => SavedEntity::Resource {
+                props: props.clone(),
+                state: state.clone(),
+                producer: producer.as_ref().map(|p| SavedBehavior { type_name: p.type_name().to_owned(), parameters: p.parameters() }),
+            },
+
+            Entity::Structure { ref props, ref state, ref producer } => SavedEntity::Structure {
+                props: props.clone(),
+                state: state.clone(),
+                producer: producer.as_ref().map(|p| SavedBehavior { type_name: p.type_name().to_owned(), parameters: p.parameters() }),
+            },
+
+            Entity::Walker { ref props, ref state } => SavedEntity::Walker {
+                props: props.clone(),
+                state: state.clone(),
+            },
+        }
+    }
+
+    pub fn into_entity(self, registry: &Registry) -> Result<Entity, PersistenceError> {
+        let create_producer = |producer: Option<SavedBehavior>| {
+            match producer {
+                Some(producer) => {
+                    registry.create_producer(&producer.type_name, &producer.parameters)
+                        .map(Some)
+                        .map_err(|e| PersistenceError::ForRegistry { e })
+                }
+
+                None => Ok(None)
+            }
+        };
+
+        let entity = match self {
+            SavedEntity::Road => Entity::Road,
+
+            SavedEntity::Roadblock => Entity::Roadblock,
+
+            SavedEntity::Doodad { props } => Entity::Doodad { props },
+
+            SavedEntity::Resource { props, state, producer } => Entity::Resource {
+                props,
+                state,
+                producer: create_producer(producer)?,
+            },
+
+            SavedEntity::Structure { props, state, producer } => Entity::Structure {
+                props,
+                state,
+                producer: create_producer(producer)?,
+            },
+
+            SavedEntity::Walker { props, state } => Entity::Walker { props, state },
+        };
+
+        Ok(entity)
+    }
+}
+
+//serializes a `HashMap` with its entries sorted by key
+pub fn serialize_ordered<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where K: Serialize + Ord + Eq + Hash, V: Serialize, S: Serializer
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+//captures the complete simulation state; fails if the grid still has unprocessed actions,
+//as those may hold entities and effects that cannot be stored; reports queued for the caller
+//(events, spawned walkers, finished patrols and action results) are not part of the state
+pub fn save(grid: &Grid, cursor: &Cursor, exchange: &CommodityExchange) -> Result<SavedState, PersistenceError> {
+    if grid.pending_actions() > 0 {
+        return Err(PersistenceError::PendingActions);
+    }
+
+    Ok(SavedState {
+        version: SAVE_FORMAT_VERSION,
+        grid: grid.to_saved(),
+        cursor: cursor.to_saved(),
+        exchange: exchange.to_saved(),
+    })
+}
+
+//recreates the simulation state; entities in the exchange refer to the same instances as the ones in the grid
+pub fn load(state: SavedState, registry: &Registry) -> Result<(Grid, Cursor, CommodityExchange), PersistenceError> {
+    if state.version > SAVE_FORMAT_VERSION {
+        return Err(PersistenceError::UnsupportedVersion { version: state.version });
+    }
+
+    let grid = Grid::from_saved(state.grid, registry)?;
+    let cursor = Cursor::from_saved(state.cursor);
+    let exchange = CommodityExchange::from_saved(state.exchange, &grid.entities_by_id());
+
+    Ok((grid, cursor, exchange))
+}
+
+pub fn to_json(state: &SavedState) -> Result<String, PersistenceError> {
+    serde_json::to_string(state).map_err(|e| PersistenceError::Format { message: e.to_string() })
+}
+
+pub fn from_json(data: &str) -> Result<SavedState, PersistenceError> {
+    serde_json::from_str(data).map_err(|e| PersistenceError::Format { message: e.to_string() })
+}
+
+pub fn to_binary(state: &SavedState) -> Result<Vec<u8>, PersistenceError> {
+    bincode::serialize(state).map_err(|e| PersistenceError::Format { message: e.to_string() })
+}
+
+pub fn from_binary(data: &[u8]) -> Result<SavedState, PersistenceError> {
+    bincode::deserialize(data).map_err(|e| PersistenceError::Format { message: e.to_string() })
+}
@@ -0,0 +1,130 @@
+use entities::Entity;
+use map::Action;
+use production::{Commodity, Producer, ProductionStage, WalkerStage};
+use registry::{self, Parameters, RegistryError};
+use uuid::Uuid;
+
+//name under which `PeriodicProducer` is registered by `Registry::with_builtins`
+pub const PERIODIC_PRODUCER: &str = "PeriodicProducer";
+
+//produces a fixed amount of a commodity every `interval` visits of the cursor; resources stop producing once empty;
+//structures with a delivery cell send everything they have stored to the structure in that cell
+#[derive(Clone)]
+pub struct PeriodicProducer {
+    commodity: String,
+    amount: u32,
+    interval: u32,
+    progress: u32,
+    deliver_to: Option<(usize, usize)>,
+}
+
+impl PeriodicProducer {
+    pub fn new(commodity: &str, amount: u32, interval: u32) -> PeriodicProducer {
+        PeriodicProducer {
+            commodity: commodity.to_owned(),
+            amount,
+            interval,
+            progress: 0,
+            deliver_to: None,
+        }
+    }
+
+    pub fn with_delivery_to(mut self, cell: (usize, usize)) -> PeriodicProducer {
+        self.deliver_to = Some(cell);
+        self
+    }
+
+    //expects `commodity` and `amount`; `interval` defaults to every visit and `deliver_to` ("x,y") to no deliveries
+    pub fn from_parameters(parameters: &Parameters) -> Result<PeriodicProducer, RegistryError> {
+        let deliver_to = match registry::optional_parameter::<String>(parameters, "deliver_to")? {
+            Some(value) => Some(Self::parse_cell(&value).ok_or(RegistryError::ParameterInvalid { name: "deliver_to".to_owned(), value })?),
+            None => None
+        };
+
+        Ok(PeriodicProducer {
+            commodity: registry::parameter(parameters, "commodity")?,
+            amount: registry::parameter(parameters, "amount")?,
+            interval: registry::optional_parameter(parameters, "interval")?.unwrap_or(1),
+            progress: registry::optional_parameter(parameters, "progress")?.unwrap_or(0),
+            deliver_to,
+        })
+    }
+
+    fn parse_cell(value: &str) -> Option<(usize, usize)> {
+        let mut coordinates = value.split(',').map(|coordinate| coordinate.trim().parse().ok());
+
+        match (coordinates.next(), coordinates.next(), coordinates.next()) {
+            (Some(Some(x)), Some(Some(y)), None) => Some((x, y)),
+            _ => None
+        }
+    }
+}
+
+impl Producer for PeriodicProducer {
+    fn produce_commodity(&mut self, entity: &Entity) -> Option<ProductionStage> {
+        match *entity {
+            Entity::Resource { ref state, .. } if state.current_amount == 0 => None,
+
+            Entity::Resource { .. } | Entity::Structure { .. } => {
+                self.progress += 1;
+
+                if self.progress >= self.interval {
+                    self.progress = 0;
+
+                    Some(ProductionStage {
+                        commodity: Commodity { name: self.commodity.clone(), amount: self.amount },
+                        used: Vec::new(),
+                        required: Vec::new(),
+                    })
+                } else {
+                    None
+                }
+            }
+
+            _ => None
+        }
+    }
+
+    fn produce_walker(&mut self, _entity: &Entity) -> Option<WalkerStage> {
+        None
+    }
+
+    fn enqueue_actions(&mut self, entity: &Entity, id: &Uuid, at: (usize, usize), actions: &mut Vec<Action>) {
+        let to = match self.deliver_to {
+            Some(to) => to,
+            None => return
+        };
+
+        if let Entity::Structure { ref state, .. } = *entity {
+            if let Some(&amount) = state.commodities.get(&self.commodity).filter(|&&amount| amount > 0) {
+                actions.push(Action::DeliverCommodity {
+                    from: (at, *id),
+                    to,
+                    commodity: Commodity { name: self.commodity.clone(), amount },
+                });
+            }
+        }
+    }
+
+    fn type_name(&self) -> &str {
+        PERIODIC_PRODUCER
+    }
+
+    fn parameters(&self) -> Parameters {
+        let mut parameters = Parameters::new();
+        parameters.insert("commodity".to_owned(), self.commodity.clone());
+        parameters.insert("amount".to_owned(), self.amount.to_string());
+        parameters.insert("interval".to_owned(), self.interval.to_string());
+        parameters.insert("progress".to_owned(), self.progress.to_string());
+
+        if let Some((x, y)) = self.deliver_to {
+            parameters.insert("deliver_to".to_owned(), format!("{},{}", x, y));
+        }
+
+        parameters
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Producer> {
+        Box::new(self.clone())
+    }
+}
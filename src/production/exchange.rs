@@ -1,8 +1,9 @@
 use entities::Entity;
+use persistence::SavedExchange;
 use production::Commodity;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
-use std::rc::{Rc, Weak};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Weak};
 use uuid::Uuid;
 
 type EntityStatsMap = HashMap<String, HashMap<Uuid, (Weak<Entity>, u32)>>;
@@ -48,7 +49,7 @@ impl CommodityExchange {
         }
     }
 
-    fn do_update(entity_map: &mut EntityStatsMap, entity: Rc<Entity>, entity_id: &Uuid, commodity: &Commodity) -> Result<(), ExchangeError> {
+    fn do_update(entity_map: &mut EntityStatsMap, entity: Arc<Entity>, entity_id: &Uuid, commodity: &Commodity) -> Result<(), ExchangeError> {
         let entity_id = match *entity {
             Entity::Structure { .. } => Some(*entity_id),
             Entity::Walker { .. } => Some(*entity_id),
@@ -63,12 +64,12 @@ impl CommodityExchange {
                     .entry(id) {
                     Entry::Occupied(entry) => {
                         let entry = entry.into_mut();
-                        entry.0 = Rc::downgrade(&entity);
+                        entry.0 = Arc::downgrade(&entity);
                         entry.1 = commodity.amount;
                     }
 
                     Entry::Vacant(entry) => {
-                        entry.insert((Rc::downgrade(&entity), commodity.amount));
+                        entry.insert((Arc::downgrade(&entity), commodity.amount));
                     }
                 }
 
@@ -85,7 +86,7 @@ impl CommodityExchange {
         }
     }
 
-    fn collect_entities(entity_map: &EntityStatsMap, commodity: &str) -> Vec<Rc<Entity>> {
+    fn collect_entities(entity_map: &EntityStatsMap, commodity: &str) -> Vec<Arc<Entity>> {
         entity_map
             .get(commodity)
             .map_or_else(
@@ -125,14 +126,14 @@ impl CommodityExchange {
     }
 
     //adds a new commodity producer to the exchange; removal is not needed
-    pub fn add_producer(&mut self, producer: Rc<Entity>, entity_id: &Uuid, commodity: &str) -> Result<(), ExchangeError> {
+    pub fn add_producer(&mut self, producer: Arc<Entity>, entity_id: &Uuid, commodity: &str) -> Result<(), ExchangeError> {
         let producer_id = match *producer {
             Entity::Structure { .. } => Some(entity_id),
             Entity::Resource { .. } => Some(entity_id),
             _ => None
         };
 
-        let entity_ptr = Rc::downgrade(&producer);
+        let entity_ptr = Arc::downgrade(&producer);
 
         match producer_id {
             Some(id) => {
@@ -173,13 +174,13 @@ impl CommodityExchange {
     }
 
     //adds a new commodity consumer to the exchange; removal is not needed
-    pub fn add_consumer(&mut self, consumer: Rc<Entity>, entity_id: &Uuid, commodity: &str) -> Result<(), ExchangeError> {
+    pub fn add_consumer(&mut self, consumer: Arc<Entity>, entity_id: &Uuid, commodity: &str) -> Result<(), ExchangeError> {
         let consumer_id = match *consumer {
             Entity::Structure { .. } => Some(entity_id),
             _ => None
         };
 
-        let entity_ptr = Rc::downgrade(&consumer);
+        let entity_ptr = Arc::downgrade(&consumer);
 
         match consumer_id {
             Some(id) => {
@@ -218,7 +219,22 @@ impl CommodityExchange {
         }
     }
 
-    pub fn update_state(&mut self, entity: Rc<Entity>, entity_id: &Uuid, commodity: &Commodity, state: CommodityState) -> Result<(), ExchangeError> {
+    //drops all bookkeeping of the entity; commodities it still had are no longer counted
+    pub fn remove_entity(&mut self, entity_id: &Uuid) {
+        for entity_map in &mut [&mut self.required, &mut self.available, &mut self.in_transit] {
+            for entities in entity_map.values_mut() {
+                entities.remove(entity_id);
+            }
+        }
+
+        for entity_map in &mut [&mut self.producers, &mut self.consumers] {
+            for entities in entity_map.values_mut() {
+                entities.remove(entity_id);
+            }
+        }
+    }
+
+    pub fn update_state(&mut self, entity: Arc<Entity>, entity_id: &Uuid, commodity: &Commodity, state: CommodityState) -> Result<(), ExchangeError> {
         match state {
             CommodityState::Required => {
                 Self::do_update(&mut self.required, entity, entity_id, commodity)
@@ -246,19 +262,19 @@ impl CommodityExchange {
         }
     }
 
-    pub fn entities_that_need(&self, commodity: &str) -> Vec<Rc<Entity>> {
+    pub fn entities_that_need(&self, commodity: &str) -> Vec<Arc<Entity>> {
         Self::collect_entities(&self.required, commodity)
     }
 
-    pub fn entities_that_have(&self, commodity: &str) -> Vec<Rc<Entity>> {
+    pub fn entities_that_have(&self, commodity: &str) -> Vec<Arc<Entity>> {
         Self::collect_entities(&self.available, commodity)
     }
 
-    pub fn entities_transporting(&self, commodity: &str) -> Vec<Rc<Entity>> {
+    pub fn entities_transporting(&self, commodity: &str) -> Vec<Arc<Entity>> {
         Self::collect_entities(&self.in_transit, commodity)
     }
 
-    pub fn producers_of(&self, commodity: &str) -> Vec<Rc<Entity>> {
+    pub fn producers_of(&self, commodity: &str) -> Vec<Arc<Entity>> {
         self.producers
             .get(commodity)
             .map_or_else(
@@ -269,7 +285,7 @@ impl CommodityExchange {
             )
     }
 
-    pub fn consumers_of(&self, commodity: &str) -> Vec<Rc<Entity>> {
+    pub fn consumers_of(&self, commodity: &str) -> Vec<Arc<Entity>> {
         self.consumers
             .get(commodity)
             .map_or_else(
@@ -280,6 +296,23 @@ impl CommodityExchange {
             )
     }
 
+    //returns the names of all commodities known to the exchange, sorted
+    pub fn commodities(&self) -> Vec<String> {
+        let mut commodities: Vec<String> = self.required.keys()
+            .chain(self.available.keys())
+            .chain(self.in_transit.keys())
+            .chain(self.producers.keys())
+            .chain(self.consumers.keys())
+            .chain(self.used.keys())
+            .chain(self.lost.keys())
+            .cloned()
+            .collect();
+
+        commodities.sort();
+        commodities.dedup();
+        commodities
+    }
+
     pub fn amount_required_of(&self, commodity: &str) -> usize {
         Self::fold_commodity_value(&self.required, commodity)
     }
@@ -292,6 +325,87 @@ impl CommodityExchange {
         Self::fold_commodity_value(&self.in_transit, commodity)
     }
 
+    fn save_stats(entity_map: &EntityStatsMap) -> BTreeMap<String, BTreeMap<Uuid, u32>> {
+        entity_map.iter()
+            .map(|(commodity, entities)| {
+                (
+                    commodity.clone(),
+                    entities.iter()
+                        .filter(|&(_, entity_data)| entity_data.0.upgrade().is_some())
+                        .map(|(id, entity_data)| (*id, entity_data.1))
+                        .collect()
+                )
+            })
+            .collect()
+    }
+
+    fn save_entities(entity_map: &EntityMap) -> BTreeMap<String, Vec<Uuid>> {
+        entity_map.iter()
+            .map(|(commodity, entities)| {
+                let mut ids: Vec<Uuid> = entities.iter()
+                    .filter(|&(_, entity)| entity.upgrade().is_some())
+                    .map(|(id, _)| *id)
+                    .collect();
+
+                ids.sort();
+
+                (commodity.clone(), ids)
+            })
+            .collect()
+    }
+
+    fn load_stats(saved: BTreeMap<String, BTreeMap<Uuid, u32>>, entities: &HashMap<Uuid, Arc<Entity>>) -> EntityStatsMap {
+        saved.into_iter()
+            .map(|(commodity, amounts)| {
+                (
+                    commodity,
+                    amounts.into_iter()
+                        .filter_map(|(id, amount)| entities.get(&id).map(|entity| (id, (Arc::downgrade(entity), amount))))
+                        .collect()
+                )
+            })
+            .collect()
+    }
+
+    fn load_entities(saved: BTreeMap<String, Vec<Uuid>>, entities: &HashMap<Uuid, Arc<Entity>>) -> EntityMap {
+        saved.into_iter()
+            .map(|(commodity, ids)| {
+                (
+                    commodity,
+                    ids.into_iter()
+                        .filter_map(|id| entities.get(&id).map(|entity| (id, Arc::downgrade(entity))))
+                        .collect()
+                )
+            })
+            .collect()
+    }
+
+    //stores the bookkeeping of all entities that are still alive, by their IDs
+    pub fn to_saved(&self) -> SavedExchange {
+        SavedExchange {
+            required: Self::save_stats(&self.required),
+            available: Self::save_stats(&self.available),
+            in_transit: Self::save_stats(&self.in_transit),
+            producers: Self::save_entities(&self.producers),
+            consumers: Self::save_entities(&self.consumers),
+            used: self.used.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+            lost: self.lost.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+        }
+    }
+
+    //restores the bookkeeping using the supplied entities; IDs without a matching entity are dropped
+    pub fn from_saved(saved: SavedExchange, entities: &HashMap<Uuid, Arc<Entity>>) -> CommodityExchange {
+        CommodityExchange {
+            required: Self::load_stats(saved.required, entities),
+            available: Self::load_stats(saved.available, entities),
+            in_transit: Self::load_stats(saved.in_transit, entities),
+            producers: Self::load_entities(saved.producers, entities),
+            consumers: Self::load_entities(saved.consumers, entities),
+            used: saved.used.into_iter().collect(),
+            lost: saved.lost.into_iter().collect(),
+        }
+    }
+
     pub fn amount_used_of(&self, commodity: &str) -> usize {
         self.used
             .get(commodity)
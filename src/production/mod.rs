@@ -1,7 +1,12 @@
 use entities::Entity;
 use entities::walker::WalkerProperties;
+use map::Action;
+use registry::Parameters;
+use std::any;
 use std::fmt;
+use uuid::Uuid;
 
+pub mod builtin;
 pub mod exchange;
 
 pub struct Commodity {
@@ -15,29 +20,48 @@ pub struct ProductionStage {
     pub required: Vec<Commodity>,
 }
 
-pub trait Producer {
+pub struct WalkerStage {
+    pub walker: WalkerProperties,
+    pub carried: Vec<Commodity>,
+}
+
+pub trait Producer: Send + Sync {
     fn produce_commodity(&mut self, entity: &Entity) -> Option<ProductionStage>;
 
     //TODO - + return walker effects
     //TODO - + pass exchange to fn
-    fn produce_walker(&mut self, entity: &Entity) -> Option<WalkerProperties>;
+    fn produce_walker(&mut self, entity: &Entity) -> Option<WalkerStage>;
+
+    //requests grid changes that are applied at the end of the current sweep
+    fn enqueue_actions(&mut self, _entity: &Entity, _id: &Uuid, _at: (usize, usize), _actions: &mut Vec<Action>) {}
+
+    //stable name used for looking up the producer in a `Registry`; defaults to the name of the type without its path,
+    //so producers registered under any other name have to override it
+    fn type_name(&self) -> &str {
+        any::type_name::<Self>().rsplit("::").next().unwrap_or_default()
+    }
+
+    //values needed for recreating the producer (including its progress) through a `Registry`
+    fn parameters(&self) -> Parameters {
+        Parameters::new()
+    }
 
-    fn clone_boxed(&self) -> Box<Producer>;
+    fn clone_boxed(&self) -> Box<dyn Producer>;
 }
 
-impl Clone for Box<Producer> {
-    fn clone(&self) -> Box<Producer> {
+impl Clone for Box<dyn Producer> {
+    fn clone(&self) -> Box<dyn Producer> {
         self.clone_boxed()
     }
 }
 
-impl PartialEq for Producer {
-    fn eq(&self, _: &Producer) -> bool {
+impl PartialEq for dyn Producer {
+    fn eq(&self, _: &dyn Producer) -> bool {
         true //a producer defines only behavior and should not affect equality
     }
 }
 
-impl fmt::Debug for Box<Producer> {
+impl fmt::Debug for Box<dyn Producer> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         write!(formatter, "Producer {{}}")
     }
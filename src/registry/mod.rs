@@ -0,0 +1,118 @@
+use effects::Effect;
+use effects::builtin::{RISK_INCREASE, RiskIncrease};
+use production::Producer;
+use production::builtin::{PERIODIC_PRODUCER, PeriodicProducer};
+use std::collections::{BTreeMap, HashMap};
+use std::collections::hash_map::Entry;
+use std::str::FromStr;
+use std::sync::Arc;
+
+//named values used for creating producers and effects; all values are stored as strings
+//so that they can be read from any data format
+pub type Parameters = BTreeMap<String, String>;
+
+pub type ProducerFactory = Box<dyn Fn(&Parameters) -> Result<Box<dyn Producer>, RegistryError> + Send + Sync>;
+pub type EffectFactory = Box<dyn Fn(&Parameters) -> Result<Arc<dyn Effect>, RegistryError> + Send + Sync>;
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum RegistryError {
+    ProducerExists,
+    EffectExists,
+    ProducerMissing { name: String },
+    EffectMissing { name: String },
+    ParameterMissing { name: String },
+    ParameterInvalid { name: String, value: String },
+}
+
+//maps the type names of producers and effects to factories that can create them
+#[derive(Default)]
+pub struct Registry {
+    producers: HashMap<String, ProducerFactory>,
+    effects: HashMap<String, EffectFactory>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry {
+            producers: HashMap::new(),
+            effects: HashMap::new(),
+        }
+    }
+
+    //creates a registry with the producers and effects that come with the library
+    pub fn with_builtins() -> Registry {
+        let mut registry = Registry::new();
+
+        let _ = registry.register_producer(PERIODIC_PRODUCER, Box::new(|parameters| {
+            PeriodicProducer::from_parameters(parameters).map(|producer| Box::new(producer) as Box<dyn Producer>)
+        }));
+
+        let _ = registry.register_effect(RISK_INCREASE, Box::new(|parameters| {
+            RiskIncrease::from_parameters(parameters).map(|effect| Arc::new(effect) as Arc<dyn Effect>)
+        }));
+
+        registry
+    }
+
+    pub fn register_producer(&mut self, type_name: &str, factory: ProducerFactory) -> Result<(), RegistryError> {
+        match self.producers.entry(type_name.to_owned()) {
+            Entry::Occupied(_) => Err(RegistryError::ProducerExists),
+
+            Entry::Vacant(entry) => {
+                entry.insert(factory);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn register_effect(&mut self, type_name: &str, factory: EffectFactory) -> Result<(), RegistryError> {
+        match self.effects.entry(type_name.to_owned()) {
+            Entry::Occupied(_) => Err(RegistryError::EffectExists),
+
+            Entry::Vacant(entry) => {
+                entry.insert(factory);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn create_producer(&self, type_name: &str, parameters: &Parameters) -> Result<Box<dyn Producer>, RegistryError> {
+        match self.producers.get(type_name) {
+            Some(factory) => factory(parameters),
+            None => Err(RegistryError::ProducerMissing { name: type_name.to_owned() })
+        }
+    }
+
+    pub fn create_effect(&self, type_name: &str, parameters: &Parameters) -> Result<Arc<dyn Effect>, RegistryError> {
+        match self.effects.get(type_name) {
+            Some(factory) => factory(parameters),
+            None => Err(RegistryError::EffectMissing { name: type_name.to_owned() })
+        }
+    }
+
+    pub fn has_producer(&self, type_name: &str) -> bool {
+        self.producers.contains_key(type_name)
+    }
+
+    pub fn has_effect(&self, type_name: &str) -> bool {
+        self.effects.contains_key(type_name)
+    }
+}
+
+//retrieves and parses a required parameter; meant to be used by factories
+pub fn parameter<T: FromStr>(parameters: &Parameters, name: &str) -> Result<T, RegistryError> {
+    optional_parameter(parameters, name)?.ok_or(RegistryError::ParameterMissing { name: name.to_owned() })
+}
+
+//retrieves and parses a parameter, if it is present; meant to be used by factories
+pub fn optional_parameter<T: FromStr>(parameters: &Parameters, name: &str) -> Result<Option<T>, RegistryError> {
+    match parameters.get(name) {
+        Some(value) => {
+            value.parse()
+                .map(Some)
+                .map_err(|_| RegistryError::ParameterInvalid { name: name.to_owned(), value: value.clone() })
+        }
+
+        None => Ok(None)
+    }
+}
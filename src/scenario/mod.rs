@@ -0,0 +1,229 @@
+use entities::{doodad, resource, structure, walker};
+use map::{Cursor, Direction, Grid, GridBuilder, GridError, HousingLadder, IdGenerator, LabourRules, RiskRules, Terrain};
+use persistence::{PersistenceError, SavedBehavior, SavedEntity};
+use production::exchange::{CommodityExchange, ExchangeError};
+use registry::{Registry, RegistryError};
+use std::collections::HashMap;
+use toml;
+
+#[derive(Eq, PartialEq, Debug)]
+pub enum ScenarioError {
+    Format { message: String },
+    TemplateMissing { name: String },
+    ForRegistry { e: RegistryError },
+    ForGrid { e: GridError },
+    ForExchange { e: ExchangeError },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScenarioGrid {
+    pub width: usize,
+    pub height: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScenarioCursor {
+    pub range: usize,
+    pub direction: Direction,
+    pub start: (usize, usize),
+}
+
+//same as `SavedEntity` but with the entity type as a field, as TOML has no table syntax for enums
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum TemplateEntity {
+    Road,
+
+    Roadblock,
+
+    Doodad { props: doodad::Doodad },
+
+    Resource {
+        props: resource::ResourceProperties,
+        state: resource::ResourceState,
+        producer: Option<SavedBehavior>,
+    },
+
+    Structure {
+        props: structure::StructureProperties,
+        state: structure::StructureState,
+        producer: Option<SavedBehavior>,
+    },
+
+    Walker {
+        props: walker::WalkerProperties,
+        state: walker::WalkerState,
+    },
+}
+
+//an entity description that can be placed any number of times;
+//the entity is registered with the exchange as a producer/consumer of the listed commodities
+#[derive(Clone, Debug, Deserialize)]
+pub struct EntityTemplate {
+    #[serde(flatten)]
+    pub entity: TemplateEntity,
+    #[serde(default)]
+    pub produces: Vec<String>,
+    #[serde(default)]
+    pub consumes: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Placement {
+    pub template: String,
+    pub at: (usize, usize),
+}
+
+//sets the terrain of all cells between `from` and `to` (inclusive); cells not covered by any area are grass
+#[derive(Clone, Debug, Deserialize)]
+pub struct TerrainArea {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub terrain: Terrain,
+}
+
+//sets the elevation of all cells between `from` and `to` (inclusive); cells not covered by any area are at 0
+#[derive(Clone, Debug, Deserialize)]
+pub struct ElevationArea {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub elevation: u8,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CellEffect {
+    pub at: (usize, usize),
+    pub effect: SavedBehavior,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    //number of full sweeps of the cursor over the grid
+    pub sweeps: usize,
+    //when set, entity IDs are generated from the seed and every run of the scenario gives the same results
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub grid: ScenarioGrid,
+    pub cursor: ScenarioCursor,
+    #[serde(default)]
+    pub terrain: Vec<TerrainArea>,
+    #[serde(default)]
+    pub elevation: Vec<ElevationArea>,
+    #[serde(default)]
+    pub templates: HashMap<String, EntityTemplate>,
+    #[serde(default)]
+    pub placements: Vec<Placement>,
+    #[serde(default)]
+    pub global_effects: Vec<SavedBehavior>,
+    #[serde(default)]
+    pub cell_effects: Vec<CellEffect>,
+    //settings of the fire and collapse simulation; structures never burn down or collapse when not set
+    #[serde(default)]
+    pub risk: Option<RiskRules>,
+    //levels houses evolve through; houses do not evolve when not set
+    #[serde(default)]
+    pub housing: Option<HousingLadder>,
+    //settings of the labour market; employee counts are not updated when not set
+    #[serde(default)]
+    pub labour: Option<LabourRules>,
+}
+
+impl From<TemplateEntity> for SavedEntity {
+    fn from(entity: TemplateEntity) -> SavedEntity {
+        match entity {
+            TemplateEntity::Road => SavedEntity::Road,
+            TemplateEntity::Roadblock => SavedEntity::Roadblock,
+            TemplateEntity::Doodad { props } => SavedEntity::Doodad { props },
+            TemplateEntity::Resource { props, state, producer } => SavedEntity::Resource { props, state, producer },
+            TemplateEntity::Structure { props, state, producer } => SavedEntity::Structure { props, state, producer },
+            TemplateEntity::Walker { props, state } => SavedEntity::Walker { props, state },
+        }
+    }
+}
+
+impl Scenario {
+    pub fn from_toml(data: &str) -> Result<Scenario, ScenarioError> {
+        toml::from_str(data).map_err(|e| ScenarioError::Format { message: e.to_string() })
+    }
+
+    //creates the initial simulation state; producers and effects are looked up by name in the supplied registry
+    pub fn build(&self, registry: &Registry) -> Result<(Grid, Cursor, CommodityExchange), ScenarioError> {
+        let mut builder = GridBuilder::new(self.grid.width, self.grid.height);
+
+        if let Some(seed) = self.seed {
+            builder = builder.with_id_generator(IdGenerator::seeded(seed));
+        }
+
+        for area in &self.terrain {
+            for x in area.from.0..(area.to.0 + 1) {
+                for y in area.from.1..(area.to.1 + 1) {
+                    builder = builder.with_terrain((x, y), area.terrain.clone());
+                }
+            }
+        }
+
+        for area in &self.elevation {
+            for x in area.from.0..(area.to.0 + 1) {
+                for y in area.from.1..(area.to.1 + 1) {
+                    builder = builder.with_elevation((x, y), area.elevation);
+                }
+            }
+        }
+
+        for effect in &self.global_effects {
+            builder = builder.with_global_effect(
+                registry.create_effect(&effect.type_name, &effect.parameters).map_err(|e| ScenarioError::ForRegistry { e })?
+            );
+        }
+
+        let mut templates = Vec::with_capacity(self.placements.len());
+
+        for placement in &self.placements {
+            let template = self.templates
+                .get(&placement.template)
+                .ok_or(ScenarioError::TemplateMissing { name: placement.template.clone() })?;
+
+            let entity = SavedEntity::from(template.entity.clone()).into_entity(registry).map_err(|e| {
+                match e {
+                    PersistenceError::ForRegistry { e } => ScenarioError::ForRegistry { e },
+                    e => ScenarioError::Format { message: format!("{:?}", e) }
+                }
+            })?;
+
+            builder = builder.with_entity(placement.at, entity);
+            templates.push(template);
+        }
+
+        let (mut grid, ids) = builder.build().map_err(|e| ScenarioError::ForGrid { e })?;
+
+        for cell_effect in &self.cell_effects {
+            let effect = registry.create_effect(&cell_effect.effect.type_name, &cell_effect.effect.parameters)
+                .map_err(|e| ScenarioError::ForRegistry { e })?;
+
+            grid.add_cell_effect(cell_effect.at, effect).map_err(|e| ScenarioError::ForGrid { e })?;
+        }
+
+        grid.set_risk_rules(self.risk.clone());
+        grid.set_housing_ladder(self.housing.clone());
+        grid.set_labour_rules(self.labour.clone());
+
+        let mut exchange = CommodityExchange::new();
+
+        for ((placement, template), id) in self.placements.iter().zip(templates).zip(ids) {
+            let entity = grid.entity(placement.at, &id).ok_or(ScenarioError::ForGrid { e: GridError::EntityMissing })?;
+
+            for commodity in &template.produces {
+                exchange.add_producer(entity.clone(), &id, commodity).map_err(|e| ScenarioError::ForExchange { e })?;
+            }
+
+            for commodity in &template.consumes {
+                exchange.add_consumer(entity.clone(), &id, commodity).map_err(|e| ScenarioError::ForExchange { e })?;
+            }
+        }
+
+        let cursor = Cursor::new(self.cursor.range, self.cursor.direction.clone(), self.cursor.start);
+
+        Ok((grid, cursor, exchange))
+    }
+}
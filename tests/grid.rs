@@ -1,16 +1,199 @@
 extern crate owe;
 extern crate uuid;
 
-use owe::entities::{Entity, EntityType};
+use owe::entities::{Entity, EntityType, State};
 use owe::entities::{doodad, resource, structure, walker};
-use owe::map::{CellState, Direction, GridError};
-use std::collections::HashMap;
+use owe::map::{Action, ActionError, CellState, Cursor, CursorError, Direction, Disaster, DisasterEvent, DistanceMetric, EntityFilter, EntityMatch, FinishedPatrol, Grid, FlowField, GridBuilder, GridError, HousingEvent, HousingLadder, HousingLevel, IdGenerator, LabourRules, LabourStats, LifecycleEvent, PathCacheStats, PathPolicy, RiskRules, RoadEvent, Terrain, TraversalType};
+use owe::persistence;
+use owe::production::Commodity;
+use owe::production::exchange::{CommodityExchange, CommodityState};
+use owe::scenario::Scenario;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::thread;
 use utils::extract;
 use uuid::Uuid;
 
 mod setup;
 mod utils;
 
+const SWEEP_SCENARIO: &str = r#"
+name = "sweep"
+sweeps = 4
+seed = 7
+
+[grid]
+width = 7
+height = 5
+
+[cursor]
+range = 1
+direction = "Right"
+start = [0, 0]
+
+[labour]
+workforce_share = 50
+
+[templates.road]
+type = "Road"
+
+[templates.tree]
+type = "Doodad"
+props = { name = "tree", is_removable = true }
+
+[templates.depot]
+type = "Structure"
+props = { name = "depot", size = { width = 2, height = 1 }, max_employees = 3, cost = 10, desirability = [0, 0, 0, 0, 0, 0], structure_type = "Industry" }
+state = { current_employees = 0, commodities = { c0 = 9 }, risk = { fire = 0, damage = 0 } }
+producer = { type_name = "TestWalkerProducer0" }
+
+[templates.tower]
+type = "Structure"
+props = { name = "tower", size = { width = 1, height = 2 }, max_employees = 2, cost = 10, desirability = [0, 0, 0, 0, 0, 0], structure_type = "Industry" }
+state = { current_employees = 1, commodities = { c0 = 3 }, risk = { fire = 0, damage = 0 } }
+producer = { type_name = "TestWalkerProducer0" }
+
+[templates.outpost]
+type = "Structure"
+props = { name = "outpost", size = { width = 2, height = 1 }, max_employees = 0, cost = 10, desirability = [0, 0, 0, 0, 0, 0], structure_type = "Industry" }
+state = { current_employees = 0, commodities = { c0 = 4 }, risk = { fire = 0, damage = 0 } }
+producer = { type_name = "TestWalkerProducer0" }
+
+[templates.shop]
+type = "Structure"
+props = { name = "shop", size = { width = 1, height = 1 }, max_employees = 3, cost = 10, desirability = [0, 0, 0, 0, 0, 0], structure_type = "Industry" }
+state = { current_employees = 1, commodities = {}, risk = { fire = 0, damage = 0 } }
+producer = { type_name = "TestCommodityProducer0" }
+
+[templates.mill]
+type = "Structure"
+props = { name = "mill", size = { width = 1, height = 1 }, max_employees = 1, cost = 10, desirability = [0, 0, 0, 0, 0, 0], structure_type = "Industry" }
+state = { current_employees = 1, commodities = {}, risk = { fire = 0, damage = 0 } }
+producer = { type_name = "TestCommodityProducer1", parameters = { max_progress = "2" } }
+
+[templates.house]
+type = "Structure"
+props = { name = "house", size = { width = 1, height = 1 }, max_employees = 0, max_residents = 8, cost = 10, desirability = [0, 0, 0, 0, 0, 0], structure_type = "Housing" }
+state = { current_employees = 0, current_residents = 8, commodities = {}, risk = { fire = 0, damage = 0 } }
+
+[templates.ore]
+type = "Resource"
+props = { name = "ore", max_amount = 10, replenish_amount = 1 }
+state = { current_amount = 6 }
+
+[templates.courier]
+type = "Walker"
+props = { name = "courier", max_life = 6, movement_speed = 1, traversal = "RoadOnly" }
+state = { commodities = { c1 = 1 }, current_life = 6, destination = [6, 2] }
+
+[[placements]]
+template = "road"
+at = [0, 2]
+
+[[placements]]
+template = "road"
+at = [1, 2]
+
+[[placements]]
+template = "road"
+at = [2, 2]
+
+[[placements]]
+template = "road"
+at = [3, 2]
+
+[[placements]]
+template = "road"
+at = [4, 2]
+
+[[placements]]
+template = "road"
+at = [5, 2]
+
+[[placements]]
+template = "road"
+at = [6, 2]
+
+[[placements]]
+template = "depot"
+at = [0, 1]
+
+[[placements]]
+template = "tower"
+at = [3, 0]
+
+[[placements]]
+template = "shop"
+at = [5, 1]
+
+[[placements]]
+template = "mill"
+at = [2, 3]
+
+[[placements]]
+template = "house"
+at = [4, 3]
+
+[[placements]]
+template = "outpost"
+at = [5, 4]
+
+[[placements]]
+template = "tree"
+at = [0, 4]
+
+[[placements]]
+template = "tree"
+at = [5, 0]
+
+[[placements]]
+template = "ore"
+at = [1, 4]
+
+[[placements]]
+template = "courier"
+at = [1, 2]
+
+[[global_effects]]
+type_name = "TestEffect2"
+
+[[cell_effects]]
+at = [1, 0]
+effect = { type_name = "TestEffect0" }
+
+[[cell_effects]]
+at = [1, 0]
+effect = { type_name = "TestEffect1" }
+
+[[cell_effects]]
+at = [1, 0]
+effect = { type_name = "TestEffect3" }
+
+[[cell_effects]]
+at = [1, 0]
+effect = { type_name = "TestEffect4" }
+
+[[cell_effects]]
+at = [4, 1]
+effect = { type_name = "TestEffect0" }
+
+[[cell_effects]]
+at = [4, 1]
+effect = { type_name = "TestEffect4" }
+
+[[cell_effects]]
+at = [2, 2]
+effect = { type_name = "TestEffect2" }
+
+[[cell_effects]]
+at = [0, 3]
+effect = { type_name = "TestEffect3" }
+
+[[cell_effects]]
+at = [5, 3]
+effect = { type_name = "TestEffect1" }
+"#;
+
 fn sort_cells(cells: &Vec<(usize, usize)>) -> Vec<(usize, usize)> {
     let mut result = cells.clone();
     result.sort();
@@ -21,6 +204,200 @@ fn is_between(value: f64, low: f64, high: f64) -> bool {
     low <= value && high >= value
 }
 
+//processes the next cell of `grid_with_walker_production`; the structure at (0, 0) cannot place its walkers,
+//as it has no road access
+fn process_walker_production(g: &mut Grid, gc: &mut Cursor, e: &mut CommodityExchange) {
+    let expected = if gc.position() == (0, 0) {
+        Err(CursorError::ForGrid { e: GridError::RoadUnreachable })
+    } else {
+        Ok(())
+    };
+
+    assert_eq!(gc.process_and_advance(g, e), expected);
+}
+
+#[test]
+fn grid_should_support_rectangular_dimensions() {
+    let mut g = Grid::with_dimensions(4, 2);
+
+    assert_eq!(g.width(), 4);
+    assert_eq!(g.height(), 2);
+    assert!(g.is_cell_in_grid((3, 1)));
+    assert!(!g.is_cell_in_grid((1, 2)));
+    assert_eq!(g.cell_state((3, 1)), CellState::AvailableEmpty);
+    assert_eq!(g.cell_state((1, 2)), CellState::OutOfBounds);
+
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+    let d1 = doodad::Doodad { name: "d1".to_owned(), is_removable: false };
+
+    assert_eq!(g.add_entity((3, 0), Entity::Doodad { props: d0 }).map(|r| r.1), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.add_entity((0, 3), Entity::Doodad { props: d1 }).map(|r| r.1), Err(GridError::CellUnavailable));
+
+    assert_eq!(
+        g.path_between((0, 0), (3, 1)),
+        Some((vec![(0, 0), (1, 0), (2, 1), (3, 1)], 3))
+    );
+}
+
+#[test]
+fn grid_builder_should_create_grids() {
+    let effects = setup::effects::effects_default();
+
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+    let d1 = doodad::Doodad { name: "d1".to_owned(), is_removable: false };
+
+    let (g, ids) = GridBuilder::new(5, 2)
+        .with_global_effect(effects[0].clone())
+        .with_global_effect(effects[1].clone())
+        .with_entity((4, 1), Entity::Doodad { props: d0 })
+        .with_entity((0, 0), Entity::Road)
+        .with_entity((0, 0), Entity::Doodad { props: d1 })
+        .build()
+        .unwrap();
+
+    assert_eq!(g.width(), 5);
+    assert_eq!(g.height(), 2);
+    assert_eq!(ids.len(), 3);
+    assert!(g.is_effect_global(&effects[0]));
+    assert!(g.is_effect_global(&effects[1]));
+    assert!(!g.is_effect_global(&effects[2]));
+    assert_eq!(extract::doodad::name(g.entity((4, 1), &ids[0])), Some("d0".to_owned()));
+    assert_eq!(g.entity((0, 0), &ids[1]), Some(Arc::new(Entity::Road)));
+    assert_eq!(extract::doodad::name(g.entity((0, 0), &ids[2])), Some("d1".to_owned()));
+    assert_eq!(g.cell_state((0, 0)), CellState::UnavailableOccupied);
+}
+
+#[test]
+fn grid_should_generate_seeded_ids() {
+    let build = |id_generator: IdGenerator| {
+        GridBuilder::new(2, 2)
+            .with_id_generator(id_generator)
+            .with_entity((0, 0), Entity::Road)
+            .with_entity((1, 1), Entity::Road)
+            .build()
+            .unwrap()
+    };
+
+    let (_, ids_0) = build(IdGenerator::seeded(42));
+    let (_, ids_1) = build(IdGenerator::seeded(42));
+    let (_, ids_2) = build(IdGenerator::seeded(43));
+    let (_, ids_3) = build(IdGenerator::Random);
+
+    assert_eq!(ids_0, ids_1);
+    assert_ne!(ids_0[0], ids_0[1]);
+    assert_ne!(ids_0, ids_2);
+    assert_ne!(ids_0, ids_3);
+
+    let mut g = Grid::new(2);
+    g.set_id_generator(IdGenerator::seeded(42));
+
+    let (id, _) = g.add_entity((0, 0), Entity::Road).unwrap();
+    assert_eq!(id, ids_0[0]);
+    assert_eq!(id.get_version_num(), 4);
+
+    let mut id_generator = IdGenerator::seeded(42);
+    assert_eq!(id_generator.next_id(), ids_0[0]);
+    assert_eq!(id_generator.next_id(), ids_0[1]);
+}
+
+#[test]
+fn cursor_should_process_sweeps_on_several_threads() {
+    let run = |direction: &str, range: usize, threads: usize| {
+        let toml = SWEEP_SCENARIO
+            .replace("direction = \"Right\"", &format!("direction = \"{}\"", direction))
+            .replace("range = 1", &format!("range = {}", range));
+
+        let scenario = Scenario::from_toml(&toml).unwrap();
+        let (mut g, mut gc, mut e) = scenario.build(&setup::grid::registry_default()).unwrap();
+        gc.set_threads(threads);
+
+        let sweeps: Vec<_> = (0..scenario.sweeps)
+            .map(|_| (gc.process_sweep(&mut g, &mut e), gc.take_spawned_walkers(), gc.take_action_results()))
+            .collect();
+
+        //structures covering several cells have a copy in each of them, only the one at the parent cell is saved
+        let copies: Vec<_> = (0..g.width())
+            .flat_map(|x| (0..g.height()).map(move |y| (x, y)))
+            .flat_map(|cell| {
+                g.entities_along(&[cell], &EntityFilter::any()).into_iter()
+                    .map(move |found| (cell, found.id, (*found.entity).clone()))
+            })
+            .collect();
+
+        //the thread count is saved as well
+        gc.set_threads(1);
+
+        (sweeps, copies, persistence::to_binary(&persistence::save(&g, &gc, &e).unwrap()).unwrap())
+    };
+
+    for direction in &["Up", "Down", "Left", "Right"] {
+        for range in 1..3 {
+            let expected = run(direction, range, 1);
+
+            //the outpost without a road cannot spawn its walkers, the other structures can
+            assert!(expected.0.iter().all(|(result, _, _)| *result == Err(CursorError::ForGrid { e: GridError::RoadUnreachable })));
+            assert!(expected.0.iter().any(|(_, walkers, _)| !walkers.is_empty()));
+            assert!(expected.0.iter().any(|(_, _, results)| !results.is_empty()));
+
+            for threads in &[2, 3, 8] {
+                assert_eq!(run(direction, range, *threads), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn grid_should_be_processed_on_other_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Grid>();
+    assert_send_sync::<Cursor>();
+    assert_send_sync::<CommodityExchange>();
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let (mut g, mut gc, mut e, _) = setup::grid::grid_with_walker_production();
+            gc.set_threads(2);
+
+            thread::spawn(move || {
+                for _ in 0..18 {
+                    process_walker_production(&mut g, &mut gc, &mut e);
+                }
+
+                (g.find_named_entities(EntityType::Walker, "w0".to_owned()), e.amount_in_transit_of("c0"))
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), (vec![(2, 1), (2, 1)], 3));
+    }
+}
+
+#[test]
+fn grid_builder_should_fail_when_entities_cannot_be_placed() {
+    let effects = setup::effects::effects_default();
+
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+    let d1 = doodad::Doodad { name: "d1".to_owned(), is_removable: false };
+    let d2 = doodad::Doodad { name: "d2".to_owned(), is_removable: false };
+
+    assert_eq!(
+        GridBuilder::new(2, 2).with_entity((0, 0), Entity::Doodad { props: d0.clone() }).with_entity((0, 0), Entity::Doodad { props: d1 }).build().err(),
+        Some(GridError::CellUnavailable)
+    );
+
+    assert_eq!(
+        GridBuilder::new(2, 2).with_entity((0, 2), Entity::Doodad { props: d2 }).build().err(),
+        Some(GridError::CellUnavailable)
+    );
+
+    assert_eq!(
+        GridBuilder::new(2, 2).with_global_effect(effects[0].clone()).with_global_effect(effects[0].clone()).build().err(),
+        Some(GridError::EffectPresent)
+    );
+}
+
 #[test]
 fn grid_should_add_entities_to_cell() {
     let mut g = setup::grid::grid_empty();
@@ -88,30 +465,40 @@ fn grid_should_not_add_overlapping_entities() {
         name: "s0".to_owned(),
         size: structure::Size { width: 2, height: 3 },
         max_employees: 5,
+        max_residents: 0,
         cost: 1000,
         desirability: (0, 0, 0, 0, 0, 0),
         structure_type: structure::Type::Housing,
+        allows_uneven_ground: false,
     };
 
     let s1 = structure::StructureProperties {
         name: "s1".to_owned(),
         size: structure::Size { width: 2, height: 2 },
         max_employees: 2,
+        max_residents: 0,
         cost: 5000,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
     };
 
     let s0_state = structure::StructureState {
         current_employees: 0,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
     };
 
     let s1_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 3 },
+        coverage: structure::Coverage::default(),
     };
 
     assert_eq!(
@@ -145,30 +532,40 @@ fn grid_should_remove_entities_from_all_cells_they_use() {
         name: "s0".to_owned(),
         size: structure::Size { width: 2, height: 3 },
         max_employees: 5,
+        max_residents: 0,
         cost: 1000,
         desirability: (0, 0, 0, 0, 0, 0),
         structure_type: structure::Type::Housing,
+        allows_uneven_ground: false,
     };
 
     let s1 = structure::StructureProperties {
         name: "s1".to_owned(),
         size: structure::Size { width: 2, height: 2 },
         max_employees: 2,
+        max_residents: 0,
         cost: 5000,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
     };
 
     let s0_state = structure::StructureState {
         current_employees: 0,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
     };
 
     let s1_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 3 },
+        coverage: structure::Coverage::default(),
     };
 
     let mut id_map: HashMap<(usize, usize), Uuid> = HashMap::new();
@@ -216,15 +613,20 @@ fn grid_should_remove_entities_from_all_cells_they_use() {
         name: "s1".to_owned(),
         size: structure::Size { width: 2, height: 2 },
         max_employees: 2,
+        max_residents: 0,
         cost: 5000,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
     };
 
     let s1_new_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 3 },
+        coverage: structure::Coverage::default(),
     };
 
     assert_eq!(
@@ -494,6 +896,170 @@ fn grid_with_entities_should_calculate_paths_between_cells() {
     );
 }
 
+#[test]
+fn grid_should_calculate_paths_for_walkers() {
+    let road_walker = |destination: Option<(usize, usize)>| Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w0".to_owned(),
+            patrol: None,
+            max_life: None,
+            movement_speed: 1,
+            traversal: TraversalType::RoadOnly,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination,
+            origin: None,
+            patrol: None,
+        },
+    };
+
+    let free_walker = |traversal: TraversalType| Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w1".to_owned(),
+            patrol: None,
+            max_life: None,
+            movement_speed: 1,
+            traversal,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination: None,
+            origin: None,
+            patrol: None,
+        },
+    };
+
+    let d0 = Entity::Doodad { props: doodad::Doodad { name: "d0".to_owned(), is_removable: false } };
+    let rd = Entity::Road;
+    let rb = Entity::Roadblock;
+
+    let (g, _) = setup::grid::grid_with_roads(
+        (Some(&rd), Some(&rd), Some(&rd)),
+        (None/* */, Some(&d0), Some(&rd)),
+        (Some(&rd), Some(&rd), Some(&rd)),
+    );
+
+    assert_eq!(
+        g.path_for((0, 0), (0, 2), &road_walker(Some((0, 2)))),
+        Some((vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (1, 2), (0, 2)], 6))
+    );
+
+    assert_eq!(
+        g.path_for((0, 0), (0, 2), &free_walker(TraversalType::RoadOrEmpty)),
+        Some((vec![(0, 0), (0, 1), (0, 2)], 2))
+    );
+
+    assert_eq!(g.path_for((0, 1), (0, 2), &free_walker(TraversalType::EmptyOnly)), None);
+    assert_eq!(g.path_for((0, 0), (3, 0), &road_walker(Some((3, 0)))), None);
+
+    let (g, _) = setup::grid::grid_with_roads(
+        (Some(&rd), Some(&rb), Some(&rd)),
+        (None/* */, Some(&d0), Some(&rd)),
+        (Some(&rd), Some(&rd), Some(&rd)),
+    );
+
+    assert_eq!(
+        g.path_for((0, 0), (2, 0), &road_walker(Some((2, 0)))),
+        Some((vec![(0, 0), (1, 0), (2, 0)], 2))
+    );
+
+    assert_eq!(g.path_for((0, 0), (2, 0), &road_walker(None)), None);
+
+    assert_eq!(
+        g.path_for((0, 0), (2, 0), &free_walker(TraversalType::RoadOrEmpty)),
+        Some((vec![(0, 0), (0, 1), (1, 2), (2, 1), (2, 0)], 4))
+    );
+}
+
+#[test]
+fn grid_should_move_walkers_between_cells() {
+    let (mut g, id_map) = setup::grid::grid_default();
+
+    assert_eq!(g.move_entity((1, 2), &id_map[&(1, 2)], (1, 1)), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableOccupied);
+    assert_eq!(g.cell_state((1, 2)), CellState::AvailableEmpty);
+    assert_eq!(extract::walker::life(g.entity((1, 1), &id_map[&(1, 2)])), Some(None));
+    assert_eq!(g.entity((1, 2), &id_map[&(1, 2)]), None);
+
+    assert_eq!(g.move_entity((1, 1), &id_map[&(1, 2)], (2, 2)), Ok(CellState::AvailableOccupied)); //has walker
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableEmpty);
+    assert_eq!(extract::walker::life(g.entity((2, 2), &id_map[&(1, 2)])), Some(None));
+}
+
+#[test]
+fn grid_should_locate_entities_by_id() {
+    let (mut g, id_map) = setup::grid::grid_default();
+
+    let s2 = Entity::Structure {
+        props: structure::StructureProperties {
+            name: "s2".to_owned(),
+            size: structure::Size { width: 2, height: 2 },
+            max_employees: 1,
+            max_residents: 0,
+            cost: 100,
+            desirability: (0, 0, 0, 0, 0, 0),
+            structure_type: structure::Type::Religion,
+            allows_uneven_ground: false,
+        },
+        state: structure::StructureState {
+            current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
+            commodities: HashMap::new(),
+            risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
+        },
+        producer: None,
+    };
+
+    let mut g_large = Grid::new(4);
+    let (s2_id, _) = g_large.add_entity((1, 1), s2).unwrap();
+
+    let location = g_large.locate(&s2_id).unwrap();
+    assert_eq!(location.parent, (1, 1));
+    assert_eq!(sort_cells(&location.cells), vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+    assert_eq!(location.entity_type, EntityType::Structure);
+    assert_eq!(g_large.entity_by_id(&s2_id), g_large.entity((2, 2), &s2_id));
+
+    assert_eq!(g_large.remove_by_id(&s2_id), Ok(CellState::UnavailableOccupied));
+    assert_eq!(g_large.locate(&s2_id), None);
+    assert_eq!(g_large.cell_state((2, 2)), CellState::AvailableEmpty);
+    assert_eq!(g_large.remove_by_id(&s2_id), Err(GridError::EntityMissing));
+
+    let walker = id_map[&(1, 2)];
+    assert_eq!(g.locate(&walker).map(|l| (l.parent, l.cells.clone(), l.entity_type.clone())), Some(((1, 2), vec![(1, 2)], EntityType::Walker)));
+
+    assert_eq!(g.move_entity((1, 2), &walker, (1, 1)), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.locate(&walker).map(|l| l.parent), Some((1, 1)));
+    assert_eq!(extract::walker::life(g.entity_by_id(&walker)), Some(None));
+
+    assert_eq!(g.remove_entity((0, 0), &id_map[&(0, 0)]), Ok(CellState::UnavailableOccupied));
+    assert_eq!(g.locate(&id_map[&(0, 0)]), None);
+    assert_eq!(g.entity_by_id(&id_map[&(0, 0)]), None);
+    assert_eq!(g.entity_by_id(&Uuid::new_v4()), None);
+
+    assert_eq!(g.remove_by_id(&walker), Ok(CellState::AvailableOccupied));
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableEmpty);
+}
+
+#[test]
+fn grid_should_not_move_entities_to_unavailable_cells() {
+    let (mut g, id_map) = setup::grid::grid_default();
+
+    assert_eq!(g.move_entity((1, 2), &id_map[&(1, 2)], (0, 2)), Err(GridError::CellUnavailable));
+    assert_eq!(g.move_entity((1, 2), &id_map[&(1, 2)], (3, 2)), Err(GridError::CellUnavailable));
+    assert_eq!(g.move_entity((0, 0), &id_map[&(0, 0)], (1, 1)), Err(GridError::EntityImmovable));
+    assert_eq!(g.move_entity((2, 1), &id_map[&(2, 1)], (1, 1)), Err(GridError::EntityImmovable));
+    assert_eq!(g.move_entity((1, 1), &id_map[&(1, 2)], (2, 2)), Err(GridError::EntityMissing));
+    assert_eq!(g.move_entity((1, 2), &Uuid::new_v4(), (1, 1)), Err(GridError::EntityMissing));
+
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableEmpty);
+    assert_eq!(g.cell_state((1, 2)), CellState::AvailableOccupied);
+}
+
 #[test]
 fn grid_should_add_effects_to_cell() {
     let (mut g, _, _, effects, _) = setup::grid::grid_with_effects();
@@ -654,7 +1220,7 @@ fn grid_should_not_allow_duplicate_global_effects() {
 fn grid_should_not_remove_nonexistent_global_effects() {
     let (mut g, _, _, effects, _) = setup::grid::grid_with_effects();
 
-    assert_eq!(g.remove_global_effect(&effects[1]), Err((GridError::EffectMissing)));
+    assert_eq!(g.remove_global_effect(&effects[1]), Err(GridError::EffectMissing));
 }
 
 #[test]
@@ -757,6 +1323,33 @@ fn cursor_should_move_right() {
     assert_eq!(gc.position(), (0, 0));
 }
 
+#[test]
+fn cursor_should_move_through_rectangular_grids() {
+    let expected_moves = vec![
+        (3, 2, Direction::Right, (0, 0), vec![(1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (0, 0)]),
+        (3, 2, Direction::Down, (0, 0), vec![(0, 1), (1, 0), (1, 1), (2, 0), (2, 1), (0, 0)]),
+        (3, 2, Direction::Left, (2, 1), vec![(1, 1), (0, 1), (2, 0), (1, 0), (0, 0), (2, 1)]),
+        (3, 2, Direction::Up, (2, 1), vec![(2, 0), (1, 1), (1, 0), (0, 1), (0, 0), (2, 1)]),
+        (2, 3, Direction::Right, (0, 0), vec![(1, 0), (0, 1), (1, 1), (0, 2), (1, 2), (0, 0)]),
+        (2, 3, Direction::Down, (0, 0), vec![(0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 0)]),
+        (2, 3, Direction::Left, (1, 2), vec![(0, 2), (1, 1), (0, 1), (1, 0), (0, 0), (1, 2)]),
+        (2, 3, Direction::Up, (1, 2), vec![(1, 1), (1, 0), (0, 2), (0, 1), (0, 0), (1, 2)]),
+    ];
+
+    for (width, height, direction, start, moves) in expected_moves {
+        let mut g = Grid::with_dimensions(width, height);
+        let mut gc = Cursor::new(1, direction, start);
+        let mut e = CommodityExchange::new();
+
+        assert_eq!(gc.position(), start);
+
+        for expected_position in moves {
+            assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+            assert_eq!(gc.position(), expected_position);
+        }
+    }
+}
+
 #[test]
 fn cursor_should_process_effects() {
     let (mut g, mut gc, mut e, effects, id_map) = setup::grid::grid_with_effects();
@@ -923,9 +1516,197 @@ fn cursor_should_process_effects() {
     assert_eq!(extract::walker::life(g.entity((2, 2), &id_map[&(2, 2)])), Some(Some(0)));
 }
 
+#[test]
+fn cursor_should_process_walker_movement() {
+    let (mut g, mut gc, mut e, id_map) = setup::grid::grid_with_walkers();
+
+    assert_eq!(extract::walker::destination(g.entity((0, 0), &id_map[&(0, 0)])), Some(Some((2, 0))));
+    assert_eq!(extract::walker::destination(g.entity((0, 2), &id_map[&(0, 2)])), Some(Some((2, 2))));
+    assert_eq!(extract::walker::destination(g.entity((2, 1), &id_map[&(2, 1)])), Some(None));
+
+    for _ in 0..8 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    //walkers only move at the end of a full sweep
+    assert_eq!(gc.position(), (2, 2));
+    assert_eq!(g.cell_state((0, 0)), CellState::AvailableOccupied);
+    assert_eq!(g.cell_state((0, 2)), CellState::AvailableOccupied);
+
+    assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+
+    assert_eq!(gc.position(), (0, 0));
+    assert_eq!(g.cell_state((0, 0)), CellState::AvailableEmpty);
+    assert_eq!(g.cell_state((0, 2)), CellState::AvailableEmpty);
+    assert_eq!(extract::walker::destination(g.entity((1, 0), &id_map[&(0, 0)])), Some(Some((2, 0))));
+    assert_eq!(extract::walker::destination(g.entity((2, 2), &id_map[&(0, 2)])), Some(None));
+    assert_eq!(extract::walker::destination(g.entity((2, 1), &id_map[&(2, 1)])), Some(None));
+
+    for _ in 0..9 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(gc.position(), (0, 0));
+    assert_eq!(g.cell_state((1, 0)), CellState::AvailableEmpty);
+    assert_eq!(extract::walker::destination(g.entity((2, 0), &id_map[&(0, 0)])), Some(None));
+    assert_eq!(extract::walker::destination(g.entity((2, 2), &id_map[&(0, 2)])), Some(None));
+    assert_eq!(extract::walker::destination(g.entity((2, 1), &id_map[&(2, 1)])), Some(None));
+}
+
 #[test]
 fn cursor_should_process_enqueued_actions() {
-    //TODO - implement
+    let (mut g, mut gc, mut e, effects, id_map) = setup::grid::grid_with_effects();
+
+    assert_eq!(g.add_cell_effect((0, 0), effects[3].clone()), Ok(CellState::UnavailableOccupied));
+
+    g.enqueue_action(Action::MoveWalker { from: (1, 2), id: id_map[&(1, 2)], to: (1, 1) });
+    g.enqueue_action(Action::TransferCommodity {
+        from: ((2, 1), id_map[&(2, 1)]),
+        to: ((2, 2), id_map[&(2, 2)]),
+        commodity: Commodity { name: "c0".to_owned(), amount: 1 },
+    });
+
+    assert_eq!(g.pending_actions(), 2);
+    assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(())); //process (0, 0) and go to (1, 0)
+
+    //actions are only processed at the end of a full sweep
+    assert_eq!(g.pending_actions(), 4);
+    assert_eq!(gc.take_action_results(), vec![]);
+    assert_eq!(extract::doodad::name(g.entity((0, 0), &id_map[&(0, 0)])), Some("d0".to_owned()));
+    assert_eq!(extract::doodad::name(g.entity((1, 0), &id_map[&(1, 0)])), Some("d1".to_owned()));
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableEmpty);
+
+    for _ in 0..8 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(gc.position(), (0, 0));
+    assert_eq!(g.pending_actions(), 0);
+    assert_eq!(
+        gc.take_action_results(),
+        vec![
+            Ok(()),
+            Err(ActionError::ForGrid { e: GridError::CommodityUnavailable }),
+            Ok(()),
+            Ok(()),
+        ]
+    );
+
+    assert_eq!(g.entity((0, 0), &id_map[&(0, 0)]), None);
+    assert_eq!(g.entity((1, 0), &id_map[&(1, 0)]), None);
+    assert_eq!(g.cell_state((0, 0)), CellState::AvailableEmpty);
+    assert_eq!(g.cell_state((1, 0)), CellState::AvailableEmpty);
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableOccupied);
+    assert_eq!(g.cell_state((1, 2)), CellState::AvailableEmpty);
+    assert_eq!(gc.take_action_results(), vec![]);
+}
+
+#[test]
+fn grid_should_process_actions() {
+    let (mut g, _, mut e, id_map) = setup::grid::grid_with_walker_production();
+    let effects = setup::effects::effects_default();
+
+    let w0 = Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w0".to_owned(),
+            patrol: None,
+            max_life: None,
+            movement_speed: 1,
+            traversal: TraversalType::RoadOnly,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination: None,
+            origin: None,
+            patrol: None,
+        },
+    };
+
+    let (walker_id, _) = g.add_entity((2, 1), w0).unwrap();
+
+    g.enqueue_action(Action::TransferCommodity {
+        from: ((1, 1), id_map[&(1, 1)]),
+        to: ((2, 1), walker_id),
+        commodity: Commodity { name: "c0".to_owned(), amount: 2 },
+    });
+    g.enqueue_action(Action::TransferCommodity {
+        from: ((1, 1), id_map[&(1, 1)]),
+        to: ((2, 1), id_map[&(2, 1)]),
+        commodity: Commodity { name: "c0".to_owned(), amount: 1 },
+    });
+    g.enqueue_action(Action::AddGlobalEffect { effect: effects[0].clone() });
+    g.enqueue_action(Action::RemoveCellEffect { at: (0, 0), effect: effects[0].clone() });
+    g.enqueue_action(Action::AddEntity { at: (2, 2), entity: Entity::Road });
+    g.enqueue_action(Action::AddEntity { at: (1, 1), entity: Entity::Road });
+    g.enqueue_action(Action::DestroyStructure { at: (1, 2), id: id_map[&(1, 2)] });
+    g.enqueue_action(Action::DestroyStructure { at: (1, 1), id: id_map[&(1, 1)] });
+
+    assert_eq!(
+        g.process_actions(&mut e),
+        vec![
+            Ok(()),
+            Err(ActionError::ForGrid { e: GridError::UnexpectedEntity }),
+            Ok(()),
+            Err(ActionError::ForGrid { e: GridError::EffectMissing }),
+            Ok(()),
+            Err(ActionError::ForGrid { e: GridError::CellUnavailable }),
+            Err(ActionError::ForGrid { e: GridError::UnexpectedEntity }),
+            Ok(()),
+        ]
+    );
+
+    let mut expected_walker_commodities = HashMap::new();
+    expected_walker_commodities.insert("c0".to_owned(), 2);
+
+    assert_eq!(extract::walker::commodities(g.entity((2, 1), &walker_id)), Some(expected_walker_commodities));
+    assert_eq!(e.amount_in_transit_of("c0"), 2);
+    assert_eq!(e.amount_lost_of("c0"), 1);
+    assert!(g.is_effect_global(&effects[0]));
+    assert_eq!(g.cell_state((2, 2)), CellState::AvailableOccupied);
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableEmpty);
+    assert_eq!(g.pending_actions(), 0);
+
+    //destroyed structures go through the rest of their lifecycle and are dropped from the exchange
+    assert_eq!(
+        g.take_lifecycle_events(),
+        vec![
+            LifecycleEvent { entity: id_map[&(1, 1)], at: (1, 1), from: State::Active, to: State::Destroyed },
+            LifecycleEvent { entity: id_map[&(1, 1)], at: (1, 1), from: State::Destroyed, to: State::Deleted },
+        ]
+    );
+    assert_eq!(e.amount_available_of("c0"), 0);
+}
+
+#[test]
+fn grid_should_deliver_commodities_to_structures_in_cells() {
+    let (mut g, _, mut e, id_map) = setup::grid::grid_with_walker_production();
+
+    assert!(g.add_entity((1, 0), Entity::Road).is_ok()); //connects both structures
+
+    for &to in &[(0, 0), (0, 0), (2, 1), (2, 2)] {
+        g.enqueue_action(Action::DeliverCommodity {
+            from: ((1, 1), id_map[&(1, 1)]),
+            to,
+            commodity: Commodity { name: "c0".to_owned(), amount: 2 },
+        });
+    }
+
+    assert_eq!(
+        g.process_actions(&mut e),
+        vec![
+            Ok(()),
+            Err(ActionError::ForGrid { e: GridError::CommodityUnavailable }),
+            Err(ActionError::ForGrid { e: GridError::EntityMissing }),
+            Err(ActionError::ForGrid { e: GridError::EntityMissing }),
+        ]
+    );
+
+    let mut expected_commodities = HashMap::new();
+    expected_commodities.insert("c0".to_owned(), 2);
+
+    assert_eq!(extract::structure::commodities(g.entity((0, 0), &id_map[&(0, 0)])), Some(expected_commodities));
+    assert_eq!(e.amount_available_of("c0"), 3);
 }
 
 #[test]
@@ -1094,26 +1875,216 @@ fn cursor_should_process_resource_production() {
 
 #[test]
 fn cursor_should_process_walker_production() {
-    //TODO - implement
-}
+    let (mut g, mut gc, mut e, id_map) = setup::grid::grid_with_walker_production();
 
-#[test]
-fn grid_should_find_named_entities() {
-    let (mut g, id_map) = setup::grid::grid_default();
+    //the structure at (0, 0) has no road access
+    for _ in 0..4 {
+        process_walker_production(&mut g, &mut gc, &mut e);
+    }
 
-    assert_eq!(g.find_named_entities(EntityType::Doodad, "d0".to_owned()).len(), 1);
-    assert_eq!(g.find_named_entities(EntityType::Resource, "r0".to_owned()).len(), 1);
-    assert_eq!(g.find_named_entities(EntityType::Structure, "s0".to_owned()).len(), 1);
-    assert_eq!(g.find_named_entities(EntityType::Walker, "w0".to_owned()).len(), 1);
+    assert_eq!(gc.take_spawned_walkers(), vec![]);
+    assert_eq!(g.cell_state((2, 1)), CellState::AvailableOccupied);
+    assert_eq!(g.find_named_entities(EntityType::Walker, "w0".to_owned()), vec![]);
 
-    assert_eq!(g.find_named_entities(EntityType::Doodad, "d1".to_owned()).len(), 1);
-    assert_eq!(g.find_named_entities(EntityType::Resource, "r1".to_owned()).len(), 1);
-    assert_eq!(g.find_named_entities(EntityType::Structure, "s1".to_owned()).len(), 1);
-    assert_eq!(g.find_named_entities(EntityType::Walker, "w1".to_owned()).len(), 1);
+    assert_eq!(gc.position(), (1, 1));
+    assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
 
-    assert_eq!(g.find_named_entities(EntityType::Doodad, "d2".to_owned()).len(), 0);
-    assert_eq!(g.find_named_entities(EntityType::Resource, "r2".to_owned()).len(), 0);
-    assert_eq!(g.find_named_entities(EntityType::Structure, "s2".to_owned()).len(), 0);
+    let spawned = gc.take_spawned_walkers();
+    assert_eq!(spawned.len(), 1);
+    assert_eq!(spawned[0].origin, id_map[&(1, 1)]);
+    assert_eq!(spawned[0].cell, (2, 1));
+    assert_eq!(gc.take_spawned_walkers(), vec![]);
+
+    let mut expected_walker_commodities = HashMap::new();
+    expected_walker_commodities.insert("c0".to_owned(), 2);
+
+    let mut expected_structure_commodities = HashMap::new();
+    expected_structure_commodities.insert("c0".to_owned(), 1);
+
+    assert_eq!(extract::walker::life(g.entity((2, 1), &spawned[0].walker)), Some(Some(10)));
+    assert_eq!(extract::walker::origin(g.entity((2, 1), &spawned[0].walker)), Some(Some(id_map[&(1, 1)])));
+    assert_eq!(extract::walker::patrol(g.entity((2, 1), &spawned[0].walker)), Some(None));
+    assert_eq!(extract::walker::commodities(g.entity((2, 1), &spawned[0].walker)), Some(expected_walker_commodities));
+    assert_eq!(extract::structure::commodities(g.entity((1, 1), &id_map[&(1, 1)])), Some(expected_structure_commodities));
+    assert_eq!(e.amount_in_transit_of("c0"), 2);
+    assert_eq!(e.amount_available_of("c0"), 1);
+    assert_eq!(e.entities_transporting("c0").len(), 1);
+
+    for _ in 0..9 {
+        process_walker_production(&mut g, &mut gc, &mut e);
+    }
+
+    let spawned = gc.take_spawned_walkers();
+    assert_eq!(spawned.len(), 1);
+    assert_eq!(spawned[0].cell, (2, 1));
+
+    let mut expected_walker_commodities = HashMap::new();
+    expected_walker_commodities.insert("c0".to_owned(), 1);
+
+    let mut expected_structure_commodities = HashMap::new();
+    expected_structure_commodities.insert("c0".to_owned(), 0);
+
+    assert_eq!(extract::walker::commodities(g.entity((2, 1), &spawned[0].walker)), Some(expected_walker_commodities));
+    assert_eq!(extract::structure::commodities(g.entity((1, 1), &id_map[&(1, 1)])), Some(expected_structure_commodities));
+    assert_eq!(e.amount_in_transit_of("c0"), 3);
+    assert_eq!(e.amount_available_of("c0"), 0);
+    assert_eq!(g.find_named_entities(EntityType::Walker, "w0".to_owned()), vec![(2, 1), (2, 1)]);
+}
+
+#[test]
+fn cursor_should_report_walkers_that_cannot_be_placed() {
+    let (mut g, mut gc, mut e, id_map) = setup::grid::grid_with_walker_production();
+
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+    assert_eq!(g.add_entity((2, 1), Entity::Doodad { props: d0 }).map(|r| r.1), Ok(CellState::AvailableOccupied));
+    assert_eq!(g.cell_state((2, 1)), CellState::UnavailableOccupied);
+
+    for _ in 0..4 {
+        process_walker_production(&mut g, &mut gc, &mut e);
+    }
+
+    assert_eq!(gc.process_and_advance(&mut g, &mut e), Err(CursorError::ForGrid { e: GridError::CellUnavailable }));
+    assert_eq!(gc.position(), (2, 1));
+    assert_eq!(gc.take_spawned_walkers(), vec![]);
+    assert_eq!(g.find_named_entities(EntityType::Walker, "w0".to_owned()), vec![]);
+
+    let mut expected_structure_commodities = HashMap::new();
+    expected_structure_commodities.insert("c0".to_owned(), 3);
+
+    assert_eq!(extract::structure::commodities(g.entity((1, 1), &id_map[&(1, 1)])), Some(expected_structure_commodities));
+    assert_eq!(e.amount_in_transit_of("c0"), 0);
+}
+
+#[test]
+fn structures_should_calculate_desirability_by_distance() {
+    let props = |desirability: (i8, i8, i8, i8, i8, i8)| structure::StructureProperties {
+        name: "s0".to_owned(),
+        size: structure::Size { width: 1, height: 1 },
+        max_employees: 5,
+        max_residents: 0,
+        cost: 1000,
+        desirability,
+        structure_type: structure::Type::Housing,
+        allows_uneven_ground: false,
+    };
+
+    let s0 = props((4, 1, -1, 3, 0, 4));
+    assert_eq!(s0.desirability_at(0), 4);
+    assert_eq!(s0.desirability_at(1), 4);
+    assert_eq!(s0.desirability_at(2), 3);
+    assert_eq!(s0.desirability_at(3), 2);
+    assert_eq!(s0.desirability_at(4), 0);
+
+    let s1 = props((-6, 2, 2, 6, -6, 0));
+    assert_eq!(s1.desirability_at(1), -6);
+    assert_eq!(s1.desirability_at(2), -6);
+    assert_eq!(s1.desirability_at(3), -4);
+    assert_eq!(s1.desirability_at(5), -2);
+    assert_eq!(s1.desirability_at(6), -2);
+    assert_eq!(s1.desirability_at(7), 0);
+
+    let s2 = props((2, 1, -1, 5, 0, 10));
+    assert_eq!(s2.desirability_at(2), 1);
+    assert_eq!(s2.desirability_at(3), 0);
+    assert_eq!(s2.desirability_at(5), 0);
+
+    let s3 = props((0, 0, 0, 0, 0, 0));
+    assert_eq!(s3.desirability_at(0), 0);
+    assert_eq!(s3.desirability_at(1), 0);
+}
+
+#[test]
+fn cursor_should_process_desirability_changes() {
+    let mut g = Grid::new(5);
+    let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+    let mut e = CommodityExchange::new();
+
+    let structure_with = |size: structure::Size, desirability: (i8, i8, i8, i8, i8, i8)| Entity::Structure {
+        props: structure::StructureProperties {
+            name: "s0".to_owned(),
+            size,
+            max_employees: 5,
+            max_residents: 0,
+            cost: 1000,
+            desirability,
+            structure_type: structure::Type::Housing,
+            allows_uneven_ground: false,
+        },
+        state: structure::StructureState {
+            current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
+            commodities: HashMap::new(),
+            risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
+        },
+        producer: None,
+    };
+
+    let (s0, _) = g.add_entity((3, 3), structure_with(structure::Size { width: 1, height: 1 }, (4, 1, -1, 3, 0, 4))).unwrap();
+    let (s1, _) = g.add_entity((0, 0), structure_with(structure::Size { width: 2, height: 1 }, (-2, 1, 1, 2, -2, 0))).unwrap();
+
+    assert!(g.is_desirability_outdated());
+    assert_eq!(g.desirability_at((3, 3)), Some(0));
+    assert_eq!(g.desirability_at((5, 5)), None);
+
+    for _ in 0..25 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert!(!g.is_desirability_outdated());
+    assert_eq!(g.desirability_at((3, 3)), Some(4));
+    assert_eq!(g.desirability_at((4, 4)), Some(4));
+    assert_eq!(g.desirability_at((1, 3)), Some(3));
+    assert_eq!(g.desirability_at((0, 0)), Some(0)); //-2 from s1 and +2 from s0
+    assert_eq!(g.desirability_at((1, 0)), Some(0)); //-2 from s1 and +2 from s0
+    assert_eq!(g.desirability_at((2, 1)), Some(1)); //-2 from s1 and +3 from s0
+    assert_eq!(g.desirability_at((3, 0)), Some(1)); //-1 from s1 and +2 from s0
+    assert_eq!(g.desirability_at((0, 2)), Some(1)); //-1 from s1 and +2 from s0
+    assert_eq!(g.desirability_at((4, 0)), Some(2));
+
+    assert_eq!(g.remove_entity((3, 3), &s0), Ok(CellState::UnavailableOccupied));
+    assert!(g.is_desirability_outdated());
+    assert_eq!(g.desirability_at((3, 3)), Some(4));
+
+    for _ in 0..25 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(g.desirability_at((3, 3)), Some(0));
+    assert_eq!(g.desirability_at((0, 0)), Some(-2));
+    assert_eq!(g.desirability_at((1, 1)), Some(-2));
+    assert_eq!(g.desirability_at((2, 1)), Some(-2));
+    assert_eq!(g.desirability_at((3, 2)), Some(-1));
+    assert_eq!(g.desirability_at((4, 0)), Some(0));
+
+    assert_eq!(g.remove_entity((1, 0), &s1), Ok(CellState::UnavailableOccupied));
+
+    for _ in 0..25 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(g.desirability_at((0, 0)), Some(0));
+    assert_eq!(g.desirability_at((2, 1)), Some(0));
+}
+
+#[test]
+fn grid_should_find_named_entities() {
+    let (mut g, id_map) = setup::grid::grid_default();
+
+    assert_eq!(g.find_named_entities(EntityType::Doodad, "d0".to_owned()).len(), 1);
+    assert_eq!(g.find_named_entities(EntityType::Resource, "r0".to_owned()).len(), 1);
+    assert_eq!(g.find_named_entities(EntityType::Structure, "s0".to_owned()).len(), 1);
+    assert_eq!(g.find_named_entities(EntityType::Walker, "w0".to_owned()).len(), 1);
+
+    assert_eq!(g.find_named_entities(EntityType::Doodad, "d1".to_owned()).len(), 1);
+    assert_eq!(g.find_named_entities(EntityType::Resource, "r1".to_owned()).len(), 1);
+    assert_eq!(g.find_named_entities(EntityType::Structure, "s1".to_owned()).len(), 1);
+    assert_eq!(g.find_named_entities(EntityType::Walker, "w1".to_owned()).len(), 1);
+
+    assert_eq!(g.find_named_entities(EntityType::Doodad, "d2".to_owned()).len(), 0);
+    assert_eq!(g.find_named_entities(EntityType::Resource, "r2".to_owned()).len(), 0);
+    assert_eq!(g.find_named_entities(EntityType::Structure, "s2".to_owned()).len(), 0);
     assert_eq!(g.find_named_entities(EntityType::Walker, "w2".to_owned()).len(), 0);
 
     assert_eq!(g.find_named_entities(EntityType::Doodad, "d3".to_owned()).len(), 0);
@@ -1152,30 +2123,40 @@ fn grid_should_find_named_entities() {
         name: "s2".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 5,
+        max_residents: 0,
         cost: 1000,
         desirability: (0, 0, 0, 0, 0, 0),
         structure_type: structure::Type::Housing,
+        allows_uneven_ground: false,
     };
 
     let s2_2 = structure::StructureProperties {
         name: "s2".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 2,
+        max_residents: 0,
         cost: 5000,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
     };
 
     let s2_state_1 = structure::StructureState {
         current_employees: 0,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
     };
 
     let s2_state_2 = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 3 },
+        coverage: structure::Coverage::default(),
     };
 
     let d2 = doodad::Doodad { name: "d2".to_owned(), is_removable: false };
@@ -1301,14 +2282,19 @@ fn grid_should_find_first_adjacent_road() {
             name: "s0".to_owned(),
             size: structure::Size { width: 1, height: 1 },
             max_employees: 5,
+            max_residents: 0,
             cost: 1000,
             desirability: (0, 0, 0, 0, 0, 0),
             structure_type: structure::Type::Housing,
+            allows_uneven_ground: false,
         },
         state: structure::StructureState {
             current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
             commodities: HashMap::new(),
             risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
         },
         producer: None,
     };
@@ -1318,14 +2304,19 @@ fn grid_should_find_first_adjacent_road() {
             name: "s1".to_owned(),
             size: structure::Size { width: 2, height: 1 },
             max_employees: 5,
+            max_residents: 0,
             cost: 1000,
             desirability: (0, 0, 0, 0, 0, 0),
             structure_type: structure::Type::Housing,
+            allows_uneven_ground: false,
         },
         state: structure::StructureState {
             current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
             commodities: HashMap::new(),
             risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
         },
         producer: None,
     };
@@ -1335,14 +2326,19 @@ fn grid_should_find_first_adjacent_road() {
             name: "s2".to_owned(),
             size: structure::Size { width: 2, height: 2 },
             max_employees: 5,
+            max_residents: 0,
             cost: 1000,
             desirability: (0, 0, 0, 0, 0, 0),
             structure_type: structure::Type::Housing,
+            allows_uneven_ground: false,
         },
         state: structure::StructureState {
             current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
             commodities: HashMap::new(),
             risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
         },
         producer: None,
     };
@@ -1352,14 +2348,19 @@ fn grid_should_find_first_adjacent_road() {
             name: "s3".to_owned(),
             size: structure::Size { width: 2, height: 3 },
             max_employees: 5,
+            max_residents: 0,
             cost: 1000,
             desirability: (0, 0, 0, 0, 0, 0),
             structure_type: structure::Type::Housing,
+            allows_uneven_ground: false,
         },
         state: structure::StructureState {
             current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
             commodities: HashMap::new(),
             risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
         },
         producer: None,
     };
@@ -1369,14 +2370,19 @@ fn grid_should_find_first_adjacent_road() {
             name: "s0".to_owned(),
             size: structure::Size { width: 3, height: 3 },
             max_employees: 5,
+            max_residents: 0,
             cost: 1000,
             desirability: (0, 0, 0, 0, 0, 0),
             structure_type: structure::Type::Housing,
+            allows_uneven_ground: false,
         },
         state: structure::StructureState {
             current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
             commodities: HashMap::new(),
             risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
         },
         producer: None,
     };
@@ -1386,10 +2392,15 @@ fn grid_should_find_first_adjacent_road() {
             name: "w0".to_owned(),
             patrol: None,
             max_life: Some(3),
+            movement_speed: 1,
+            traversal: TraversalType::RoadOrEmpty,
         },
         state: walker::WalkerState {
             current_life: None,
             commodities: HashMap::new(),
+            destination: None,
+            origin: None,
+            patrol: None,
         },
     };
 
@@ -1650,3 +2661,1255 @@ fn grid_should_find_first_adjacent_road() {
     assert_eq!(g.find_first_adjacent_road((1, 1), &id_map[&(1, 1)]), Some((1, 2)));
     assert_eq!(g.find_first_adjacent_road((2, 1), &id_map[&(1, 1)]), Some((1, 2)));
 }
+
+fn sorted_parents(matches: &[EntityMatch]) -> Vec<(usize, usize)> {
+    let mut parents: Vec<(usize, usize)> = matches.iter().map(|m| m.parent).collect();
+    parents.sort();
+    parents
+}
+
+#[test]
+fn grid_should_find_entities_in_areas() {
+    let (g, id_map) = setup::grid::grid_large();
+    let any = EntityFilter::any();
+
+    assert_eq!(sorted_parents(&g.entities_in_rect((0, 0), (4, 4), &any)).len(), 12);
+    assert_eq!(sorted_parents(&g.entities_in_rect((1, 0), (2, 2), &any)), vec![(1, 0), (2, 0), (2, 1), (2, 2)]);
+    assert_eq!(sorted_parents(&g.entities_in_rect((2, 2), (1, 0), &any)), vec![(1, 0), (2, 0), (2, 1), (2, 2)]);
+    assert_eq!(sorted_parents(&g.entities_in_rect((3, 0), (3, 2), &any)), vec![]);
+
+    let structures = EntityFilter::any().with_entity_type(EntityType::Structure);
+    assert_eq!(sorted_parents(&g.entities_in_rect((0, 0), (4, 4), &structures)), vec![(2, 0), (2, 1), (2, 2), (4, 1)]);
+
+    let industry = EntityFilter::any().with_structure_type(structure::Type::Industry);
+    let found = g.entities_in_rect((0, 0), (4, 4), &industry);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, id_map[&(2, 1)]);
+    assert_eq!(Some(found[0].entity.clone()), g.entity((2, 1), &id_map[&(2, 1)]));
+
+    let is_risky = |entity: &Entity| {
+        match *entity {
+            Entity::Structure { ref state, .. } => state.risk.fire > 2,
+            _ => false
+        }
+    };
+
+    let risky = EntityFilter::any().with_entity_type(EntityType::Structure).with_predicate(&is_risky);
+    assert_eq!(sorted_parents(&g.entities_in_rect((0, 0), (4, 4), &risky)), vec![(2, 1), (4, 1)]);
+
+    assert_eq!(sorted_parents(&g.entities_within((2, 2), 1, DistanceMetric::Chebyshev, &any)), vec![(2, 1), (2, 2), (2, 3), (3, 3)]);
+    assert_eq!(sorted_parents(&g.entities_within((2, 2), 1, DistanceMetric::Manhattan, &any)), vec![(2, 1), (2, 2), (2, 3)]);
+    assert_eq!(sorted_parents(&g.entities_within((0, 4), 1, DistanceMetric::Euclidean, &any)), vec![(0, 3), (0, 4), (1, 4)]);
+    assert_eq!(sorted_parents(&g.entities_within((0, 4), 2, DistanceMetric::Euclidean, &any)), vec![(0, 2), (0, 3), (0, 4), (1, 4)]);
+    assert_eq!(sorted_parents(&g.entities_within((0, 4), 2, DistanceMetric::Chebyshev, &any)), vec![(0, 2), (0, 3), (0, 4), (1, 4), (2, 2), (2, 3)]);
+    assert_eq!(sorted_parents(&g.entities_within((0, 4), 2, DistanceMetric::Manhattan, &any)), vec![(0, 2), (0, 3), (0, 4), (1, 4)]);
+
+    let path = vec![(0, 2), (0, 3), (1, 3), (2, 3), (2, 2), (2, 3), (9, 9)];
+    let found: Vec<(usize, usize)> = g.entities_along(&path, &any).iter().map(|m| m.parent).collect();
+    assert_eq!(found, vec![(0, 2), (0, 3), (2, 3), (2, 2)]);
+
+    let doodads = EntityFilter::any().with_entity_type(EntityType::Doodad);
+    let found: Vec<(usize, usize)> = g.entities_along(&path, &doodads).iter().map(|m| m.parent).collect();
+    assert_eq!(found, vec![(2, 3)]);
+}
+
+#[test]
+fn grid_should_keep_spatial_index_up_to_date() {
+    let s0 = Entity::Structure {
+        props: structure::StructureProperties {
+            name: "s0".to_owned(),
+            size: structure::Size { width: 3, height: 3 },
+            max_employees: 1,
+            max_residents: 0,
+            cost: 100,
+            desirability: (0, 0, 0, 0, 0, 0),
+            structure_type: structure::Type::Monument,
+            allows_uneven_ground: false,
+        },
+        state: structure::StructureState {
+            current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
+            commodities: HashMap::new(),
+            risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
+        },
+        producer: None,
+    };
+
+    let w0 = Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w0".to_owned(),
+            patrol: None,
+            max_life: None,
+            movement_speed: 1,
+            traversal: TraversalType::RoadOrEmpty,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination: None,
+            origin: None,
+            patrol: None,
+        },
+    };
+
+    let mut g = Grid::new(20);
+    let any = EntityFilter::any();
+
+    //the structure spans several blocks of the index and is found through any of its cells
+    let (s0_id, _) = g.add_entity((7, 7), s0).unwrap();
+    let (w0_id, _) = g.add_entity((15, 15), w0).unwrap();
+
+    assert_eq!(sorted_parents(&g.entities_in_rect((9, 9), (19, 19), &any)), vec![(7, 7), (15, 15)]);
+    assert_eq!(sorted_parents(&g.entities_in_rect((0, 0), (6, 6), &any)), vec![]);
+    assert_eq!(sorted_parents(&g.entities_within((11, 11), 2, DistanceMetric::Chebyshev, &any)), vec![(7, 7)]);
+    assert_eq!(sorted_parents(&g.entities_within((11, 11), 2, DistanceMetric::Euclidean, &any)), vec![]);
+
+    //areas reaching past the grid only cover the cells inside it
+    assert_eq!(sorted_parents(&g.entities_within((11, 11), usize::MAX, DistanceMetric::Euclidean, &any)), vec![(7, 7), (15, 15)]);
+    assert_eq!(sorted_parents(&g.entities_in_rect((9, 9), (usize::MAX, usize::MAX), &any)), vec![(7, 7), (15, 15)]);
+    assert_eq!(sorted_parents(&g.entities_in_rect((25, 25), (usize::MAX, usize::MAX), &any)), vec![]);
+
+    assert_eq!(g.move_entity((15, 15), &w0_id, (3, 3)), Ok(CellState::AvailableEmpty));
+    assert_eq!(sorted_parents(&g.entities_in_rect((0, 0), (6, 6), &any)), vec![(3, 3)]);
+    assert_eq!(sorted_parents(&g.entities_in_rect((10, 10), (19, 19), &any)), vec![]);
+
+    assert!(g.remove_by_id(&s0_id).is_ok());
+    assert_eq!(sorted_parents(&g.entities_in_rect((0, 0), (19, 19), &any)), vec![(3, 3)]);
+    assert_eq!(g.entities_in_rect((0, 0), (19, 19), &any)[0].id, w0_id);
+}
+
+#[test]
+fn grid_should_apply_terrain_rules() {
+    let s0 = Entity::Structure {
+        props: structure::StructureProperties {
+            name: "s0".to_owned(),
+            size: structure::Size { width: 2, height: 1 },
+            max_employees: 1,
+            max_residents: 0,
+            cost: 100,
+            desirability: (0, 0, 0, 0, 0, 0),
+            structure_type: structure::Type::Industry,
+            allows_uneven_ground: false,
+        },
+        state: structure::StructureState {
+            current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
+            commodities: HashMap::new(),
+            risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
+        },
+        producer: None,
+    };
+
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+
+    let mut g = Grid::with_dimensions(4, 3);
+
+    assert_eq!(g.terrain((0, 0)), Some(Terrain::Grass));
+    assert_eq!(g.terrain((4, 0)), None);
+    assert_eq!(g.set_terrain((4, 0), Terrain::Water), Err(GridError::CellUnavailable));
+
+    assert_eq!(g.set_terrain((1, 0), Terrain::Rock), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.set_terrain((1, 1), Terrain::Water), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.set_terrain((2, 1), Terrain::Water), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.set_terrain((1, 2), Terrain::Sand), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.set_terrain((2, 2), Terrain::Sand), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.set_terrain((3, 0), Terrain::Floodplain), Ok(CellState::AvailableEmpty));
+
+    //structures need buildable terrain in all of their cells
+    assert_eq!(g.add_entity((0, 0), s0.clone()).map(|r| r.1), Err(GridError::TerrainUnsuitable));
+    assert_eq!(g.cell_state((0, 0)), CellState::AvailableEmpty);
+    assert_eq!(g.add_entity((2, 0), s0.clone()).map(|r| r.1), Ok(CellState::AvailableEmpty));
+
+    //so do all other entities, except for walkers which only need terrain they can move through
+    assert_eq!(g.add_entity((1, 1), Entity::Doodad { props: d0.clone() }).map(|r| r.1), Err(GridError::TerrainUnsuitable));
+    assert_eq!(g.add_entity((1, 1), Entity::Road).map(|r| r.1), Err(GridError::TerrainUnsuitable));
+    assert_eq!(g.add_entity((1, 1), Entity::Roadblock).map(|r| r.1), Err(GridError::TerrainUnsuitable));
+    assert_eq!(g.add_entity((1, 0), Entity::Road).map(|r| r.1), Err(GridError::TerrainUnsuitable));
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableEmpty);
+    assert_eq!(g.road_component_at((1, 1)), None);
+
+    let r0 = Entity::Resource {
+        props: resource::ResourceProperties { max_amount: 5, name: "r0".to_owned(), replenish_amount: None },
+        state: resource::ResourceState { current_amount: 5 },
+        producer: None,
+    };
+
+    assert_eq!(g.add_entity((2, 1), r0.clone()).map(|r| r.1), Err(GridError::TerrainUnsuitable));
+    assert_eq!(g.add_entity((0, 2), r0).map(|r| r.1), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.add_entity((0, 0), Entity::Doodad { props: d0 }).map(|r| r.1), Ok(CellState::AvailableEmpty));
+
+    assert_eq!(g.movement_cost((0, 0)), Some(1));
+    assert_eq!(g.movement_cost((1, 0)), Some(4));
+    assert_eq!(g.movement_cost((2, 1)), None);
+    assert_eq!(g.movement_cost((1, 2)), Some(2));
+    assert_eq!(g.movement_cost((4, 0)), None);
+
+    assert_eq!(
+        g.path_between((0, 1), (3, 1)),
+        Some((vec![(0, 1), (1, 2), (2, 2), (3, 1)], 5))
+    );
+
+    //roads can be crossed at the lowest cost
+    assert!(g.add_entity((1, 2), Entity::Road).is_ok());
+    assert_eq!(g.movement_cost((1, 2)), Some(1));
+
+    assert_eq!(g.set_terrain((2, 2), Terrain::Water), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.path_between((0, 1), (3, 1)), None);
+
+    let w0 = Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w0".to_owned(),
+            patrol: None,
+            max_life: None,
+            movement_speed: 1,
+            traversal: TraversalType::RoadOrEmpty,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination: None,
+            origin: None,
+            patrol: None,
+        },
+    };
+
+    assert_eq!(g.add_entity((2, 2), w0.clone()).map(|r| r.1), Err(GridError::TerrainUnsuitable));
+    assert_eq!(g.add_entity((1, 0), w0.clone()).map(|r| r.1), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.add_entity((1, 2), w0).map(|r| r.1), Ok(CellState::AvailableOccupied));
+
+    let built = GridBuilder::new(2, 2)
+        .with_terrain((1, 1), Terrain::Marsh)
+        .with_entity((0, 1), s0)
+        .build();
+
+    assert_eq!(built.map(|r| r.1), Err(GridError::TerrainUnsuitable));
+}
+
+#[test]
+fn grid_should_apply_elevation_rules() {
+    let s0_props = structure::StructureProperties {
+        name: "s0".to_owned(),
+        size: structure::Size { width: 2, height: 1 },
+        max_employees: 1,
+        max_residents: 0,
+        cost: 100,
+        desirability: (0, 0, 0, 0, 0, 0),
+        structure_type: structure::Type::Military,
+        allows_uneven_ground: false,
+    };
+
+    let s0_state = structure::StructureState {
+        current_employees: 0,
+        current_residents: 0,
+        shift_progress: 0,
+        commodities: HashMap::new(),
+        risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
+    };
+
+    let s0 = Entity::Structure { props: s0_props.clone(), state: s0_state.clone(), producer: None };
+    let s1 = Entity::Structure {
+        props: structure::StructureProperties { size: structure::Size { width: 1, height: 1 }, ..s0_props.clone() },
+        state: s0_state.clone(),
+        producer: None,
+    };
+    let s2 = Entity::Structure {
+        props: structure::StructureProperties { allows_uneven_ground: true, ..s0_props },
+        state: s0_state,
+        producer: None,
+    };
+
+    let mut g = Grid::with_dimensions(3, 2);
+
+    assert_eq!(g.elevation_at((1, 0)), Some(0));
+    assert_eq!(g.elevation_at((3, 0)), None);
+    assert_eq!(g.set_elevation((3, 0), 1), Err(GridError::CellUnavailable));
+    assert_eq!(g.set_elevation((1, 0), 2), Ok(CellState::AvailableEmpty));
+
+    assert_eq!(g.slope_between((0, 0), (1, 0)), Some(2));
+    assert_eq!(g.slope_between((1, 0), (0, 0)), Some(2));
+    assert_eq!(g.movement_cost_between((0, 0), (1, 0)), Some(3));
+    assert_eq!(g.movement_cost_between((0, 0), (0, 1)), Some(1));
+
+    assert_eq!(
+        g.path_between((0, 0), (2, 0)),
+        Some((vec![(0, 0), (1, 1), (2, 0)], 2))
+    );
+
+    assert_eq!(g.set_elevation((1, 1), 3), Ok(CellState::AvailableEmpty));
+
+    assert_eq!(
+        g.path_between((0, 0), (2, 0)),
+        Some((vec![(0, 0), (1, 0), (2, 0)], 6))
+    );
+
+    //multi-cell structures need level ground, unless they are flagged otherwise
+    assert_eq!(g.add_entity((0, 0), s0.clone()).map(|r| r.1), Err(GridError::GroundUneven));
+    assert_eq!(g.add_entity((1, 1), s1).map(|r| r.1), Ok(CellState::AvailableEmpty));
+    assert_eq!(g.add_entity((0, 0), s2).map(|r| r.1), Ok(CellState::AvailableEmpty));
+
+    let built = GridBuilder::new(2, 1)
+        .with_elevation((0, 0), 1)
+        .with_elevation((1, 0), 1)
+        .with_entity((0, 0), s0)
+        .build();
+
+    assert!(built.is_ok());
+}
+
+#[test]
+fn grid_should_check_line_of_sight() {
+    assert_eq!(Grid::cells_on_line((0, 0), (3, 1)), vec![(0, 0), (1, 0), (2, 1), (3, 1)]);
+    assert_eq!(Grid::cells_on_line((2, 2), (2, 0)), vec![(2, 2), (2, 1), (2, 0)]);
+    assert_eq!(Grid::cells_on_line((1, 1), (1, 1)), vec![(1, 1)]);
+
+    let mut g = Grid::with_dimensions(5, 2);
+
+    assert!(g.has_line_of_sight((0, 0), (4, 0)));
+    assert!(!g.has_line_of_sight((0, 0), (5, 0)));
+
+    assert!(g.set_elevation((0, 0), 2).is_ok());
+    assert!(g.set_elevation((2, 0), 1).is_ok());
+
+    assert!(g.has_line_of_sight((0, 0), (4, 0)));
+    assert!(g.has_line_of_sight((4, 0), (0, 0)));
+
+    assert!(g.set_elevation((2, 0), 2).is_ok());
+
+    assert!(!g.has_line_of_sight((0, 0), (4, 0)));
+    assert!(!g.has_line_of_sight((4, 0), (0, 0)));
+    assert!(g.has_line_of_sight((0, 0), (2, 0)));
+    assert!(g.has_line_of_sight((0, 1), (4, 1)));
+}
+
+#[test]
+fn grid_should_cache_paths_until_grid_changes() {
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+    let mut g = Grid::new(8);
+
+    let (path, cost) = g.cached_path_between((0, 0), (3, 0)).unwrap();
+    assert_eq!(Some((path.clone(), cost)), g.path_between((0, 0), (3, 0)));
+    assert_eq!(g.path_cache_stats(), PathCacheStats { hits: 0, misses: 1, invalidated: 0, cached_paths: 1 });
+
+    //all parts of a path leading to the same end are reused
+    assert_eq!(g.cached_path_between((0, 0), (3, 0)), Some((path.clone(), cost)));
+    assert_eq!(g.cached_path_between(path[1], (3, 0)), Some((path[1..].to_vec(), cost - 1)));
+    assert_eq!(g.path_cache_stats(), PathCacheStats { hits: 2, misses: 1, invalidated: 0, cached_paths: 1 });
+
+    //failed searches are cached as well
+    assert_eq!(g.cached_path_between((0, 0), (8, 0)), None);
+    assert_eq!(g.cached_path_between((0, 0), (8, 0)), None);
+    assert_eq!(g.path_cache_stats().hits, 3);
+
+    //changes away from the path keep it, but may open up previously missing paths
+    assert!(g.add_entity((6, 6), Entity::Doodad { props: d0.clone() }).is_ok());
+    assert!(g.add_entity((7, 7), Entity::Road).is_ok());
+    assert_eq!(g.cached_path_between((0, 0), (8, 0)), None);
+    assert_eq!(g.cached_path_between((0, 0), (3, 0)), Some((path.clone(), cost)));
+    assert_eq!(g.path_cache_stats(), PathCacheStats { hits: 4, misses: 3, invalidated: 0, cached_paths: 1 });
+
+    //walkers do not block paths
+    let walker = Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w0".to_owned(),
+            patrol: None,
+            max_life: None,
+            movement_speed: 1,
+            traversal: TraversalType::RoadOrEmpty,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination: Some((3, 0)),
+            origin: None,
+            patrol: None,
+        },
+    };
+
+    let (walker_id, _) = g.add_entity(path[1], walker.clone()).unwrap();
+    assert!(g.move_entity(path[1], &walker_id, path[2]).is_ok());
+    assert_eq!(g.path_cache_stats().invalidated, 0);
+
+    //obstacles on the path and terrain changes next to it remove it from the cache
+    assert!(g.add_entity(path[1], Entity::Doodad { props: d0 }).is_ok());
+    assert_eq!(g.path_cache_stats(), PathCacheStats { hits: 4, misses: 3, invalidated: 1, cached_paths: 0 });
+
+    let blocked = path[1];
+    let (path, _) = g.cached_path_between((0, 0), (3, 0)).unwrap();
+    assert!(!path.contains(&blocked));
+    assert_eq!(g.path_cache_stats().misses, 4);
+
+    assert!(g.set_terrain((path[1].0, path[1].1 + 1), Terrain::Sand).is_ok());
+    assert_eq!(g.path_cache_stats().invalidated, 2);
+
+    //walkers have their own paths, based on how they can move
+    let (walker_path, _) = g.walker_path((0, 0), (3, 0), &walker).unwrap();
+    assert_eq!(g.walker_path((0, 0), (3, 0), &walker), Some((walker_path.clone(), 300)));
+    assert_eq!(g.walker_path(walker_path[1], (3, 0), &walker).map(|(path, cost)| (path.len(), cost)), Some((walker_path.len() - 1, 200)));
+    assert_eq!(g.walker_path((0, 0), (3, 0), &Entity::Road), None);
+    assert_eq!(g.path_cache_stats().cached_paths, 1);
+
+    g.clear_path_cache();
+    assert_eq!(g.path_cache_stats().cached_paths, 0);
+}
+
+#[test]
+fn grid_should_drop_cached_paths_when_cheaper_paths_open_up() {
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: true };
+    let mut g = Grid::new(8);
+
+    //the direct path crosses rock; the only way around it is a long detour through (3, 3)
+    for x in 1..6 {
+        assert!(g.set_terrain((x, 0), Terrain::Rock).is_ok());
+    }
+
+    for y in 1..8 {
+        for x in 0..8 {
+            if y >= 4 || (y < 3 && x != 0 && x != 6) {
+                assert!(g.set_terrain((x, y), Terrain::Water).is_ok());
+            }
+        }
+    }
+
+    let (blocker, _) = g.add_entity((3, 3), Entity::Doodad { props: d0 }).unwrap();
+
+    let (path, cost) = g.cached_path_between((0, 0), (6, 0)).unwrap();
+    assert!(path.iter().all(|&(_, y)| y == 0));
+    assert_eq!(cost, 21);
+
+    //removing the blocker far away from the path opens up a cheaper one
+    assert!(g.remove_entity((3, 3), &blocker).is_ok());
+    assert_eq!(g.path_cache_stats().invalidated, 1);
+
+    let (path, cost) = g.cached_path_between((0, 0), (6, 0)).unwrap();
+    assert!(path.contains(&(3, 3)));
+    assert_eq!(Some((path, cost)), g.path_between((0, 0), (6, 0)));
+    assert!(cost < 21);
+}
+
+fn flow_costs(field: &FlowField, width: usize, height: usize) -> Vec<Option<usize>> {
+    (0..height).flat_map(|y| (0..width).map(move |x| field.cost_at((x, y)))).collect()
+}
+
+#[test]
+fn grid_should_create_and_update_flow_fields() {
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+    let mut g = Grid::new(5);
+
+    assert!(g.flow_field_to((5, 5), PathPolicy::Passable).is_none());
+
+    let field = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(field.target(), (4, 4));
+    assert_eq!(field.cost_at((0, 0)), g.path_between((0, 0), (4, 4)).map(|(_, cost)| cost));
+    assert_eq!(field.path_from((0, 0)).map(|path| path.len()), Some(5));
+    assert_eq!(field.directions_at((0, 0)), Some(vec![Direction::Right, Direction::Down]));
+    assert_eq!(field.directions_at((4, 3)), Some(vec![Direction::Down]));
+    assert_eq!(field.directions_at((4, 4)), Some(vec![]));
+    assert_eq!(field.directions_at((5, 4)), None);
+
+    //blocking cells only updates the cells that were leading through them
+    let mut ids = Vec::new();
+
+    for y in 0..4 {
+        ids.push(g.add_entity((2, y), Entity::Doodad { props: d0.clone() }).unwrap().0);
+    }
+
+    let updated = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(updated.cost_at((0, 0)), g.path_between((0, 0), (4, 4)).map(|(_, cost)| cost));
+    assert_eq!(updated.cost_at((0, 0)), Some(6));
+    assert_eq!(updated.cost_at((2, 1)), None);
+    assert!(updated.path_from((0, 0)).unwrap().contains(&(2, 4)));
+
+    //fields that were already retrieved are not changed
+    assert_eq!(field.cost_at((0, 0)), Some(4));
+
+    g.clear_flow_fields();
+    let fresh = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(flow_costs(&updated, 5, 5), flow_costs(&fresh, 5, 5));
+
+    //opening up cells lowers the costs around them
+    assert!(g.remove_entity((2, 1), &ids[1]).is_ok());
+    assert!(g.set_terrain((3, 3), Terrain::Rock).is_ok());
+
+    let updated = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(updated.cost_at((0, 0)), Some(5));
+    assert_eq!(updated.cost_at((2, 1)), Some(3));
+
+    g.clear_flow_fields();
+    let fresh = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(flow_costs(&updated, 5, 5), flow_costs(&fresh, 5, 5));
+
+    //walker fields follow the traversal rules of the policy
+    let mut g = Grid::new(5);
+
+    for x in 0..5 {
+        assert!(g.add_entity((x, 0), Entity::Road).is_ok());
+    }
+
+    let road_only = PathPolicy::Walker { traversal: TraversalType::RoadOnly, has_destination: true };
+    let field = g.flow_field_to((4, 0), road_only.clone()).unwrap();
+    assert_eq!(field.cost_at((0, 0)), Some(4));
+    assert_eq!(field.cost_at((0, 1)), None);
+    assert_eq!(field.directions_at((1, 0)), Some(vec![Direction::Right]));
+
+    assert!(g.add_entity((2, 0), Entity::Roadblock).is_ok());
+    assert_eq!(g.flow_field_to((4, 0), road_only).unwrap().cost_at((0, 0)), Some(4));
+
+    let road_only = PathPolicy::Walker { traversal: TraversalType::RoadOnly, has_destination: false };
+    assert_eq!(g.flow_field_to((4, 0), road_only).unwrap().cost_at((0, 0)), None);
+
+    //only the changed cells and the cells leading through them are searched again
+    let mut g = Grid::new(5);
+
+    let field = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(field.recomputed_cells(), 1);
+
+    assert!(g.add_entity((0, 0), Entity::Doodad { props: d0 }).is_ok());
+
+    let updated = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(updated.recomputed_cells(), 1);
+    assert_eq!(updated.cost_at((0, 0)), None);
+
+    let leading_through = (0..5)
+        .flat_map(|y| (0..5).map(move |x| (x, y)))
+        .filter(|cell| updated.path_from(*cell).is_some_and(|path| path.contains(&(3, 3))))
+        .count();
+
+    assert!(leading_through > 1 && leading_through < 24);
+    assert!(g.set_terrain((3, 3), Terrain::Sand).is_ok());
+
+    let updated = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(updated.recomputed_cells(), leading_through);
+
+    g.clear_flow_fields();
+    let fresh = g.flow_field_to((4, 4), PathPolicy::Passable).unwrap();
+    assert_eq!(flow_costs(&updated, 5, 5), flow_costs(&fresh, 5, 5));
+}
+
+#[test]
+fn grid_should_track_road_networks() {
+    let structure_with = |name: &str, commodities: HashMap<String, u32>| {
+        setup::grid::structure_with(name, structure::Type::Industry, commodities)
+    };
+
+    let mut stored = HashMap::new();
+    stored.insert("c0".to_owned(), 3);
+
+    let mut g = Grid::new(5);
+    let mut e = CommodityExchange::new();
+    let mut roads = HashMap::new();
+
+    for &cell in &[(0, 2), (1, 2), (2, 2), (3, 2), (4, 2), (0, 4)] {
+        roads.insert(cell, g.add_entity(cell, Entity::Road).unwrap().0);
+    }
+
+    let (s0, _) = g.add_entity((0, 1), structure_with("s0", stored)).unwrap();
+    let (s1, _) = g.add_entity((4, 1), structure_with("s1", HashMap::new())).unwrap();
+    let (s2, _) = g.add_entity((1, 4), structure_with("s2", HashMap::new())).unwrap();
+
+    let main = g.road_component_at((0, 2)).unwrap();
+
+    assert!(g.are_road_connected((0, 2), (4, 2)));
+    assert!(!g.are_road_connected((0, 2), (0, 4)));
+    assert!(!g.are_road_connected((0, 1), (0, 2)));
+    assert_eq!(g.road_component_of(&s0), Some(main));
+    assert_eq!(g.road_component_of(&s1), Some(main));
+    assert_eq!(g.road_component_of(&s2), g.road_component_at((0, 4)));
+    assert_ne!(g.road_component_of(&s2), Some(main));
+    assert_eq!(g.road_component_of(&roads[&(0, 2)]), None);
+    assert_eq!(g.take_road_events(), vec![]);
+
+    let transfer = |from: ((usize, usize), Uuid), to: ((usize, usize), Uuid)| {
+        Action::TransferCommodity { from, to, commodity: Commodity { name: "c0".to_owned(), amount: 1 } }
+    };
+
+    //structures only deliver to structures on the same road network
+    g.enqueue_action(transfer(((0, 1), s0), ((4, 1), s1)));
+    g.enqueue_action(transfer(((0, 1), s0), ((1, 4), s2)));
+
+    assert_eq!(
+        g.process_actions(&mut e),
+        vec![Ok(()), Err(ActionError::ForGrid { e: GridError::RoadUnreachable })]
+    );
+
+    //removing a road in the middle of a network splits it
+    assert!(g.remove_entity((2, 2), &roads[&(2, 2)]).is_ok());
+
+    let split = g.road_component_at((3, 2)).unwrap();
+    assert_ne!(split, main);
+    assert_eq!(g.road_component_at((1, 2)), Some(main));
+    assert_eq!(g.road_component_at((2, 2)), None);
+    assert!(!g.are_road_connected((0, 2), (4, 2)));
+    assert!(g.are_road_connected((3, 2), (4, 2)));
+    assert_eq!(g.road_component_of(&s1), Some(split));
+    assert_eq!(g.take_road_events(), vec![RoadEvent::Split { component: main, into: vec![main, split] }]);
+    assert_eq!(g.take_road_events(), vec![]);
+
+    g.enqueue_action(transfer(((0, 1), s0), ((4, 1), s1)));
+    assert_eq!(g.process_actions(&mut e), vec![Err(ActionError::ForGrid { e: GridError::RoadUnreachable })]);
+
+    //removing the end of a network does not split it
+    assert!(g.remove_entity((0, 4), &roads[&(0, 4)]).is_ok());
+    assert!(g.remove_entity((4, 2), &roads[&(4, 2)]).is_ok());
+    assert_eq!(g.take_road_events(), vec![]);
+    assert_eq!(g.road_component_of(&s2), None);
+    assert_eq!(g.road_component_of(&s1), None);
+
+    //new roads join the networks next to them
+    assert!(g.add_entity((4, 2), Entity::Road).is_ok());
+    assert!(g.add_entity((2, 2), Entity::Road).is_ok());
+    assert!(g.are_road_connected((0, 2), (4, 2)));
+    assert_eq!(g.road_component_of(&s1), Some(main));
+    assert_eq!(g.take_road_events(), vec![]);
+
+    g.enqueue_action(transfer(((0, 1), s0), ((4, 1), s1)));
+    assert_eq!(g.process_actions(&mut e), vec![Ok(())]);
+
+    let mut expected = HashMap::new();
+    expected.insert("c0".to_owned(), 2);
+    assert_eq!(extract::structure::commodities(g.entity((4, 1), &s1)), Some(expected));
+
+    //structures next to several networks reach targets on any of them
+    let mut stored = HashMap::new();
+    stored.insert("c0".to_owned(), 3);
+
+    let (s3, _) = g.add_entity((2, 3), structure_with("s3", stored)).unwrap();
+    let (s4, _) = g.add_entity((3, 4), structure_with("s4", HashMap::new())).unwrap();
+    assert!(g.add_entity((2, 4), Entity::Road).is_ok());
+
+    assert_eq!(g.road_component_of(&s3), Some(main));
+    assert_ne!(g.road_component_of(&s4), Some(main));
+    assert!(g.is_reachable_by_road(&s3, &s4));
+    assert!(g.is_reachable_by_road(&s4, &s3));
+    assert!(!g.is_reachable_by_road(&s1, &s4));
+
+    g.enqueue_action(transfer(((2, 3), s3), ((3, 4), s4)));
+    assert_eq!(g.process_actions(&mut e), vec![Ok(())]);
+}
+
+#[test]
+fn cursor_should_process_patrols() {
+    let structure_named = |name: &str| setup::grid::structure_with(name, structure::Type::Religion, HashMap::new());
+
+    let walker_with = |patrol: Option<u8>, origin: Option<Uuid>, commodities: HashMap<String, u32>| {
+        Entity::Walker {
+            props: walker::WalkerProperties {
+                name: "w0".to_owned(),
+                patrol,
+                max_life: None,
+                movement_speed: 1,
+                traversal: TraversalType::RoadOnly,
+            },
+            state: walker::WalkerState {
+                current_life: None,
+                commodities,
+                destination: None,
+                origin,
+                patrol: None,
+            },
+        }
+    };
+
+    let mut g = Grid::with_dimensions(6, 3);
+    let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+    let mut e = CommodityExchange::new();
+
+    for x in 0..6 {
+        assert!(g.add_entity((x, 1), Entity::Road).is_ok());
+    }
+
+    let (s0, _) = g.add_entity((0, 0), structure_named("s0")).unwrap();
+    let (s1, _) = g.add_entity((2, 2), structure_named("s1")).unwrap();
+    let (s2, _) = g.add_entity((4, 2), structure_named("s2")).unwrap();
+    assert!(g.add_entity((5, 0), structure_named("s3")).is_ok());
+
+    let mut carried = HashMap::new();
+    carried.insert("c0".to_owned(), 2);
+
+    let (w0, _) = g.add_entity((0, 1), walker_with(Some(3), Some(s0), carried)).unwrap();
+    let (w1, _) = g.add_entity((5, 1), walker_with(None, None, HashMap::new())).unwrap();
+
+    //only walkers with a patrol distance can patrol
+    assert_eq!(g.start_patrol((5, 1), &w1), Err(GridError::UnexpectedEntity));
+    assert_eq!(g.start_patrol((0, 0), &s0), Err(GridError::UnexpectedEntity));
+    assert_eq!(g.start_patrol((1, 1), &w0), Err(GridError::EntityMissing));
+    assert_eq!(g.start_patrol((0, 1), &w0), Ok(()));
+
+    //walkers go out as far as their patrol distance allows and come back the same way on a single road
+    for _ in 0..(18 * 3) {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(sort_cells(&g.find_named_entities(EntityType::Walker, "w0".to_owned())), vec![(3, 1), (5, 1)]);
+    assert_eq!(gc.take_finished_patrols(), vec![]);
+
+    for _ in 0..(18 * 3) {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    let mut structures = BTreeSet::new();
+    structures.insert(s0);
+    structures.insert(s1);
+    structures.insert(s2);
+
+    assert_eq!(
+        gc.take_finished_patrols(),
+        vec![FinishedPatrol {
+            walker: w0,
+            origin: Some(s0),
+            cells: vec![(0, 1), (1, 1), (2, 1), (3, 1), (2, 1), (1, 1), (0, 1)],
+            structures,
+        }]
+    );
+
+    //returning walkers give back their commodities and leave the grid
+    let mut expected = HashMap::new();
+    expected.insert("c0".to_owned(), 2);
+
+    assert_eq!(gc.take_action_results(), vec![Ok(()), Ok(())]);
+    assert_eq!(g.entity_by_id(&w0), None);
+    assert_eq!(extract::structure::commodities(g.entity((0, 0), &s0)), Some(expected));
+    assert_eq!(g.find_named_entities(EntityType::Walker, "w0".to_owned()), vec![(5, 1)]);
+}
+
+#[test]
+fn patrols_should_be_deterministic() {
+    let patrol_route = |seed: u64| {
+        let mut g = Grid::new(5);
+        let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+        let mut e = CommodityExchange::new();
+
+        g.set_id_generator(IdGenerator::seeded(seed));
+
+        for i in 0..5 {
+            assert!(g.add_entity((i, 2), Entity::Road).is_ok());
+
+            if i != 2 {
+                assert!(g.add_entity((2, i), Entity::Road).is_ok());
+            }
+        }
+
+        let w0 = Entity::Walker {
+            props: walker::WalkerProperties {
+                name: "w0".to_owned(),
+                patrol: Some(6),
+                max_life: None,
+                movement_speed: 2,
+                traversal: TraversalType::RoadOnly,
+            },
+            state: walker::WalkerState {
+                current_life: None,
+                commodities: HashMap::new(),
+                destination: None,
+                origin: None,
+                patrol: None,
+            },
+        };
+
+        let (w0, _) = g.add_entity((0, 2), w0).unwrap();
+        assert_eq!(g.start_patrol((0, 2), &w0), Ok(()));
+
+        for _ in 0..(25 * 8) {
+            assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+        }
+
+        let mut finished = gc.take_finished_patrols();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(g.entity_by_id(&w0), None);
+
+        finished.remove(0).cells
+    };
+
+    let route = patrol_route(3);
+
+    assert_eq!(route, patrol_route(3));
+    assert_eq!(route.first(), Some(&(0, 2)));
+    assert_eq!(route.last(), Some(&(0, 2)));
+    assert!(route.windows(2).all(|cells| Grid::distance_between(&cells[0], &cells[1]) == 1.0));
+    assert!((0..=6).all(|seed| patrol_route(seed).len() <= 13));
+}
+
+#[test]
+fn patrols_should_refresh_service_coverage() {
+    let structure_of = |structure_type: structure::Type| setup::grid::structure_with("s0", structure_type, HashMap::new());
+
+    let mut g = Grid::with_dimensions(6, 3);
+    let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+    let mut e = CommodityExchange::new();
+
+    for x in 0..6 {
+        assert!(g.add_entity((x, 1), Entity::Road).is_ok());
+    }
+
+    let (temple, _) = g.add_entity((0, 0), structure_of(structure::Type::Religion)).unwrap();
+    assert!(g.add_entity((2, 2), structure_of(structure::Type::Housing)).is_ok());
+    assert!(g.add_entity((5, 0), structure_of(structure::Type::Housing)).is_ok());
+
+    let w0 = Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w0".to_owned(),
+            patrol: Some(3),
+            max_life: None,
+            movement_speed: 1,
+            traversal: TraversalType::RoadOnly,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination: None,
+            origin: Some(temple),
+            patrol: None,
+        },
+    };
+
+    let (w0, _) = g.add_entity((0, 1), w0).unwrap();
+    assert_eq!(g.start_patrol((0, 1), &w0), Ok(()));
+
+    let religion = structure::Service::Religion;
+    assert_eq!(g.coverage_at((2, 2), &religion), Some(0));
+
+    for _ in 0..18 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    //structures next to the walker (including corners) get the service of its origin
+    assert_eq!(g.coverage_at((0, 0), &religion), Some(structure::MAX_COVERAGE));
+    assert_eq!(g.coverage_at((2, 2), &religion), Some(structure::MAX_COVERAGE));
+    assert_eq!(g.coverage_at((2, 2), &structure::Service::Fire), Some(0));
+    assert_eq!(g.coverage_at((5, 0), &religion), Some(0));
+    assert_eq!(g.coverage_at((1, 1), &religion), None);
+    assert_eq!(g.coverage_at((6, 0), &religion), None);
+
+    //coverage decays at the end of every sweep, unless a walker passes by again
+    for _ in 0..18 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(g.coverage_at((0, 0), &religion), Some(structure::MAX_COVERAGE - 1));
+    assert_eq!(g.coverage_at((2, 2), &religion), Some(structure::MAX_COVERAGE));
+
+    let heatmap = g.coverage_heatmap(&religion);
+    assert_eq!(heatmap.len(), 18);
+    assert_eq!(heatmap[0], Some(structure::MAX_COVERAGE - 1));
+    assert_eq!(heatmap[6 * 2 + 2], Some(structure::MAX_COVERAGE));
+    assert_eq!(heatmap[5], Some(0));
+    assert_eq!(heatmap.iter().filter(|level| level.is_some()).count(), 3);
+
+    for _ in 0..(18 * 10) {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    //the walker returned after 6 sweeps and the last structure it passed was the temple in sweep 6
+    assert_eq!(g.entity_by_id(&w0), None);
+    assert_eq!(g.coverage_at((0, 0), &religion), Some(structure::MAX_COVERAGE - 6));
+    assert_eq!(g.coverage_at((2, 2), &religion), Some(structure::MAX_COVERAGE - 7));
+    assert_eq!(g.coverage_at((5, 0), &religion), Some(0));
+}
+
+#[test]
+fn cursor_should_process_fires_and_collapses() {
+    let structure_with = |structure_type: structure::Type, width: u8, risk: structure::Risk, commodities: HashMap<String, u32>| {
+        let mut entity = setup::grid::structure_with("s0", structure_type, commodities);
+
+        if let Entity::Structure { ref mut props, ref mut state, .. } = entity {
+            props.size.width = width;
+            state.risk = risk;
+        }
+
+        entity
+    };
+
+    let mut g = Grid::with_dimensions(4, 3);
+    let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+    let mut e = CommodityExchange::new();
+
+    assert_eq!(g.risk_rules(), None);
+    g.set_risk_rules(Some(RiskRules { fire_threshold: 10, collapse_threshold: 20, fire_spread: Some(5) }));
+
+    for x in 0..4 {
+        assert!(g.add_entity((x, 1), Entity::Road).is_ok());
+    }
+
+    let mut stored = HashMap::new();
+    stored.insert("c0".to_owned(), 4);
+
+    let housing = structure::Type::Housing;
+    let (h0, _) = g.add_entity((0, 0), structure_with(housing.clone(), 1, structure::Risk { fire: 7, damage: 0 }, HashMap::new())).unwrap();
+    let (h1, _) = g.add_entity((1, 0), structure_with(housing, 1, structure::Risk { fire: 0, damage: 0 }, HashMap::new())).unwrap();
+    let (p0, _) = g.add_entity((3, 0), structure_with(structure::Type::Military, 1, structure::Risk { fire: 0, damage: 0 }, HashMap::new())).unwrap();
+    let (i0, _) = g.add_entity((2, 2), structure_with(structure::Type::Industry, 2, structure::Risk { fire: 0, damage: 18 }, stored)).unwrap();
+
+    let prefect = Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w0".to_owned(),
+            patrol: Some(1),
+            max_life: None,
+            movement_speed: 1,
+            traversal: TraversalType::RoadOnly,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination: None,
+            origin: Some(p0),
+            patrol: None,
+        },
+    };
+
+    let (w0, _) = g.add_entity((3, 1), prefect).unwrap();
+    assert_eq!(g.start_patrol((3, 1), &w0), Ok(()));
+
+    for _ in 0..12 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    //risks grow by structure type; fire risk is reset where the prefect passed by, but damage is not
+    assert_eq!(extract::structure::risk(g.entity((0, 0), &h0)), Some(structure::Risk { fire: 9, damage: 1 }));
+    assert_eq!(extract::structure::risk(g.entity((1, 0), &h1)), Some(structure::Risk { fire: 2, damage: 1 }));
+    assert_eq!(extract::structure::risk(g.entity((3, 0), &p0)), Some(structure::Risk { fire: 0, damage: 1 }));
+
+    //structures that reach a threshold are destroyed and leave rubble in all of their cells
+    assert_eq!(g.entity_by_id(&i0), None);
+    assert_eq!(sort_cells(&g.find_named_entities(EntityType::Doodad, "rubble".to_owned())), vec![(2, 2), (3, 2)]);
+    assert_eq!(e.amount_lost_of("c0"), 4);
+    assert_eq!(
+        g.take_disaster_events(),
+        vec![DisasterEvent { structure: i0, at: (2, 2), disaster: Disaster::Collapse, state: State::Destroyed }]
+    );
+
+    for _ in 0..12 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    //burning structures spread fire to their neighbours
+    assert_eq!(g.entity_by_id(&h0), None);
+    assert_eq!(extract::structure::risk(g.entity((1, 0), &h1)), Some(structure::Risk { fire: 9, damage: 2 }));
+    assert_eq!(
+        g.take_disaster_events(),
+        vec![DisasterEvent { structure: h0, at: (0, 0), disaster: Disaster::Fire, state: State::Destroyed }]
+    );
+
+    for _ in 0..12 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(g.entity_by_id(&h1), None);
+    assert_eq!(sort_cells(&g.find_named_entities(EntityType::Doodad, "rubble".to_owned())), vec![(0, 0), (1, 0), (2, 2), (3, 2)]);
+    assert_eq!(g.take_disaster_events().len(), 1);
+    assert_eq!(extract::structure::risk(g.entity((3, 0), &p0)), Some(structure::Risk { fire: 0, damage: 3 }));
+}
+
+#[test]
+fn grid_should_enforce_entity_lifecycles() {
+    let (mut g, mut gc, mut e, id_map) = setup::grid::grid_with_walker_production();
+    let s0 = id_map[&(1, 1)];
+
+    assert_eq!(g.entity_state((1, 1), &s0), Some(State::Active));
+    assert_eq!(g.entity_state((1, 1), &id_map[&(2, 1)]), None);
+    assert_eq!(g.set_entity_state((1, 2), &s0, State::Affected), Err(GridError::EntityMissing));
+
+    assert_eq!(g.set_entity_state((1, 1), &s0, State::Affected), Ok(()));
+    assert_eq!(g.set_entity_state((1, 1), &s0, State::Affected), Ok(()));
+    assert_eq!(g.set_entity_state((1, 1), &s0, State::Active), Ok(()));
+    assert_eq!(g.set_entity_state((1, 1), &s0, State::Destroyed), Ok(()));
+    assert_eq!(
+        g.set_entity_state((1, 1), &s0, State::Active),
+        Err(GridError::TransitionInvalid { from: State::Destroyed, to: State::Active })
+    );
+
+    let transition = |from: State, to: State| LifecycleEvent { entity: s0, at: (1, 1), from, to };
+
+    assert_eq!(
+        g.take_lifecycle_events(),
+        vec![
+            transition(State::Active, State::Affected),
+            transition(State::Affected, State::Active),
+            transition(State::Active, State::Destroyed),
+        ]
+    );
+
+    //destroyed structures stay on the grid, but no longer produce walkers
+    for _ in 0..9 {
+        process_walker_production(&mut g, &mut gc, &mut e);
+    }
+
+    assert_eq!(gc.take_spawned_walkers(), vec![]);
+    assert_eq!(g.entity_state((1, 1), &s0), Some(State::Destroyed));
+
+    //deleted entities are removed at the end of the sweep, together with their commodities in the exchange
+    let s0_ref = g.entity((1, 1), &s0).unwrap();
+    assert!(e.update_state(s0_ref.clone(), &s0, &Commodity { name: "c0".to_owned(), amount: 5 }, CommodityState::Available).is_ok());
+    assert_eq!(e.amount_available_of("c0"), 5);
+
+    g.enqueue_action(Action::SetEntityState { at: (1, 1), id: s0, state: State::Deleted });
+
+    for _ in 0..9 {
+        process_walker_production(&mut g, &mut gc, &mut e);
+    }
+
+    assert_eq!(gc.take_action_results(), vec![Ok(())]);
+    assert_eq!(g.entity_by_id(&s0), None);
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableEmpty);
+    assert_eq!(e.amount_available_of("c0"), 0);
+    assert_eq!(g.take_lifecycle_events(), vec![transition(State::Destroyed, State::Deleted)]);
+
+    //entities removed directly are deleted right away
+    assert!(g.remove_entity((2, 1), &id_map[&(2, 1)]).is_ok());
+    assert_eq!(
+        g.take_lifecycle_events(),
+        vec![LifecycleEvent { entity: id_map[&(2, 1)], at: (2, 1), from: State::Active, to: State::Deleted }]
+    );
+}
+
+#[test]
+fn cursor_should_ignore_destroyed_structures_for_desirability() {
+    let mut g = Grid::new(3);
+    let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+    let mut e = CommodityExchange::new();
+
+    let mut entity = setup::grid::structure_with("s0", structure::Type::Housing, HashMap::new());
+
+    if let Entity::Structure { ref mut props, .. } = entity {
+        props.desirability = (4, 1, -1, 1, 0, 4);
+    }
+
+    let (s0, _) = g.add_entity((1, 1), entity).unwrap();
+    assert_eq!(gc.process_sweep(&mut g, &mut e), Ok(()));
+
+    assert_eq!(g.desirability_at((1, 1)), Some(4));
+    assert_eq!(g.desirability_at((0, 0)), Some(4));
+
+    //lifecycle changes are picked up at the end of the next sweep
+    assert_eq!(g.set_entity_state((1, 1), &s0, State::Destroyed), Ok(()));
+    assert!(g.is_desirability_outdated());
+    assert_eq!(gc.process_sweep(&mut g, &mut e), Ok(()));
+
+    assert_eq!(g.desirability_at((1, 1)), Some(0));
+    assert_eq!(g.desirability_at((0, 0)), Some(0));
+}
+
+#[test]
+fn cursor_should_evolve_and_devolve_housing() {
+    let structure_with = |name: &str, structure_type: structure::Type, desirability: (i8, i8, i8, i8, i8, i8), commodities: HashMap<String, u32>| {
+        let mut entity = setup::grid::structure_with(name, structure_type, commodities);
+
+        if let Entity::Structure { ref mut props, .. } = entity {
+            props.max_employees = 0;
+            props.max_residents = 2;
+            props.desirability = desirability;
+        }
+
+        entity
+    };
+
+    let level = |name: &str, width: u8, height: u8, max_residents: u8, min_desirability: i8, commodities: HashMap<String, u32>| {
+        HousingLevel {
+            name: name.to_owned(),
+            size: structure::Size { width, height },
+            max_residents,
+            min_desirability,
+            min_coverage: structure::Coverage::default(),
+            commodities,
+        }
+    };
+
+    let mut food = HashMap::new();
+    food.insert("food".to_owned(), 1);
+
+    let mut g = Grid::with_dimensions(3, 2);
+    let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+    let mut e = CommodityExchange::new();
+
+    assert_eq!(g.housing_ladder(), None);
+    g.set_housing_ladder(Some(HousingLadder {
+        levels: vec![
+            level("tent", 1, 1, 2, 0, HashMap::new()),
+            level("hut", 2, 1, 4, 0, food.clone()),
+            level("villa", 2, 2, 6, 5, HashMap::new()),
+        ],
+        growth: 2,
+    }));
+
+    let (h0, _) = g.add_entity((0, 0), structure_with("tent", structure::Type::Housing, (0, 0, 0, 0, 0, 0), food)).unwrap();
+    let (t0, _) = g.add_entity((0, 1), Entity::Doodad { props: doodad::Doodad { name: "tree".to_owned(), is_removable: true } }).unwrap();
+
+    let mut sweep = |g: &mut Grid, e: &mut CommodityExchange| {
+        for _ in 0..6 {
+            assert_eq!(gc.process_and_advance(g, e), Ok(()));
+        }
+    };
+
+    //houses with the commodities of the next level evolve into it and take its size and capacity
+    sweep(&mut g, &mut e);
+
+    assert_eq!(extract::structure::name(g.entity((1, 0), &h0)), Some("hut".to_owned()));
+    assert_eq!(extract::structure::residents(g.entity((0, 0), &h0)), Some(2));
+    assert_eq!(g.cell_state((1, 0)), CellState::UnavailableOccupied);
+    assert_eq!(
+        g.take_housing_events(),
+        vec![HousingEvent { structure: h0, at: (0, 0), from: "tent".to_owned(), to: "hut".to_owned(), evicted: 0 }]
+    );
+
+    //desirable houses stay at their level while there is no room for the next one
+    let (m0, _) = g.add_entity((2, 1), structure_with("garden", structure::Type::Monument, (5, 0, 0, 3, 0, 5), HashMap::new())).unwrap();
+    sweep(&mut g, &mut e);
+
+    assert_eq!(g.desirability_at((1, 1)), Some(5));
+    assert_eq!(extract::structure::name(g.entity((0, 0), &h0)), Some("hut".to_owned()));
+    assert_eq!(extract::structure::residents(g.entity((0, 0), &h0)), Some(4));
+    assert_eq!(g.take_housing_events(), vec![]);
+
+    assert!(g.remove_entity((0, 1), &t0).is_ok());
+    sweep(&mut g, &mut e);
+
+    assert_eq!(extract::structure::name(g.entity((1, 1), &h0)), Some("villa".to_owned()));
+    assert_eq!(extract::structure::residents(g.entity((0, 0), &h0)), Some(6));
+    assert_eq!(g.population(), 6);
+    assert_eq!(g.housing_capacity(), 6);
+    assert_eq!(
+        g.take_housing_events(),
+        vec![HousingEvent { structure: h0, at: (0, 0), from: "hut".to_owned(), to: "villa".to_owned(), evicted: 0 }]
+    );
+
+    //houses that no longer meet the requirements of their level devolve and evict the residents they cannot hold
+    assert!(g.remove_entity((2, 1), &m0).is_ok());
+    sweep(&mut g, &mut e);
+
+    assert_eq!(extract::structure::name(g.entity((0, 0), &h0)), Some("hut".to_owned()));
+    assert_eq!(g.entity((1, 1), &h0), None);
+    assert_eq!(g.cell_state((1, 1)), CellState::AvailableEmpty);
+    assert_eq!(g.population(), 4);
+    assert_eq!(g.housing_capacity(), 4);
+    assert_eq!(
+        g.take_housing_events(),
+        vec![HousingEvent { structure: h0, at: (0, 0), from: "villa".to_owned(), to: "hut".to_owned(), evicted: 2 }]
+    );
+}
+
+#[test]
+fn cursor_should_assign_workers_to_structures() {
+    let structure_with = |structure_type: structure::Type, max_employees: u8, current_residents: u8, is_producer: bool| {
+        let mut entity = setup::grid::structure_with("s0", structure_type, HashMap::new());
+
+        if let Entity::Structure { ref mut props, ref mut state, ref mut producer } = entity {
+            props.max_employees = max_employees;
+            props.max_residents = current_residents;
+            state.current_residents = current_residents;
+
+            if is_producer {
+                *producer = Some(Box::new(setup::grid::TestCommodityProducer3 {}));
+            }
+        }
+
+        entity
+    };
+
+    let mut g = Grid::with_dimensions(5, 3);
+    let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+    let mut e = CommodityExchange::new();
+
+    assert_eq!(g.labour_rules(), None);
+    g.set_labour_rules(Some(LabourRules { workforce_share: 50 }));
+
+    for x in 0..4 {
+        assert!(g.add_entity((x, 1), Entity::Road).is_ok());
+    }
+
+    let (h0, _) = g.add_entity((0, 0), structure_with(structure::Type::Housing, 0, 10, false)).unwrap();
+    let (h1, _) = g.add_entity((4, 2), structure_with(structure::Type::Housing, 0, 4, false)).unwrap();
+    let (i0, _) = g.add_entity((1, 0), structure_with(structure::Type::Industry, 4, 0, true)).unwrap();
+    let (p0, _) = g.add_entity((2, 0), structure_with(structure::Type::Military, 2, 0, false)).unwrap();
+    let (i1, _) = g.add_entity((4, 0), structure_with(structure::Type::Industry, 3, 0, true)).unwrap();
+
+    let mut sweep = |g: &mut Grid, e: &mut CommodityExchange| {
+        for _ in 0..15 {
+            assert_eq!(gc.process_and_advance(g, e), Ok(()));
+        }
+    };
+
+    //unstaffed structures produce nothing
+    sweep(&mut g, &mut e);
+
+    assert_eq!(extract::structure::commodities(g.entity((1, 0), &i0)).and_then(|c| c.get("c3").cloned()), None);
+    assert_eq!(e.amount_used_of("c2"), 0);
+
+    //structures with a higher priority are staffed first; houses and structures without road access are left out
+    assert_eq!(extract::structure::employees(g.entity((2, 0), &p0)), Some(2));
+    assert_eq!(extract::structure::employees(g.entity((1, 0), &i0)), Some(3));
+    assert_eq!(extract::structure::employees(g.entity((4, 0), &i1)), Some(0));
+    assert_eq!(extract::structure::employees(g.entity((0, 0), &h0)), Some(0));
+    assert_eq!(extract::structure::residents(g.entity((4, 2), &h1)), Some(4));
+    assert_eq!(g.labour_stats(), &LabourStats { workforce: 7, employed: 5, unemployed: 2, jobs: 9, shortage: 4 });
+
+    //inputs and output scale with the share of filled jobs, even for small amounts
+    for _ in 0..4 {
+        sweep(&mut g, &mut e);
+    }
+
+    assert_eq!(extract::structure::commodities(g.entity((1, 0), &i0)).and_then(|c| c.get("c3").cloned()), Some(3));
+    assert_eq!(extract::structure::commodities(g.entity((4, 0), &i1)).and_then(|c| c.get("c3").cloned()), None);
+    assert_eq!(e.amount_used_of("c2"), 3);
+
+    //connecting the house and the structure to the road network lets everyone find work
+    assert!(g.add_entity((4, 1), Entity::Road).is_ok());
+    sweep(&mut g, &mut e);
+
+    assert_eq!(extract::structure::employees(g.entity((2, 0), &p0)), Some(2));
+    assert_eq!(extract::structure::employees(g.entity((1, 0), &i0)), Some(4));
+    assert_eq!(extract::structure::employees(g.entity((4, 0), &i1)), Some(1));
+    assert_eq!(g.labour_stats(), &LabourStats { workforce: 7, employed: 7, unemployed: 0, jobs: 9, shortage: 2 });
+}
+
+#[test]
+fn cursor_should_staff_structures_once_per_sweep() {
+    let mut g = Grid::with_dimensions(5, 3);
+    let mut gc = Cursor::new(1, Direction::Right, (0, 0));
+    let mut e = CommodityExchange::new();
+
+    g.set_labour_rules(Some(LabourRules { workforce_share: 50 }));
+
+    //the first road next to the house is a dead end, the employer can only be reached through the other one
+    assert!(g.add_entity((2, 0), Entity::Road).is_ok());
+    assert!(g.add_entity((2, 2), Entity::Road).is_ok());
+    assert!(g.add_entity((3, 2), Entity::Road).is_ok());
+
+    let mut house = setup::grid::structure_with("h0", structure::Type::Housing, HashMap::new());
+
+    if let Entity::Structure { ref mut props, ref mut state, .. } = house {
+        props.max_employees = 0;
+        props.max_residents = 4;
+        state.current_residents = 4;
+    }
+
+    let mut industry = setup::grid::structure_with("i0", structure::Type::Industry, HashMap::new());
+
+    if let Entity::Structure { ref mut props, ref mut producer, .. } = industry {
+        props.size = structure::Size { width: 2, height: 1 };
+        props.max_employees = 4;
+        *producer = Some(Box::new(setup::grid::TestCommodityProducer3 {}));
+    }
+
+    let (h0, _) = g.add_entity((2, 1), house).unwrap();
+    let (i0, _) = g.add_entity((3, 1), industry).unwrap();
+
+    assert_eq!(g.road_component_of(&h0), g.road_component_at((2, 0)));
+    assert_ne!(g.road_component_of(&h0), g.road_component_of(&i0));
+
+    //the residents find work along any road next to their house
+    assert_eq!(gc.process_sweep(&mut g, &mut e), Ok(()));
+
+    assert_eq!(extract::structure::employees(g.entity((3, 1), &i0)), Some(2));
+    assert_eq!(g.labour_stats(), &LabourStats { workforce: 2, employed: 2, unemployed: 0, jobs: 4, shortage: 2 });
+
+    //half of the staff only adds up to a full production cycle every second sweep, no matter how many cells are covered
+    for _ in 0..4 {
+        assert_eq!(gc.process_sweep(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(extract::structure::commodities(g.entity((3, 1), &i0)).and_then(|c| c.get("c3").cloned()), Some(2));
+    assert_eq!(e.amount_used_of("c2"), 2);
+}
@@ -0,0 +1,203 @@
+extern crate owe;
+
+use owe::entities::{doodad, structure, walker};
+use owe::entities::Entity;
+use owe::map::{Grid, PathOptions, Terrain, TraversalType};
+use owe::movement::Movable;
+use owe::movement::cost::{CombinedCost, DangerPenalty, DesirabilityPenalty, DiagonalPenalty, PathCost, RoadCost, TerrainCost, UnitCost};
+use std::collections::HashMap;
+
+
+fn walker_with(traversal: TraversalType, destination: Option<(usize, usize)>) -> Entity {
+    Entity::Walker {
+        props: walker::WalkerProperties {
+            name: "w0".to_owned(),
+            patrol: None,
+            max_life: None,
+            movement_speed: 1,
+            traversal,
+        },
+        state: walker::WalkerState {
+            current_life: None,
+            commodities: HashMap::new(),
+            destination,
+            origin: None,
+            patrol: None,
+        },
+    }
+}
+
+#[test]
+fn walkers_should_traverse_cells_based_on_traversal_type() {
+    let d0 = Entity::Doodad { props: doodad::Doodad { name: "d0".to_owned(), is_removable: false } };
+    let other = walker_with(TraversalType::RoadOrEmpty, None);
+
+    let road_only = walker_with(TraversalType::RoadOnly, None);
+    assert!(!road_only.can_traverse(None));
+    assert!(road_only.can_traverse(Some(&Entity::Road)));
+    assert!(!road_only.can_traverse(Some(&Entity::Roadblock)));
+    assert!(road_only.can_traverse(Some(&other)));
+    assert!(!road_only.can_traverse(Some(&d0)));
+    assert!(!road_only.can_move_diagonally());
+
+    let empty_only = walker_with(TraversalType::EmptyOnly, None);
+    assert!(empty_only.can_traverse(None));
+    assert!(!empty_only.can_traverse(Some(&Entity::Road)));
+    assert!(!empty_only.can_traverse(Some(&Entity::Roadblock)));
+    assert!(empty_only.can_traverse(Some(&other)));
+    assert!(!empty_only.can_traverse(Some(&d0)));
+    assert!(empty_only.can_move_diagonally());
+
+    let road_or_empty = walker_with(TraversalType::RoadOrEmpty, None);
+    assert!(road_or_empty.can_traverse(None));
+    assert!(road_or_empty.can_traverse(Some(&Entity::Road)));
+    assert!(!road_or_empty.can_traverse(Some(&Entity::Roadblock)));
+    assert!(road_or_empty.can_traverse(Some(&other)));
+    assert!(!road_or_empty.can_traverse(Some(&d0)));
+    assert!(road_or_empty.can_move_diagonally());
+}
+
+#[test]
+fn walkers_with_destination_should_pass_through_roadblocks() {
+    assert!(walker_with(TraversalType::RoadOnly, Some((1, 1))).can_traverse(Some(&Entity::Roadblock)));
+    assert!(walker_with(TraversalType::RoadOrEmpty, Some((1, 1))).can_traverse(Some(&Entity::Roadblock)));
+    assert!(!walker_with(TraversalType::EmptyOnly, Some((1, 1))).can_traverse(Some(&Entity::Roadblock)));
+
+    assert_eq!(walker_with(TraversalType::RoadOnly, Some((1, 1))).destination(), &Some((1, 1)));
+    assert_eq!(walker_with(TraversalType::RoadOnly, None).destination(), &None);
+}
+
+#[test]
+fn non_walker_entities_should_not_move() {
+    let d0 = Entity::Doodad { props: doodad::Doodad { name: "d0".to_owned(), is_removable: false } };
+
+    assert!(!d0.can_traverse(None));
+    assert!(!Entity::Road.can_traverse(Some(&Entity::Road)));
+    assert!(!Entity::Roadblock.can_move_diagonally());
+    assert_eq!(d0.destination(), &None);
+}
+
+#[test]
+fn walkers_should_find_paths_with_cost_functions() {
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+
+    let mut g = Grid::with_dimensions(4, 3);
+
+    assert_eq!(
+        g.find_path((0, 0), (2, 2), &PathOptions::new(&UnitCost)),
+        Some((vec![(0, 0), (1, 1), (2, 2)], 200))
+    );
+
+    let diagonal = CombinedCost { costs: vec![Box::new(UnitCost), Box::new(DiagonalPenalty)] };
+
+    assert_eq!(
+        g.find_path((0, 0), (2, 2), &PathOptions::new(&diagonal)),
+        Some((vec![(0, 0), (1, 1), (2, 2)], 282))
+    );
+
+    assert_eq!(
+        g.find_path((0, 0), (3, 0), &PathOptions::new(&diagonal)),
+        Some((vec![(0, 0), (1, 0), (2, 0), (3, 0)], 300))
+    );
+
+    assert_eq!(g.find_path((0, 0), (4, 0), &PathOptions::new(&UnitCost)), None);
+
+    assert!(g.set_terrain((1, 1), Terrain::Rock).is_ok());
+
+    assert_eq!(
+        g.find_path((0, 1), (2, 1), &PathOptions::new(&TerrainCost)).map(|(_, cost)| cost),
+        Some(200)
+    );
+
+    assert_eq!(
+        g.find_path((0, 1), (2, 1), &PathOptions::new(&diagonal)),
+        Some((vec![(0, 1), (1, 1), (2, 1)], 200))
+    );
+
+    //limiting the search distance prevents detours
+    assert!(g.set_terrain((1, 1), Terrain::Grass).is_ok());
+    assert!(g.add_entity((1, 0), Entity::Doodad { props: d0.clone() }).is_ok());
+    assert!(g.add_entity((1, 1), Entity::Doodad { props: d0 }).is_ok());
+
+    assert_eq!(
+        g.find_path((0, 0), (2, 0), &PathOptions::new(&UnitCost)),
+        Some((vec![(0, 0), (0, 1), (1, 2), (2, 1), (2, 0)], 400))
+    );
+
+    assert_eq!(g.find_path((0, 0), (2, 0), &PathOptions::new(&UnitCost).with_max_distance(2)), None);
+    assert!(g.find_path((0, 0), (2, 0), &PathOptions::new(&UnitCost).with_max_distance(3)).is_some());
+    assert_eq!(g.find_path((0, 0), (3, 2), &PathOptions::new(&UnitCost).with_max_distance(3)), None);
+}
+
+#[test]
+fn walkers_should_prefer_roads_with_road_costs() {
+    let mut g = Grid::new(3);
+
+    for cell in &[(0, 0), (0, 1), (0, 2), (1, 2), (2, 2), (2, 1), (2, 0)] {
+        assert!(g.add_entity(*cell, Entity::Road).is_ok());
+    }
+
+    let walker = walker_with(TraversalType::RoadOrEmpty, Some((2, 0)));
+
+    assert_eq!(
+        g.find_path((0, 0), (2, 0), &PathOptions::new(&TerrainCost).for_movable(&walker)).map(|(_, cost)| cost),
+        Some(200)
+    );
+
+    assert_eq!(
+        g.find_path((0, 0), (2, 0), &PathOptions::new(&RoadCost { off_road_penalty: 500 }).for_movable(&walker)),
+        Some((vec![(0, 0), (0, 1), (1, 2), (2, 1), (2, 0)], 400))
+    );
+
+    let road_walker = walker_with(TraversalType::RoadOnly, Some((2, 0)));
+
+    assert_eq!(
+        g.find_path((0, 0), (2, 0), &PathOptions::new(&TerrainCost).for_movable(&road_walker)),
+        Some((vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2), (2, 1), (2, 0)], 600))
+    );
+}
+
+#[test]
+fn walkers_should_avoid_undesirable_and_dangerous_cells() {
+    let s0 = Entity::Structure {
+        props: structure::StructureProperties {
+            name: "s0".to_owned(),
+            size: structure::Size { width: 1, height: 1 },
+            max_employees: 1,
+            max_residents: 0,
+            cost: 100,
+            desirability: (0, 0, 0, 0, 0, 0),
+            structure_type: structure::Type::Industry,
+            allows_uneven_ground: false,
+        },
+        state: structure::StructureState {
+            current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
+            commodities: HashMap::new(),
+            risk: structure::Risk { damage: 2, fire: 8 },
+            coverage: structure::Coverage::default(),
+        },
+        producer: None,
+    };
+
+    let mut g = Grid::with_dimensions(4, 3);
+    assert!(g.add_entity((1, 0), s0).is_ok());
+
+    let danger = DangerPenalty { max_risk: 5, penalty: 300 };
+    assert_eq!(danger.cost(&g, (0, 0), (1, 1)), Some(300));
+    assert_eq!(danger.cost(&g, (0, 0), (0, 1)), Some(300));
+    assert_eq!(danger.cost(&g, (1, 1), (2, 2)), Some(0));
+    assert_eq!(DangerPenalty { max_risk: 8, penalty: 300 }.cost(&g, (0, 0), (1, 1)), Some(0));
+
+    let careful = CombinedCost { costs: vec![Box::new(UnitCost), Box::new(danger)] };
+
+    assert_eq!(
+        g.find_path((0, 2), (3, 0), &PathOptions::new(&careful)),
+        Some((vec![(0, 2), (1, 2), (2, 2), (3, 1), (3, 0)], 400))
+    );
+
+    assert_eq!(DesirabilityPenalty { min_desirability: 0, penalty: 50 }.cost(&g, (0, 0), (0, 1)), Some(0));
+    assert_eq!(DesirabilityPenalty { min_desirability: 1, penalty: 50 }.cost(&g, (0, 0), (0, 1)), Some(50));
+    assert_eq!(DesirabilityPenalty { min_desirability: 1, penalty: 50 }.cost(&g, (0, 0), (0, 3)), None);
+}
@@ -0,0 +1,324 @@
+extern crate owe;
+extern crate uuid;
+
+use owe::effects::Effect;
+use owe::entities::structure;
+use owe::entities::Entity;
+use owe::map::{CursorError, Grid, GridError, HousingLadder, HousingLevel, LabourRules, LabourStats, RiskRules, Terrain};
+use owe::persistence;
+use owe::persistence::{PersistenceError, SavedEntity};
+use owe::production::Commodity;
+use owe::production::exchange::CommodityState;
+use owe::registry::{Registry, RegistryError};
+use owe::scenario::Scenario;
+use setup::effects::TestEffect0;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+mod setup;
+
+const SEEDED_SCENARIO: &str = r#"
+name = "seeded"
+sweeps = 5
+seed = 42
+
+[grid]
+width = 4
+height = 3
+
+[cursor]
+range = 1
+direction = "Down"
+start = [0, 0]
+
+[templates.road]
+type = "Road"
+
+[templates.d0]
+type = "Doodad"
+props = { name = "d0", is_removable = true }
+
+[templates.s0]
+type = "Structure"
+produces = ["c0"]
+props = { name = "s0", size = { width = 2, height = 1 }, max_employees = 2, cost = 10, desirability = [2, 1, -1, 3, 0, 5], structure_type = "Industry" }
+state = { current_employees = 2, commodities = { c0 = 10, c1 = 3 }, risk = { fire = 0, damage = 0 } }
+producer = { type_name = "TestWalkerProducer0" }
+
+[templates.s1]
+type = "Structure"
+props = { name = "s1", size = { width = 1, height = 1 }, max_employees = 5, cost = 10, desirability = [-1, 1, 1, 2, -2, 0], structure_type = "Housing" }
+state = { current_employees = 4, commodities = {}, risk = { fire = 0, damage = 0 } }
+producer = { type_name = "TestCommodityProducer0" }
+
+[[placements]]
+template = "s0"
+at = [0, 0]
+
+[[placements]]
+template = "s1"
+at = [3, 2]
+
+[[placements]]
+template = "d0"
+at = [0, 2]
+
+[[placements]]
+template = "road"
+at = [0, 1]
+
+[[placements]]
+template = "road"
+at = [1, 1]
+
+[[placements]]
+template = "road"
+at = [2, 1]
+
+[[global_effects]]
+type_name = "TestEffect0"
+
+[[global_effects]]
+type_name = "TestEffect2"
+
+[[global_effects]]
+type_name = "TestEffect3"
+"#;
+
+fn assert_same_entities(expected: &Grid, actual: &Grid) {
+    let expected_entities = expected.entities_by_id();
+    let actual_entities = actual.entities_by_id();
+
+    assert_eq!(expected_entities.len(), actual_entities.len());
+
+    for (id, entity) in expected_entities {
+        assert_eq!(actual_entities.get(&id), Some(&entity));
+    }
+}
+
+#[test]
+fn persistence_should_round_trip_simulation_state() {
+    let (mut g, mut gc, mut e, id_map) = setup::grid::grid_with_production();
+    let r = setup::grid::registry_default();
+
+    let s2 = Entity::Structure {
+        props: structure::StructureProperties {
+            name: "s2".to_owned(),
+            size: structure::Size { width: 2, height: 1 },
+            max_employees: 1,
+            max_residents: 0,
+            cost: 100,
+            desirability: (2, 1, -1, 2, 0, 2),
+            structure_type: structure::Type::Religion,
+            allows_uneven_ground: false,
+        },
+        state: structure::StructureState {
+            current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
+            commodities: HashMap::new(),
+            risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
+        },
+        producer: None,
+    };
+
+    let (s2_id, _) = g.add_entity((0, 0), s2).unwrap();
+    assert!(g.add_entity((1, 1), Entity::Road).is_ok());
+
+    let risk_rules = RiskRules { fire_threshold: 200, collapse_threshold: 250, fire_spread: Some(3) };
+    g.set_risk_rules(Some(risk_rules.clone()));
+
+    let mut furniture = HashMap::new();
+    furniture.insert("c1".to_owned(), 2);
+    furniture.insert("c0".to_owned(), 1);
+
+    let housing_ladder = HousingLadder {
+        levels: vec![
+            HousingLevel {
+                name: "s0".to_owned(),
+                size: structure::Size { width: 1, height: 1 },
+                max_residents: 2,
+                min_desirability: 0,
+                min_coverage: structure::Coverage::default(),
+                commodities: HashMap::new(),
+            },
+            HousingLevel {
+                name: "s0+".to_owned(),
+                size: structure::Size { width: 1, height: 1 },
+                max_residents: 4,
+                min_desirability: 1,
+                min_coverage: structure::Coverage { health: 20, ..structure::Coverage::default() },
+                commodities: furniture,
+            },
+        ],
+        growth: 1,
+    };
+
+    g.set_housing_ladder(Some(housing_ladder.clone()));
+    g.set_labour_rules(Some(LabourRules { workforce_share: 40 }));
+
+    assert!(g.set_terrain((2, 0), Terrain::Marsh).is_ok());
+    assert!(g.set_elevation((2, 2), 4).is_ok());
+
+    let shared_effect = Arc::new(TestEffect0 {}) as Arc<dyn Effect>;
+    assert!(g.add_cell_effect((1, 1), shared_effect.clone()).is_ok());
+    assert!(g.add_cell_effect((1, 0), shared_effect.clone()).is_ok());
+
+    for _ in 0..14 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    assert_ne!(g.labour_stats(), &LabourStats::default());
+
+    let s0 = g.entity((2, 1), &id_map[&(2, 1)]).unwrap();
+    assert!(e.update_state(s0.clone(), &id_map[&(2, 1)], &Commodity { name: "c0".to_owned(), amount: 7 }, CommodityState::Available).is_ok());
+    assert!(e.update_state(s0, &id_map[&(2, 1)], &Commodity { name: "c1".to_owned(), amount: 2 }, CommodityState::Lost).is_ok());
+
+    gc.set_threads(3);
+
+    let saved = persistence::save(&g, &gc, &e).unwrap();
+    assert_eq!(saved.version, persistence::SAVE_FORMAT_VERSION);
+    assert_eq!(saved.grid.effects.iter().map(|e| e.type_name.clone()).collect::<Vec<_>>(), vec!["TestEffect0".to_owned()]);
+
+    let producer_parameters = saved.grid.entities.iter()
+        .filter(|e| e.id == id_map[&(0, 2)])
+        .filter_map(|e| match e.entity {
+            SavedEntity::Structure { ref producer, .. } => producer.as_ref().map(|p| p.parameters.clone()),
+            _ => None
+        })
+        .next()
+        .unwrap();
+
+    assert_eq!(producer_parameters.get("max_progress"), Some(&"100".to_owned()));
+
+    let json = persistence::to_json(&saved).unwrap();
+    let binary = persistence::to_binary(&saved).unwrap();
+
+    for restored in [persistence::from_json(&json).unwrap(), persistence::from_binary(&binary).unwrap()] {
+        let (loaded_g, loaded_gc, loaded_e) = persistence::load(restored, &r).unwrap();
+
+        assert_same_entities(&g, &loaded_g);
+        assert_eq!((loaded_g.width(), loaded_g.height()), (3, 3));
+        assert_eq!(loaded_gc.position(), gc.position());
+
+        //multi-cell structures keep their parent cell
+        assert!(loaded_g.entity((1, 0), &s2_id).is_some());
+        assert_eq!(loaded_g.locate(&s2_id), g.locate(&s2_id));
+        assert_eq!(loaded_g.desirability_at((1, 1)), g.desirability_at((1, 1)));
+        assert_eq!(loaded_g.terrain((2, 0)), Some(Terrain::Marsh));
+        assert_eq!(loaded_g.elevation_at((2, 2)), Some(4));
+        assert!(loaded_g.road_component_of(&s2_id).is_some());
+        assert_eq!(loaded_g.road_component_of(&s2_id), g.road_component_of(&s2_id));
+        assert_eq!(loaded_g.risk_rules(), Some(&risk_rules));
+        assert_eq!(loaded_g.housing_ladder(), Some(&housing_ladder));
+        assert_eq!(loaded_g.labour_rules(), Some(&LabourRules { workforce_share: 40 }));
+        assert_eq!(loaded_g.labour_stats(), g.labour_stats());
+
+        for commodity in ["c0", "c1", "c2"] {
+            assert_eq!(loaded_e.amount_available_of(commodity), e.amount_available_of(commodity));
+            assert_eq!(loaded_e.amount_required_of(commodity), e.amount_required_of(commodity));
+            assert_eq!(loaded_e.amount_used_of(commodity), e.amount_used_of(commodity));
+            assert_eq!(loaded_e.producers_of(commodity).len(), e.producers_of(commodity).len());
+            assert_eq!(loaded_e.entities_that_have(commodity).len(), e.entities_that_have(commodity).len());
+        }
+
+        //the exchange refers to the entities on the grid
+        assert_eq!(loaded_e.amount_available_of("c0"), 7);
+        assert_eq!(loaded_e.amount_lost_of("c1"), 2);
+
+        let available = loaded_e.entities_that_have("c0").pop().unwrap();
+        assert!(Arc::ptr_eq(&available, &loaded_g.entity((2, 1), &id_map[&(2, 1)]).unwrap()));
+
+        let resaved = persistence::save(&loaded_g, &loaded_gc, &loaded_e).unwrap();
+        assert_eq!(resaved.cursor.threads, 3);
+        assert_eq!(resaved.grid.effects.len(), 1);
+        assert_eq!(resaved.grid.cells[1].effects, vec![0]);
+        assert_eq!(resaved.grid.cells[4].effects, vec![0]);
+    }
+}
+
+#[test]
+fn persistence_should_continue_simulation_after_load() {
+    let (mut g, mut gc, mut e, id_map) = setup::grid::grid_with_walker_production();
+    let r = setup::grid::registry_default();
+
+    //the structure at (0, 0) has no road access for its walkers
+    assert_eq!(gc.process_and_advance(&mut g, &mut e), Err(CursorError::ForGrid { e: GridError::RoadUnreachable }));
+
+    for _ in 0..2 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+    }
+
+    let saved = persistence::save(&g, &gc, &e).unwrap();
+    let (mut loaded_g, mut loaded_gc, mut loaded_e) = persistence::load(saved, &r).unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(gc.process_and_advance(&mut g, &mut e), Ok(()));
+        assert_eq!(loaded_gc.process_and_advance(&mut loaded_g, &mut loaded_e), Ok(()));
+    }
+
+    assert_eq!(gc.take_spawned_walkers().len(), 1);
+    assert_eq!(loaded_gc.take_spawned_walkers().len(), 1);
+    assert_eq!(loaded_e.amount_in_transit_of("c0"), e.amount_in_transit_of("c0"));
+    assert_eq!(loaded_g.entity((1, 1), &id_map[&(1, 1)]), g.entity((1, 1), &id_map[&(1, 1)]));
+}
+
+#[test]
+fn persistence_should_fail_with_invalid_state() {
+    let (mut g, gc, e, _) = setup::grid::grid_with_production();
+
+    let saved = persistence::save(&g, &gc, &e).unwrap();
+
+    //states saved with earlier versions can still be loaded, but not those from later ones
+    let mut earlier = saved.clone();
+    earlier.version = persistence::SAVE_FORMAT_VERSION - 1;
+    assert!(persistence::load(earlier, &setup::grid::registry_default()).is_ok());
+
+    let mut unsupported = saved.clone();
+    unsupported.version = persistence::SAVE_FORMAT_VERSION + 1;
+    assert_eq!(
+        persistence::load(unsupported, &setup::grid::registry_default()).err(),
+        Some(PersistenceError::UnsupportedVersion { version: persistence::SAVE_FORMAT_VERSION + 1 })
+    );
+
+    assert_eq!(
+        persistence::load(saved, &Registry::new()).err().map(|e| {
+            matches!(e, PersistenceError::ForRegistry { e: RegistryError::ProducerMissing { .. } })
+        }),
+        Some(true)
+    );
+
+    assert!(persistence::from_json("{}").is_err());
+
+    g.enqueue_action(owe::map::Action::AddGlobalEffect { effect: Arc::new(TestEffect0 {}) });
+    assert_eq!(persistence::save(&g, &gc, &e).err(), Some(PersistenceError::PendingActions));
+}
+
+#[test]
+fn persistence_should_store_identical_state_for_seeded_runs() {
+    let run = |seed: u64, threads: usize| {
+        let scenario = Scenario::from_toml(&SEEDED_SCENARIO.replace("seed = 42", &format!("seed = {}", seed))).unwrap();
+        let (mut g, mut gc, mut e) = scenario.build(&setup::grid::registry_default()).unwrap();
+        gc.set_threads(threads);
+
+        for _ in 0..scenario.sweeps {
+            assert_eq!(gc.process_sweep(&mut g, &mut e), Ok(()));
+        }
+
+        //the thread count is saved as well
+        gc.set_threads(1);
+
+        persistence::to_binary(&persistence::save(&g, &gc, &e).unwrap()).unwrap()
+    };
+
+    let first = run(42, 1);
+
+    assert_eq!(first, run(42, 1));
+    assert_ne!(first, run(43, 1));
+
+    //processing sweeps in parallel gives the same results
+    assert_eq!(first, run(42, 2));
+    assert_eq!(first, run(42, 3));
+    assert_eq!(first, run(42, 8));
+}
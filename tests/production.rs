@@ -4,11 +4,11 @@ extern crate uuid;
 use owe::entities::Entity;
 use owe::production::Commodity;
 use owe::production::exchange::{CommodityState, ExchangeError};
-use std::rc::Rc;
+use std::sync::Arc;
 
 mod setup;
 
-fn sort_entities(entities: &Vec<Rc<Entity>>) -> Vec<Rc<Entity>> {
+fn sort_entities(entities: &Vec<Arc<Entity>>) -> Vec<Arc<Entity>> {
     let mut result = entities.clone();
 
     result.sort_by_key(|&ref e| -> String {
@@ -0,0 +1,97 @@
+extern crate owe;
+extern crate uuid;
+
+use owe::registry::{Parameters, Registry, RegistryError};
+use setup::effects::TestEffect0;
+use std::sync::Arc;
+
+mod setup;
+
+#[test]
+fn registry_should_create_registered_types() {
+    let mut r = Registry::new();
+
+    assert_eq!(r.register_effect("TestEffect0", Box::new(|_| Ok(Arc::new(TestEffect0 {})))), Ok(()));
+    assert_eq!(r.register_effect("TestEffect0", Box::new(|_| Ok(Arc::new(TestEffect0 {})))), Err(RegistryError::EffectExists));
+    assert!(r.has_effect("TestEffect0"));
+    assert!(!r.has_producer("TestEffect0"));
+
+    let none = Parameters::new();
+
+    assert_eq!(r.create_effect("TestEffect0", &none).map(|e| e.type_name().to_owned()), Ok("TestEffect0".to_owned()));
+    assert_eq!(r.create_effect("TestEffect1", &none).err(), Some(RegistryError::EffectMissing { name: "TestEffect1".to_owned() }));
+    assert_eq!(
+        r.create_producer("TestCommodityProducer0", &none).err(),
+        Some(RegistryError::ProducerMissing { name: "TestCommodityProducer0".to_owned() })
+    );
+
+    let r = setup::grid::registry_default();
+    assert_eq!(
+        r.create_producer("TestCommodityProducer0", &none).map(|p| p.type_name().to_owned()),
+        Ok("TestCommodityProducer0".to_owned())
+    );
+}
+
+#[test]
+fn registry_should_create_producers_with_parameters() {
+    let r = setup::grid::registry_default();
+
+    assert_eq!(
+        r.create_producer("TestCommodityProducer1", &Parameters::new()).err(),
+        Some(RegistryError::ParameterMissing { name: "max_progress".to_owned() })
+    );
+
+    let mut parameters = Parameters::new();
+    parameters.insert("max_progress".to_owned(), "abc".to_owned());
+
+    assert_eq!(
+        r.create_producer("TestCommodityProducer1", &parameters).err(),
+        Some(RegistryError::ParameterInvalid { name: "max_progress".to_owned(), value: "abc".to_owned() })
+    );
+
+    parameters.insert("max_progress".to_owned(), "10".to_owned());
+
+    let producer = r.create_producer("TestCommodityProducer1", &parameters).unwrap();
+    assert_eq!(producer.type_name(), "TestCommodityProducer1");
+    assert_eq!(producer.parameters().get("max_progress"), Some(&"10".to_owned()));
+    assert_eq!(producer.parameters().get("current_progress"), Some(&"0".to_owned()));
+}
+
+#[test]
+fn registry_should_provide_builtin_types() {
+    let r = Registry::with_builtins();
+
+    assert!(r.has_producer("PeriodicProducer"));
+    assert!(r.has_effect("RiskIncrease"));
+
+    let mut parameters = Parameters::new();
+    parameters.insert("commodity".to_owned(), "food".to_owned());
+
+    assert_eq!(
+        r.create_producer("PeriodicProducer", &parameters).err(),
+        Some(RegistryError::ParameterMissing { name: "amount".to_owned() })
+    );
+
+    parameters.insert("amount".to_owned(), "2".to_owned());
+
+    let producer = r.create_producer("PeriodicProducer", &parameters).unwrap();
+    assert_eq!(producer.parameters().get("interval"), Some(&"1".to_owned()));
+    assert_eq!(producer.parameters().get("progress"), Some(&"0".to_owned()));
+    assert_eq!(producer.parameters().get("deliver_to"), None);
+
+    parameters.insert("deliver_to".to_owned(), "4,x".to_owned());
+
+    assert_eq!(
+        r.create_producer("PeriodicProducer", &parameters).err(),
+        Some(RegistryError::ParameterInvalid { name: "deliver_to".to_owned(), value: "4,x".to_owned() })
+    );
+
+    parameters.insert("deliver_to".to_owned(), "4, 0".to_owned());
+
+    let producer = r.create_producer("PeriodicProducer", &parameters).unwrap();
+    assert_eq!(producer.parameters().get("deliver_to"), Some(&"4,0".to_owned()));
+
+    let effect = r.create_effect("RiskIncrease", &Parameters::new()).unwrap();
+    assert_eq!(effect.parameters().get("fire"), Some(&"0".to_owned()));
+    assert_eq!(effect.parameters().get("damage"), Some(&"0".to_owned()));
+}
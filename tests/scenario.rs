@@ -0,0 +1,158 @@
+extern crate owe;
+extern crate uuid;
+
+use owe::entities::{Entity, EntityType};
+use owe::map::{RiskRules, Terrain};
+use owe::registry::{Registry, RegistryError};
+use owe::scenario::{Scenario, ScenarioError};
+
+mod setup;
+
+const SCENARIO_WITH_PRODUCTION: &str = r#"
+name = "production"
+sweeps = 1
+
+[grid]
+width = 3
+height = 3
+
+[cursor]
+range = 1
+direction = "Right"
+start = [0, 0]
+
+[templates.s0]
+type = "Structure"
+produces = ["c1"]
+props = { name = "s0", size = { width = 1, height = 1 }, max_employees = 2, cost = 10, desirability = [0, 0, 0, 0, 0, 0], structure_type = "Industry" }
+state = { current_employees = 2, commodities = {}, risk = { fire = 0, damage = 0 } }
+producer = { type_name = "TestCommodityProducer1", parameters = { max_progress = "10" } }
+
+[risk]
+fire_threshold = 200
+collapse_threshold = 150
+
+[[placements]]
+template = "s0"
+at = [1, 1]
+
+[[global_effects]]
+type_name = "TestEffect0"
+
+[[cell_effects]]
+at = [2, 2]
+effect = { type_name = "TestEffect1" }
+"#;
+
+#[test]
+fn scenario_should_build_grid_from_file() {
+    let scenario = Scenario::from_toml(include_str!("../scenarios/basic.toml")).unwrap();
+
+    assert_eq!(scenario.name, "basic");
+    assert_eq!(scenario.sweeps, 5);
+
+    let (mut g, mut gc, mut e) = scenario.build(&Registry::new()).unwrap();
+
+    assert_eq!((g.width(), g.height()), (5, 3));
+    assert_eq!(gc.position(), (0, 0));
+    assert_eq!(g.terrain((1, 0)), Some(Terrain::Floodplain));
+    assert_eq!(g.terrain((0, 2)), Some(Terrain::Water));
+    assert_eq!(g.terrain((2, 2)), Some(Terrain::Grass));
+    assert_eq!(g.elevation_at((4, 2)), Some(2));
+    assert_eq!(g.elevation_at((4, 1)), Some(0));
+    assert_eq!(g.find_named_entities(EntityType::Structure, "farm".to_owned()), vec![(1, 0)]);
+    assert_eq!(g.find_named_entities(EntityType::Walker, "courier".to_owned()), vec![(0, 1)]);
+    assert_eq!(e.producers_of("food").len(), 1);
+    assert_eq!(e.consumers_of("food").len(), 1);
+    assert_eq!(e.commodities(), vec!["food".to_owned()]);
+
+    for _ in 0..scenario.sweeps {
+        assert_eq!(gc.process_sweep(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(g.find_named_entities(EntityType::Walker, "courier".to_owned()), vec![(4, 1)]);
+    assert_eq!(e.amount_in_transit_of("food"), 2);
+
+    //the courier finds its path once and follows it afterwards
+    assert_eq!(g.path_cache_stats().misses, 1);
+    assert!(g.path_cache_stats().hits > 0);
+}
+
+#[test]
+fn scenario_should_create_producers_and_effects_from_registry() {
+    let scenario = Scenario::from_toml(SCENARIO_WITH_PRODUCTION).unwrap();
+
+    let (mut g, mut gc, mut e) = scenario.build(&setup::grid::registry_default()).unwrap();
+
+    assert_eq!(g.risk_rules(), Some(&RiskRules { fire_threshold: 200, collapse_threshold: 150, fire_spread: None }));
+
+    for _ in 0..scenario.sweeps {
+        assert_eq!(gc.process_sweep(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(e.amount_used_of("c0"), 2);
+    assert_eq!(e.commodities(), vec!["c0".to_owned(), "c1".to_owned()]);
+
+    assert_eq!(
+        scenario.build(&Registry::new()).err(),
+        Some(ScenarioError::ForRegistry { e: RegistryError::EffectMissing { name: "TestEffect0".to_owned() } })
+    );
+}
+
+#[test]
+fn scenario_should_fail_with_invalid_data() {
+    let missing_template = SCENARIO_WITH_PRODUCTION.replace("template = \"s0\"", "template = \"s1\"");
+
+    assert_eq!(
+        Scenario::from_toml(&missing_template).unwrap().build(&setup::grid::registry_default()).err(),
+        Some(ScenarioError::TemplateMissing { name: "s1".to_owned() })
+    );
+
+    let invalid_parameter = SCENARIO_WITH_PRODUCTION.replace("max_progress = \"10\"", "max_progress = \"ten\"");
+
+    assert_eq!(
+        Scenario::from_toml(&invalid_parameter).unwrap().build(&setup::grid::registry_default()).err(),
+        Some(ScenarioError::ForRegistry { e: RegistryError::ParameterInvalid { name: "max_progress".to_owned(), value: "ten".to_owned() } })
+    );
+
+    let invalid_placement = SCENARIO_WITH_PRODUCTION.replace("at = [1, 1]", "at = [3, 1]");
+
+    assert!(Scenario::from_toml(&invalid_placement).unwrap().build(&setup::grid::registry_default()).is_err());
+    assert!(Scenario::from_toml("name = \"empty\"").is_err());
+}
+
+#[test]
+fn scenario_should_use_builtin_producers_and_effects() {
+    let scenario = Scenario::from_toml(include_str!("../scenarios/production.toml")).unwrap();
+
+    assert_eq!(scenario.build(&Registry::new()).err(), Some(ScenarioError::ForRegistry { e: RegistryError::EffectMissing { name: "RiskIncrease".to_owned() } }));
+
+    let (mut g, mut gc, mut e) = scenario.build(&Registry::with_builtins()).unwrap();
+
+    for _ in 0..scenario.sweeps {
+        assert_eq!(gc.process_sweep(&mut g, &mut e), Ok(()));
+    }
+
+    assert_eq!(gc.position(), (0, 0));
+
+    let entities = g.entities_by_id();
+
+    let structure = |name: &str| {
+        entities.values()
+            .filter_map(|entity| {
+                match **entity {
+                    Entity::Structure { ref props, ref state, .. } if props.name == name => {
+                        Some((state.commodities.get("food").cloned(), state.risk.fire))
+                    }
+
+                    _ => None
+                }
+            })
+            .next()
+    };
+
+    //the farm produces every other visit and delivers to the house on the next one, along their shared road;
+    //the dry season raises the fire risk every sweep
+    assert_eq!(structure("farm"), Some((Some(3), 30)));
+    assert_eq!(structure("house"), Some((Some(6), 30)));
+}
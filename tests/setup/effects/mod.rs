@@ -1,6 +1,9 @@
 use owe::effects;
+use owe::entities::doodad;
 use owe::entities::Entity;
-use std::rc::Rc;
+use owe::map::Action;
+use std::sync::Arc;
+use uuid::Uuid;
 
 pub struct TestEffect0 {}
 
@@ -8,6 +11,10 @@ pub struct TestEffect1 {}
 
 pub struct TestEffect2 {}
 
+pub struct TestEffect3 {}
+
+pub struct TestEffect4 {}
+
 impl effects::Effect for TestEffect0 {
     fn apply(&self, entity: &mut Entity) -> () {
         match entity {
@@ -27,6 +34,10 @@ impl effects::Effect for TestEffect0 {
 }
 
 impl effects::Effect for TestEffect1 {
+    fn type_name(&self) -> &str {
+        "TestEffect1"
+    }
+
     fn apply(&self, entity: &mut Entity) -> () {
         match entity {
             &mut Entity::Structure { ref mut state, .. } => {
@@ -42,6 +53,10 @@ impl effects::Effect for TestEffect1 {
 }
 
 impl effects::Effect for TestEffect2 {
+    fn type_name(&self) -> &str {
+        "TestEffect2"
+    }
+
     fn apply(&self, entity: &mut Entity) -> () {
         match entity {
             &mut Entity::Resource { ref mut state, .. } => {
@@ -62,10 +77,49 @@ impl effects::Effect for TestEffect2 {
     }
 }
 
-pub fn effects_default() -> Vec<Rc<effects::Effect>> {
-    let e0 = Rc::new(TestEffect0 {});
-    let e1 = Rc::new(TestEffect1 {});
-    let e2 = Rc::new(TestEffect2 {});
+impl effects::Effect for TestEffect3 {
+    fn type_name(&self) -> &str {
+        "TestEffect3"
+    }
+
+    fn apply(&self, _: &mut Entity) {
+        //does nothing
+    }
+
+    fn enqueue_actions(&self, entity: &Entity, id: &Uuid, at: (usize, usize), actions: &mut Vec<Action>) {
+        if let Entity::Doodad { .. } = *entity {
+            actions.push(Action::RemoveEntity { at, id: *id });
+        }
+    }
+}
+
+impl effects::Effect for TestEffect4 {
+    fn type_name(&self) -> &str {
+        "TestEffect4"
+    }
+
+    fn apply(&self, _: &mut Entity) {
+        //does nothing
+    }
+
+    fn enqueue_actions(&self, entity: &Entity, _: &Uuid, _: (usize, usize), actions: &mut Vec<Action>) {
+        //leaves a note of the stored commodities of the structure in the top-left cell
+        if let Entity::Structure { ref props, ref state, .. } = *entity {
+            let stored = state.commodities.get("c0").cloned().unwrap_or(0);
+
+            actions.push(Action::AddEntity {
+                at: (0, 0),
+                entity: Entity::Doodad { props: doodad::Doodad { name: format!("{} {}", props.name, stored), is_removable: true } },
+            });
+        }
+    }
+}
+
+pub fn effects_default() -> Vec<Arc<dyn effects::Effect>> {
+    let e0 = Arc::new(TestEffect0 {});
+    let e1 = Arc::new(TestEffect1 {});
+    let e2 = Arc::new(TestEffect2 {});
+    let e3 = Arc::new(TestEffect3 {});
 
-    vec![e0 as Rc<effects::Effect>, e1, e2]
+    vec![e0 as Arc<dyn effects::Effect>, e1, e2, e3]
 }
@@ -5,11 +5,13 @@ use owe::entities::resource;
 use owe::entities::structure;
 use owe::entities::walker;
 use owe::map;
-use owe::production::{Commodity, Producer, ProductionStage};
+use owe::production::{Commodity, Producer, ProductionStage, WalkerStage};
 use owe::production::exchange;
+use owe::registry;
+use owe::registry::{Parameters, Registry};
 use setup::effects::*;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[allow(dead_code)]
@@ -17,6 +19,31 @@ pub fn grid_empty() -> map::Grid {
     map::Grid::new(3)
 }
 
+#[allow(dead_code)]
+pub fn structure_with(name: &str, structure_type: structure::Type, commodities: HashMap<String, u32>) -> Entity {
+    Entity::Structure {
+        props: structure::StructureProperties {
+            name: name.to_owned(),
+            size: structure::Size { width: 1, height: 1 },
+            max_employees: 1,
+            max_residents: 0,
+            cost: 100,
+            desirability: (0, 0, 0, 0, 0, 0),
+            structure_type,
+            allows_uneven_ground: false,
+        },
+        state: structure::StructureState {
+            current_employees: 0,
+            current_residents: 0,
+            shift_progress: 0,
+            commodities,
+            risk: structure::Risk { damage: 0, fire: 0 },
+            coverage: structure::Coverage::default(),
+        },
+        producer: None,
+    }
+}
+
 #[allow(dead_code)]
 pub fn grid_default() -> (map::Grid, HashMap<(usize, usize), Uuid>) {
     let mut g = map::Grid::new(3);
@@ -33,52 +60,72 @@ pub fn grid_default() -> (map::Grid, HashMap<(usize, usize), Uuid>) {
         name: "s0".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 5,
+        max_residents: 0,
         cost: 1000,
         desirability: (0, 0, 0, 0, 0, 0),
         structure_type: structure::Type::Housing,
+        allows_uneven_ground: false,
     };
 
     let s1 = structure::StructureProperties {
         name: "s1".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 2,
+        max_residents: 0,
         cost: 5000,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
     };
 
     let s0_state = structure::StructureState {
         current_employees: 0,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
     };
 
     let s1_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 3 },
+        coverage: structure::Coverage::default(),
     };
 
     let w0 = walker::WalkerProperties {
         name: "w0".to_owned(),
         patrol: None,
         max_life: Some(3),
+        movement_speed: 1,
+        traversal: map::TraversalType::RoadOrEmpty,
     };
 
     let w1 = walker::WalkerProperties {
         name: "w1".to_owned(),
         patrol: Some(5),
         max_life: Some(1),
+        movement_speed: 1,
+        traversal: map::TraversalType::RoadOrEmpty,
     };
 
     let w0_state = walker::WalkerState {
         current_life: None,
         commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
     };
 
     let w1_state = walker::WalkerState {
         current_life: Some(1),
         commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
     };
 
     let mut id_map = HashMap::new();
@@ -91,7 +138,6 @@ pub fn grid_default() -> (map::Grid, HashMap<(usize, usize), Uuid>) {
         state: r0_state,
         producer: Some(Box::new(TestCommodityProducer0 {})),
     }).map(|r| id_map.insert((2, 0), r.0));
-    ;
 
     let _ = g.add_entity((0, 1), Entity::Resource {
         props: r1,
@@ -143,82 +189,112 @@ pub fn grid_large() -> (map::Grid, HashMap<(usize, usize), Uuid>) {
         name: "s0".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 5,
+        max_residents: 0,
         cost: 1000,
         desirability: (0, 0, 0, 0, 0, 0),
         structure_type: structure::Type::Housing,
+        allows_uneven_ground: false,
     };
 
     let s1 = structure::StructureProperties {
         name: "s1".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 2,
+        max_residents: 0,
         cost: 5000,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
     };
 
     let s2 = structure::StructureProperties {
         name: "s2".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 10,
+        max_residents: 0,
         cost: 500,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::CivilService,
+        allows_uneven_ground: false,
     };
 
     let s3 = structure::StructureProperties {
         name: "s3".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 1,
+        max_residents: 0,
         cost: 1,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Religion,
+        allows_uneven_ground: false,
     };
 
     let s0_state = structure::StructureState {
         current_employees: 0,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
     };
 
     let s1_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 3 },
+        coverage: structure::Coverage::default(),
     };
 
     let s2_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 1, fire: 1 },
+        coverage: structure::Coverage::default(),
     };
 
     let s3_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 10 },
+        coverage: structure::Coverage::default(),
     };
 
     let w0 = walker::WalkerProperties {
         name: "w0".to_owned(),
         patrol: None,
         max_life: None,
+        movement_speed: 1,
+        traversal: map::TraversalType::RoadOrEmpty,
     };
 
     let w1 = walker::WalkerProperties {
         name: "w1".to_owned(),
         patrol: Some(5),
         max_life: None,
+        movement_speed: 1,
+        traversal: map::TraversalType::RoadOrEmpty,
     };
 
     let w0_state = walker::WalkerState {
         current_life: None,
         commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
     };
 
     let w1_state = walker::WalkerState {
         current_life: None,
         commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
     };
 
     let mut id_map = HashMap::new();
@@ -303,7 +379,7 @@ pub fn grid_with_direction_from(
 }
 
 #[allow(dead_code)]
-pub fn grid_with_effects() -> (map::Grid, map::Cursor, exchange::CommodityExchange, Vec<Rc<Effect>>, HashMap<(usize, usize), Uuid>) {
+pub fn grid_with_effects() -> (map::Grid, map::Cursor, exchange::CommodityExchange, Vec<Arc<dyn Effect>>, HashMap<(usize, usize), Uuid>) {
     let g = grid_default();
     let gc = map::Cursor::new(1, map::Direction::Right, (0, 0));
     let e = exchange::CommodityExchange::new();
@@ -327,52 +403,72 @@ pub fn grid_with_production() -> (map::Grid, map::Cursor, exchange::CommodityExc
         name: "s0".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 5,
+        max_residents: 0,
         cost: 1000,
         desirability: (0, 0, 0, 0, 0, 0),
         structure_type: structure::Type::Housing,
+        allows_uneven_ground: false,
     };
 
     let s1 = structure::StructureProperties {
         name: "s1".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 2,
+        max_residents: 0,
         cost: 5000,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
     };
 
     let s0_state = structure::StructureState {
         current_employees: 5,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
     };
 
     let s1_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 3 },
+        coverage: structure::Coverage::default(),
     };
 
     let w0 = walker::WalkerProperties {
         name: "w0".to_owned(),
         patrol: None,
         max_life: Some(3),
+        movement_speed: 1,
+        traversal: map::TraversalType::RoadOrEmpty,
     };
 
     let w1 = walker::WalkerProperties {
         name: "w1".to_owned(),
         patrol: Some(5),
         max_life: Some(1),
+        movement_speed: 1,
+        traversal: map::TraversalType::RoadOrEmpty,
     };
 
     let w0_state = walker::WalkerState {
         current_life: None,
         commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
     };
 
     let w1_state = walker::WalkerState {
         current_life: Some(1),
         commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
     };
 
     let mut id_map = HashMap::new();
@@ -418,6 +514,176 @@ pub fn grid_with_production() -> (map::Grid, map::Cursor, exchange::CommodityExc
     (g, gc, e, id_map)
 }
 
+#[allow(dead_code)]
+pub fn grid_with_walkers() -> (map::Grid, map::Cursor, exchange::CommodityExchange, HashMap<(usize, usize), Uuid>) {
+    let mut g = map::Grid::new(3);
+    let gc = map::Cursor::new(1, map::Direction::Right, (0, 0));
+    let e = exchange::CommodityExchange::new();
+
+    let d0 = doodad::Doodad { name: "d0".to_owned(), is_removable: false };
+
+    let w0 = walker::WalkerProperties {
+        name: "w0".to_owned(),
+        patrol: None,
+        max_life: None,
+        movement_speed: 1,
+        traversal: map::TraversalType::RoadOrEmpty,
+    };
+
+    let w1 = walker::WalkerProperties {
+        name: "w1".to_owned(),
+        patrol: None,
+        max_life: None,
+        movement_speed: 2,
+        traversal: map::TraversalType::RoadOrEmpty,
+    };
+
+    let w2 = walker::WalkerProperties {
+        name: "w2".to_owned(),
+        patrol: None,
+        max_life: None,
+        movement_speed: 1,
+        traversal: map::TraversalType::RoadOrEmpty,
+    };
+
+    let w0_state = walker::WalkerState {
+        current_life: None,
+        commodities: HashMap::new(),
+        destination: Some((2, 0)),
+        origin: None,
+        patrol: None,
+    };
+
+    let w1_state = walker::WalkerState {
+        current_life: None,
+        commodities: HashMap::new(),
+        destination: Some((2, 2)),
+        origin: None,
+        patrol: None,
+    };
+
+    let w2_state = walker::WalkerState {
+        current_life: None,
+        commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
+    };
+
+    let mut id_map = HashMap::new();
+
+    let _ = g.add_entity((1, 1), Entity::Doodad { props: d0 }).map(|r| id_map.insert((1, 1), r.0));
+
+    let _ = g.add_entity((0, 0), Entity::Walker {
+        props: w0,
+        state: w0_state,
+    }).map(|r| id_map.insert((0, 0), r.0));
+
+    let _ = g.add_entity((0, 2), Entity::Walker {
+        props: w1,
+        state: w1_state,
+    }).map(|r| id_map.insert((0, 2), r.0));
+
+    let _ = g.add_entity((2, 1), Entity::Walker {
+        props: w2,
+        state: w2_state,
+    }).map(|r| id_map.insert((2, 1), r.0));
+
+    (g, gc, e, id_map)
+}
+
+#[allow(dead_code)]
+pub fn grid_with_walker_production() -> (map::Grid, map::Cursor, exchange::CommodityExchange, HashMap<(usize, usize), Uuid>) {
+    let mut g = map::Grid::new(3);
+    let gc = map::Cursor::new(1, map::Direction::Right, (0, 0));
+    let e = exchange::CommodityExchange::new();
+
+    let s0 = structure::StructureProperties {
+        name: "s0".to_owned(),
+        size: structure::Size { width: 1, height: 1 },
+        max_employees: 5,
+        max_residents: 0,
+        cost: 1000,
+        desirability: (0, 0, 0, 0, 0, 0),
+        structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
+    };
+
+    let s1 = structure::StructureProperties {
+        name: "s1".to_owned(),
+        size: structure::Size { width: 1, height: 1 },
+        max_employees: 5,
+        max_residents: 0,
+        cost: 1000,
+        desirability: (0, 0, 0, 0, 0, 0),
+        structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
+    };
+
+    let mut s0_commodities = HashMap::new();
+    s0_commodities.insert("c0".to_owned(), 3);
+
+    let s0_state = structure::StructureState {
+        current_employees: 5,
+        current_residents: 0,
+        shift_progress: 0,
+        commodities: s0_commodities,
+        risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
+    };
+
+    let s1_state = structure::StructureState {
+        current_employees: 5,
+        current_residents: 0,
+        shift_progress: 0,
+        commodities: HashMap::new(),
+        risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
+    };
+
+    let mut id_map = HashMap::new();
+
+    let _ = g.add_entity((1, 1), Entity::Structure {
+        props: s0,
+        state: s0_state,
+        producer: Some(Box::new(TestWalkerProducer0 {})),
+    }).map(|r| id_map.insert((1, 1), r.0));
+
+    let _ = g.add_entity((0, 0), Entity::Structure {
+        props: s1,
+        state: s1_state,
+        producer: Some(Box::new(TestWalkerProducer0 {})),
+    }).map(|r| id_map.insert((0, 0), r.0));
+
+    let _ = g.add_entity((2, 1), Entity::Road).map(|r| id_map.insert((2, 1), r.0));
+    let _ = g.add_entity((1, 2), Entity::Road).map(|r| id_map.insert((1, 2), r.0));
+
+    (g, gc, e, id_map)
+}
+
+#[allow(dead_code)]
+pub fn registry_default() -> Registry {
+    let mut r = Registry::new();
+
+    let _ = r.register_effect("TestEffect0", Box::new(|_| Ok(Arc::new(TestEffect0 {}))));
+    let _ = r.register_effect("TestEffect1", Box::new(|_| Ok(Arc::new(TestEffect1 {}))));
+    let _ = r.register_effect("TestEffect2", Box::new(|_| Ok(Arc::new(TestEffect2 {}))));
+    let _ = r.register_effect("TestEffect3", Box::new(|_| Ok(Arc::new(TestEffect3 {}))));
+    let _ = r.register_effect("TestEffect4", Box::new(|_| Ok(Arc::new(TestEffect4 {}))));
+
+    let _ = r.register_producer("TestCommodityProducer0", Box::new(|_| Ok(Box::new(TestCommodityProducer0 {}))));
+    let _ = r.register_producer("TestCommodityProducer1", Box::new(|parameters| {
+        Ok(Box::new(TestCommodityProducer1 {
+            max_progress: registry::parameter(parameters, "max_progress")?,
+            current_progress: registry::optional_parameter(parameters, "current_progress")?.unwrap_or(0),
+        }))
+    }));
+    let _ = r.register_producer("TestCommodityProducer2", Box::new(|_| Ok(Box::new(TestCommodityProducer2 {}))));
+    let _ = r.register_producer("TestWalkerProducer0", Box::new(|_| Ok(Box::new(TestWalkerProducer0 {}))));
+
+    r
+}
+
 #[allow(dead_code)]
 pub fn grid_with_roads(
     row0: (Option<&Entity>, Option<&Entity>, Option<&Entity>),
@@ -464,11 +730,11 @@ impl Producer for TestCommodityProducer0 {
         }
     }
 
-    fn produce_walker(&mut self, _: &Entity) -> Option<walker::WalkerProperties> {
+    fn produce_walker(&mut self, _: &Entity) -> Option<WalkerStage> {
         None //no walker is produced
     }
 
-    fn clone_boxed(&self) -> Box<Producer> {
+    fn clone_boxed(&self) -> Box<dyn Producer> {
         Box::new(self.clone())
     }
 }
@@ -500,11 +766,22 @@ impl Producer for TestCommodityProducer1 {
         }
     }
 
-    fn produce_walker(&mut self, _: &Entity) -> Option<walker::WalkerProperties> {
+    fn produce_walker(&mut self, _: &Entity) -> Option<WalkerStage> {
         None //no walker is produced
     }
 
-    fn clone_boxed(&self) -> Box<Producer> {
+    fn type_name(&self) -> &str {
+        "TestCommodityProducer1"
+    }
+
+    fn parameters(&self) -> Parameters {
+        let mut parameters = Parameters::new();
+        parameters.insert("max_progress".to_owned(), self.max_progress.to_string());
+        parameters.insert("current_progress".to_owned(), self.current_progress.to_string());
+        parameters
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Producer> {
         Box::new(self.clone())
     }
 }
@@ -527,11 +804,83 @@ impl Producer for TestCommodityProducer2 {
         }
     }
 
-    fn produce_walker(&mut self, _: &Entity) -> Option<walker::WalkerProperties> {
+    fn produce_walker(&mut self, _: &Entity) -> Option<WalkerStage> {
         None //no walker is produced
     }
 
-    fn clone_boxed(&self) -> Box<Producer> {
+    fn type_name(&self) -> &str {
+        "TestCommodityProducer2"
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Producer> {
+        Box::new(self.clone())
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct TestCommodityProducer3 {}
+
+impl Producer for TestCommodityProducer3 {
+    fn produce_commodity(&mut self, entity: &Entity) -> Option<ProductionStage> {
+        match entity {
+            &Entity::Structure { .. } => {
+                Some(ProductionStage {
+                    commodity: Commodity { name: "c3".to_owned(), amount: 1 },
+                    required: Vec::new(),
+                    used: vec![Commodity { name: "c2".to_owned(), amount: 1 }],
+                })
+            }
+
+            _ => None //does nothing
+        }
+    }
+
+    fn produce_walker(&mut self, _: &Entity) -> Option<WalkerStage> {
+        None //no walker is produced
+    }
+
+    fn type_name(&self) -> &str {
+        "TestCommodityProducer3"
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Producer> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct TestWalkerProducer0 {}
+
+impl Producer for TestWalkerProducer0 {
+    fn produce_commodity(&mut self, _: &Entity) -> Option<ProductionStage> {
+        None //no commodity is produced
+    }
+
+    fn produce_walker(&mut self, entity: &Entity) -> Option<WalkerStage> {
+        match entity {
+            &Entity::Structure { .. } => {
+                Some(WalkerStage {
+                    walker: walker::WalkerProperties {
+                        name: "w0".to_owned(),
+                        patrol: None,
+                        max_life: Some(10),
+                        movement_speed: 1,
+                        traversal: map::TraversalType::RoadOnly,
+                    },
+                    carried: vec![Commodity { name: "c0".to_owned(), amount: 2 }],
+                })
+            }
+
+            _ => None //does nothing
+        }
+    }
+
+    fn type_name(&self) -> &str {
+        "TestWalkerProducer0"
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Producer> {
         Box::new(self.clone())
     }
 }
@@ -3,10 +3,11 @@ use owe::entities::Entity;
 use owe::entities::resource;
 use owe::entities::structure;
 use owe::entities::walker;
+use owe::map::TraversalType;
 use owe::production::Commodity;
 use owe::production::exchange::CommodityExchange;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[allow(dead_code)]
@@ -24,57 +25,77 @@ pub fn commodities_default() -> Vec<Commodity> {
 }
 
 #[allow(dead_code)]
-pub fn entities_default() -> Vec<(Uuid, Rc<Entity>)> {
+pub fn entities_default() -> Vec<(Uuid, Arc<Entity>)> {
     let s0 = structure::StructureProperties {
         name: "s0".to_owned(),
         size: structure::Size { width: 1, height: 1 },
         max_employees: 5,
+        max_residents: 0,
         cost: 1000,
         desirability: (0, 0, 0, 0, 0, 0),
         structure_type: structure::Type::Housing,
+        allows_uneven_ground: false,
     };
 
     let s1 = structure::StructureProperties {
         name: "s1".to_owned(),
         size: structure::Size { width: 3, height: 1 },
         max_employees: 2,
+        max_residents: 0,
         cost: 5000,
         desirability: (1, 2, 3, 4, 5, 6),
         structure_type: structure::Type::Industry,
+        allows_uneven_ground: false,
     };
 
     let s0_state = structure::StructureState {
         current_employees: 0,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 0, fire: 0 },
+        coverage: structure::Coverage::default(),
     };
 
     let s1_state = structure::StructureState {
         current_employees: 1,
+        current_residents: 0,
+        shift_progress: 0,
         commodities: HashMap::new(),
         risk: structure::Risk { damage: 10, fire: 3 },
+        coverage: structure::Coverage::default(),
     };
 
     let w0 = walker::WalkerProperties {
         name: "w0".to_owned(),
         patrol: None,
         max_life: None,
+        movement_speed: 1,
+        traversal: TraversalType::RoadOrEmpty,
     };
 
     let w1 = walker::WalkerProperties {
         name: "w1".to_owned(),
         patrol: Some(5),
         max_life: None,
+        movement_speed: 1,
+        traversal: TraversalType::RoadOrEmpty,
     };
 
     let w0_state = walker::WalkerState {
         current_life: None,
         commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
     };
 
     let w1_state = walker::WalkerState {
         current_life: None,
         commodities: HashMap::new(),
+        destination: None,
+        origin: None,
+        patrol: None,
     };
 
     let d0 = doodad::Doodad {
@@ -103,18 +124,18 @@ pub fn entities_default() -> Vec<(Uuid, Rc<Entity>)> {
 
     let r1_state = resource::ResourceState { current_amount: 5 };
 
-    let e0 = Rc::new(Entity::Structure { props: s0, state: s0_state, producer: None });
-    let e1 = Rc::new(Entity::Structure { props: s1, state: s1_state, producer: None });
-    let e2 = Rc::new(Entity::Walker { props: w0, state: w0_state });
-    let e3 = Rc::new(Entity::Walker { props: w1, state: w1_state });
-    let e4 = Rc::new(Entity::Road);
-    let e5 = Rc::new(Entity::Road);
-    let e6 = Rc::new(Entity::Roadblock);
-    let e7 = Rc::new(Entity::Roadblock);
-    let e8 = Rc::new(Entity::Doodad { props: d0 });
-    let e9 = Rc::new(Entity::Doodad { props: d1 });
-    let e10 = Rc::new(Entity::Resource { props: r0, state: r0_state, producer: None });
-    let e11 = Rc::new(Entity::Resource { props: r1, state: r1_state, producer: None });
+    let e0 = Arc::new(Entity::Structure { props: s0, state: s0_state, producer: None });
+    let e1 = Arc::new(Entity::Structure { props: s1, state: s1_state, producer: None });
+    let e2 = Arc::new(Entity::Walker { props: w0, state: w0_state });
+    let e3 = Arc::new(Entity::Walker { props: w1, state: w1_state });
+    let e4 = Arc::new(Entity::Road);
+    let e5 = Arc::new(Entity::Road);
+    let e6 = Arc::new(Entity::Roadblock);
+    let e7 = Arc::new(Entity::Roadblock);
+    let e8 = Arc::new(Entity::Doodad { props: d0 });
+    let e9 = Arc::new(Entity::Doodad { props: d1 });
+    let e10 = Arc::new(Entity::Resource { props: r0, state: r0_state, producer: None });
+    let e11 = Arc::new(Entity::Resource { props: r1, state: r1_state, producer: None });
 
     vec![
         (Uuid::new_v4(), e0),
@@ -1,8 +1,8 @@
 pub mod doodad {
-    use std::rc::Rc;
+    use std::sync::Arc;
     use owe::entities::Entity;
 
-    pub fn name(entity: Option<Rc<Entity>>) -> Option<String> {
+    pub fn name(entity: Option<Arc<Entity>>) -> Option<String> {
         entity.and_then(|entity| {
             match *entity {
                 Entity::Doodad { ref props } => {
@@ -15,11 +15,12 @@ pub mod doodad {
 }
 
 pub mod structure {
-    use std::rc::Rc;
+    use std::collections::HashMap;
+    use std::sync::Arc;
     use owe::entities::Entity;
     use owe::entities::structure;
 
-    pub fn employees(entity: Option<Rc<Entity>>) -> Option<u8> {
+    pub fn employees(entity: Option<Arc<Entity>>) -> Option<u8> {
         entity.and_then(|entity| {
             match *entity {
                 Entity::Structure { ref state, .. } => {
@@ -30,7 +31,40 @@ pub mod structure {
         })
     }
 
-    pub fn risk(entity: Option<Rc<Entity>>) -> Option<structure::Risk> {
+    pub fn residents(entity: Option<Arc<Entity>>) -> Option<u8> {
+        entity.and_then(|entity| {
+            match *entity {
+                Entity::Structure { ref state, .. } => {
+                    Some(state.current_residents)
+                }
+                _ => None
+            }
+        })
+    }
+
+    pub fn name(entity: Option<Arc<Entity>>) -> Option<String> {
+        entity.and_then(|entity| {
+            match *entity {
+                Entity::Structure { ref props, .. } => {
+                    Some(props.name.clone())
+                }
+                _ => None
+            }
+        })
+    }
+
+    pub fn commodities(entity: Option<Arc<Entity>>) -> Option<HashMap<String, u32>> {
+        entity.and_then(|entity| {
+            match *entity {
+                Entity::Structure { ref state, .. } => {
+                    Some(state.commodities.clone())
+                }
+                _ => None
+            }
+        })
+    }
+
+    pub fn risk(entity: Option<Arc<Entity>>) -> Option<structure::Risk> {
         entity.and_then(|entity| {
             match *entity {
                 Entity::Structure { ref state, .. } => {
@@ -43,10 +77,10 @@ pub mod structure {
 }
 
 pub mod resource {
-    use std::rc::Rc;
+    use std::sync::Arc;
     use owe::entities::Entity;
 
-    pub fn level(entity: Option<Rc<Entity>>) -> Option<u32> {
+    pub fn level(entity: Option<Arc<Entity>>) -> Option<u32> {
         entity.and_then(|entity| {
             match *entity {
                 Entity::Resource { ref state, .. } => {
@@ -59,10 +93,13 @@ pub mod resource {
 }
 
 pub mod walker {
-    use std::rc::Rc;
+    use std::collections::HashMap;
+    use std::sync::Arc;
     use owe::entities::Entity;
+    use owe::entities::walker::PatrolState;
+    use uuid::Uuid;
 
-    pub fn life(entity: Option<Rc<Entity>>) -> Option<Option<u16>> {
+    pub fn life(entity: Option<Arc<Entity>>) -> Option<Option<u16>> {
         entity.and_then(|entity| {
             match *entity {
                 Entity::Walker { ref state, .. } => {
@@ -72,4 +109,47 @@ pub mod walker {
             }
         })
     }
+
+    pub fn destination(entity: Option<Arc<Entity>>) -> Option<Option<(usize, usize)>> {
+        entity.and_then(|entity| {
+            match *entity {
+                Entity::Walker { ref state, .. } => {
+                    Some(state.destination)
+                }
+                _ => None
+            }
+        })
+    }
+
+    pub fn commodities(entity: Option<Arc<Entity>>) -> Option<HashMap<String, u32>> {
+        entity.and_then(|entity| {
+            match *entity {
+                Entity::Walker { ref state, .. } => {
+                    Some(state.commodities.clone())
+                }
+                _ => None
+            }
+        })
+    }
+    pub fn origin(entity: Option<Arc<Entity>>) -> Option<Option<Uuid>> {
+        entity.and_then(|entity| {
+            match *entity {
+                Entity::Walker { ref state, .. } => {
+                    Some(state.origin)
+                }
+                _ => None
+            }
+        })
+    }
+
+    pub fn patrol(entity: Option<Arc<Entity>>) -> Option<Option<PatrolState>> {
+        entity.and_then(|entity| {
+            match *entity {
+                Entity::Walker { ref state, .. } => {
+                    Some(state.patrol.clone())
+                }
+                _ => None
+            }
+        })
+    }
 }